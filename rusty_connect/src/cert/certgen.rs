@@ -1,11 +1,11 @@
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 use rand::rngs::OsRng;
 use rcgen::{CertificateParams, DistinguishedName};
 use rsa::RsaPrivateKey;
 use tracing::info;
 
-use super::{der_to_pem_cert, der_to_pem_key, CertPair};
+use super::CertPair;
 use rsa::pkcs8::EncodePrivateKey;
 
 pub async fn generate_cert(
@@ -8,18 +8,23 @@ use std::{
 use async_graphql::{Object, SimpleObject};
 use flume::{Receiver, Sender};
 use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
 use tracing::debug;
 
 use crate::{
     cert::CertPair,
     payloads::{IdentityPayloadBody, PairPayloadBody, Payload, PayloadType},
     plugins::{
-        share::DownloadProgress, Connected, Disconnected, PluginConfigs, PluginStates,
-        ReceivedPayload,
+        connectivity_report::ConnectivityReportState, share::DownloadProgress, Connected,
+        DeviceCapabilities, Disconnected, PluginConfigs, PluginStates, ReceivedPayload,
     },
+    utils::BROADCAST_CHANNEL_SIZE,
 };
 
+use self::profiles::{PluginDefaults, TRUSTED_PROFILE};
+
+pub mod profiles;
+
 pub struct DeviceManager {
     pub devices: HashMap<String, DeviceWithState>,
     pub sender: flume::Sender<PayloadType>,
@@ -27,8 +32,25 @@ pub struct DeviceManager {
     config_path: PathBuf,
     pub icons_path: PathBuf,
     pub downloads_path: PathBuf,
+    pub sms_path: PathBuf,
+    pub contacts_path: PathBuf,
+    pub clipboard_path: PathBuf,
+    pub battery_path: PathBuf,
     pub download_tasks: Arc<Mutex<HashMap<String, tokio::sync::watch::Receiver<DownloadProgress>>>>,
     pub certs: CertPair,
+    pub defaults: PluginDefaults,
+    defaults_path: PathBuf,
+    config_changed: broadcast::Sender<ConfigChanged>,
+}
+
+/// Emitted after plugin configs of a device were changed and saved.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ConfigChanged {
+    pub device_id: String,
+    /// Keys of the changed configs, as in `PluginConfigs` or extension ids.
+    pub plugins: Vec<String>,
+    /// Effective configs after the change.
+    pub configs: PluginConfigs,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -46,8 +68,17 @@ impl DeviceManager {
         let device_config = config_folder.join("devices");
         let icons_path = config_folder.join("icons_path");
         let downloads_path = config_folder.join("downloads");
+        let sms_path = config_folder.join("sms");
+        let contacts_path = config_folder.join("contacts");
+        let clipboard_path = config_folder.join("clipboard");
+        let battery_path = config_folder.join("battery");
+        let defaults_path = config_folder.join("plugin_defaults");
         tokio::fs::create_dir_all(&icons_path).await?;
         tokio::fs::create_dir_all(&downloads_path).await?;
+        tokio::fs::create_dir_all(&sms_path).await?;
+        tokio::fs::create_dir_all(&contacts_path).await?;
+        tokio::fs::create_dir_all(&clipboard_path).await?;
+        tokio::fs::create_dir_all(&battery_path).await?;
         let config = 'config: {
             if let Ok(data) = tokio::fs::read(&device_config).await {
                 if let Ok(config) = serde_json::from_slice(&data) {
@@ -56,47 +87,146 @@ impl DeviceManager {
             }
             DeviceConfig { devices: vec![] }
         };
+        // Devices paired before profiles existed keep what they had through the trusted profile.
+        let migrating = !tokio::fs::try_exists(&defaults_path).await?;
+        let defaults = PluginDefaults::load(&defaults_path).await?;
         let mut devices = HashMap::new();
-        for device in config.devices.into_iter() {
+        for mut device in config.devices.into_iter() {
+            if migrating && device.paired && device.profile.is_none() {
+                device.profile = Some(TRUSTED_PROFILE.to_string());
+            }
+            device.effective_configs =
+                defaults.resolve(device.profile.as_deref(), &device.plugin_configs);
             devices.insert(
                 device.id.clone(),
                 DeviceWithState {
                     device,
                     state: DeviceState::InActive,
+                    capabilities: DeviceCapabilities::default(),
                 },
             );
         }
-        Ok(Self {
+        let manager = Self {
             devices,
             sender,
             receiver,
             config_path: device_config.clone(),
             icons_path,
             downloads_path,
+            sms_path,
+            contacts_path,
+            clipboard_path,
+            battery_path,
             certs,
+            defaults,
+            defaults_path,
             download_tasks: Arc::new(Mutex::new(HashMap::new())),
-        })
+            config_changed: broadcast::channel(BROADCAST_CHANNEL_SIZE).0,
+        };
+        if migrating && !manager.devices.is_empty() {
+            manager.save().await?;
+        }
+        Ok(manager)
+    }
+
+    pub fn subscribe_config_changed(&self) -> broadcast::Receiver<ConfigChanged> {
+        self.config_changed.subscribe()
+    }
+
+    pub fn notify_config_changed(&self, device_id: &str, plugins: Vec<String>) {
+        let Some(device) = self.devices.get(device_id) else {
+            return;
+        };
+        // No receivers just means nobody is watching configs.
+        let _ = self.config_changed.send(ConfigChanged {
+            device_id: device_id.to_string(),
+            plugins,
+            configs: device.device.effective_configs.clone(),
+        });
+    }
+
+    /// Recomputes the device's effective configs after its overrides or profile changed.
+    pub fn resolve_configs(&mut self, device_id: &str) {
+        if let Some(device) = self.devices.get_mut(device_id) {
+            device.device.effective_configs = self.defaults.resolve(
+                device.device.profile.as_deref(),
+                &device.device.plugin_configs,
+            );
+        }
+    }
+
+    /// Applies `update` to the defaults, persists them and re-resolves every device.
+    pub async fn update_defaults<F>(&mut self, update: F) -> anyhow::Result<PluginDefaults>
+    where
+        F: FnOnce(&mut PluginDefaults) -> anyhow::Result<Vec<String>>,
+    {
+        let mut defaults = self.defaults.clone();
+        let changed = update(&mut defaults)?;
+        if let Some(profile) = &defaults.pairing_profile {
+            defaults.ensure_profile(profile)?;
+        }
+        defaults.save(&self.defaults_path).await?;
+        self.defaults = defaults;
+        let device_ids = self.devices.keys().cloned().collect::<Vec<_>>();
+        for device_id in device_ids {
+            self.resolve_configs(&device_id);
+            self.notify_config_changed(&device_id, changed.clone());
+        }
+        Ok(self.defaults.clone())
+    }
+
+    pub async fn set_profile(
+        &mut self,
+        device_id: &str,
+        profile: Option<String>,
+    ) -> anyhow::Result<DeviceWithState> {
+        if let Some(profile) = &profile {
+            self.defaults.ensure_profile(profile)?;
+        }
+        let device = self
+            .devices
+            .get_mut(device_id)
+            .ok_or(anyhow::anyhow!("No device with given id"))?;
+        device.device.profile = profile;
+        self.resolve_configs(device_id);
+        self.save().await?;
+        self.notify_config_changed(device_id, vec![]);
+        self.devices
+            .get(device_id)
+            .cloned()
+            .ok_or(anyhow::anyhow!("No device with given id"))
     }
 
+    /// Marks the device active, `capabilities` being the ones negotiated with `identity`.
     pub async fn connected_to(
         &mut self,
         address: SocketAddr,
         identity: IdentityPayloadBody,
+        capabilities: DeviceCapabilities,
     ) -> anyhow::Result<(Sender<PayloadType>, Receiver<Payload>, uuid::Uuid)> {
         let device_id = identity.device_id.clone();
-        let DeviceWithState { device: _, state } = self
+        let device = self
             .devices
             .entry(identity.device_id.clone())
             .or_insert(DeviceWithState {
                 device: Device {
                     paired: false,
                     id: identity.device_id.clone(),
-                    identity,
+                    identity: identity.clone(),
                     plugin_configs: PluginConfigs::default(),
+                    profile: None,
+                    effective_configs: self.defaults.global.clone(),
                     plugin_states: PluginStates::default(),
+                    extension_configs: HashMap::new(),
+                    extension_states: HashMap::new(),
+                    certificate: None,
                 },
                 state: DeviceState::InActive,
+                capabilities: DeviceCapabilities::default(),
             });
+        device.device.identity = identity;
+        device.capabilities = capabilities;
+        let state = &mut device.state;
 
         let (tx, rx) = flume::bounded(0);
         let id = uuid::Uuid::new_v4();
@@ -153,7 +283,23 @@ impl DeviceManager {
         }
     }
 
-    pub async fn pair(&mut self, id: &str, pair: bool) -> anyhow::Result<DeviceWithState> {
+    pub fn set_certificate(&mut self, device_id: &str, certificate: Option<Vec<u8>>) {
+        if let Some(device) = self.devices.get_mut(device_id) {
+            device.device.certificate = certificate;
+        }
+    }
+
+    /// Pairing gives the device `profile`, or the pairing profile if it has none yet.
+    pub async fn pair(
+        &mut self,
+        id: &str,
+        pair: bool,
+        profile: Option<String>,
+    ) -> anyhow::Result<DeviceWithState> {
+        if let Some(profile) = &profile {
+            self.defaults.ensure_profile(profile)?;
+        }
+        let pairing_profile = self.defaults.pairing_profile.clone();
         let device = self
             .devices
             .get_mut(id)
@@ -164,7 +310,19 @@ impl DeviceManager {
                 let value = serde_json::to_value(PairPayloadBody { pair })?;
                 sender.try_send(Payload::generate_new("kdeconnect.pair", value))?;
                 device.device.paired = pair;
-                let device = device.clone();
+                if pair {
+                    let profile =
+                        profile.or(pairing_profile.filter(|_| device.device.profile.is_none()));
+                    if profile.is_some() {
+                        device.device.profile = profile;
+                    }
+                }
+                self.resolve_configs(id);
+                let device = self
+                    .devices
+                    .get(id)
+                    .cloned()
+                    .ok_or(anyhow::anyhow!("No device with given id"))?;
                 self.save().await?;
 
                 Ok(device)
@@ -177,6 +335,8 @@ impl DeviceManager {
 pub struct DeviceWithState {
     pub device: Device,
     pub state: DeviceState,
+    /// Negotiated on the latest connection.
+    pub capabilities: DeviceCapabilities,
 }
 
 #[Object]
@@ -188,6 +348,30 @@ impl DeviceWithState {
     pub async fn is_connected(&self) -> bool {
         self.state.is_active()
     }
+
+    pub async fn capabilities(&self) -> &DeviceCapabilities {
+        &self.capabilities
+    }
+
+    /// Latest cellular signals reported by the device.
+    pub async fn connectivity(&self) -> &ConnectivityReportState {
+        &self.device.plugin_states.connectivityreport
+    }
+}
+
+impl DeviceWithState {
+    pub fn peer(&self) -> Option<Peer> {
+        match &self.state {
+            DeviceState::Active(_, address, sender) => Some(Peer {
+                device_id: self.device.id.clone(),
+                paired: self.device.paired,
+                address: *address,
+                certificate: self.device.certificate.clone(),
+                sender: sender.clone(),
+            }),
+            DeviceState::InActive => None,
+        }
+    }
 }
 
 #[derive(SimpleObject, Clone, Serialize, Deserialize)]
@@ -195,9 +379,56 @@ pub struct Device {
     pub id: String,
     pub identity: IdentityPayloadBody,
     pub paired: bool,
+    /// Overrides set for this device only.
     pub plugin_configs: PluginConfigs,
+    /// Profile from [`PluginDefaults::profiles`] layered below the overrides.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Configs plugins run with, see [`PluginDefaults::resolve`].
+    #[serde(skip)]
+    pub effective_configs: PluginConfigs,
     #[serde(skip)]
     pub plugin_states: PluginStates,
+    /// Configs of [`crate::plugins::extension::ExtensionPlugin`]s keyed by plugin id.
+    #[serde(default)]
+    #[graphql(skip)]
+    pub extension_configs: HashMap<String, serde_json::Value>,
+    #[serde(skip)]
+    #[graphql(skip)]
+    pub extension_states: HashMap<String, serde_json::Value>,
+    /// DER certificate presented on the current connection, used to pin payload transfers.
+    #[serde(skip)]
+    #[graphql(skip)]
+    pub certificate: Option<Vec<u8>>,
+}
+
+/// Connection details of an active device, handed to plugins when parsing its payloads.
+#[derive(Debug, Clone)]
+pub struct Peer {
+    pub device_id: String,
+    pub paired: bool,
+    pub address: SocketAddr,
+    pub certificate: Option<Vec<u8>>,
+    pub sender: Sender<Payload>,
+}
+
+impl Peer {
+    /// Sends a packet straight back to this device, bypassing plugin send checks.
+    pub async fn send(&self, payload_type: &str, body: impl Serialize) -> anyhow::Result<()> {
+        let value = serde_json::to_value(body)?;
+        self.sender
+            .send_async(Payload::generate_new(payload_type, value))
+            .await?;
+        Ok(())
+    }
+
+    /// [`Peer::send`] for blocking work.
+    pub fn send_blocking(&self, payload_type: &str, body: impl Serialize) -> anyhow::Result<()> {
+        let value = serde_json::to_value(body)?;
+        self.sender
+            .send(Payload::generate_new(payload_type, value))?;
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
@@ -0,0 +1,104 @@
+use std::{collections::BTreeMap, path::Path};
+
+use async_graphql::{ComplexObject, SimpleObject};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::plugins::PluginConfigs;
+
+/// Profile offered on first start, turning input injection, commands and file access back on.
+pub const TRUSTED_PROFILE: &str = "trusted";
+
+/// Plugin configs layered below each device's own overrides: global, then the device's profile.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, SimpleObject)]
+#[graphql(complex)]
+#[serde(default)]
+pub struct PluginDefaults {
+    /// Applies to every device.
+    pub global: PluginConfigs,
+    #[graphql(skip)]
+    pub profiles: BTreeMap<String, PluginConfigs>,
+    /// Profile given to devices paired without choosing one.
+    pub pairing_profile: Option<String>,
+}
+
+#[derive(SimpleObject)]
+pub struct Profile {
+    pub name: String,
+    pub configs: PluginConfigs,
+}
+
+#[ComplexObject]
+impl PluginDefaults {
+    async fn profiles(&self) -> Vec<Profile> {
+        self.profiles
+            .iter()
+            .map(|(name, configs)| Profile {
+                name: name.clone(),
+                configs: configs.clone(),
+            })
+            .collect()
+    }
+}
+
+impl PluginDefaults {
+    pub async fn load(path: &Path) -> anyhow::Result<Self> {
+        match tokio::fs::read(path).await {
+            Ok(data) => match serde_json::from_slice(&data) {
+                Ok(defaults) => Ok(defaults),
+                Err(err) => {
+                    let corrupt = path.with_extension("corrupt");
+                    warn!("Cannot parse plugin defaults, moved to {corrupt:?} {err:?}");
+                    tokio::fs::rename(path, &corrupt).await?;
+                    let defaults = Self::initial()?;
+                    defaults.save(path).await?;
+                    Ok(defaults)
+                }
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                let defaults = Self::initial()?;
+                defaults.save(path).await?;
+                Ok(defaults)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub async fn save(&self, path: &Path) -> anyhow::Result<()> {
+        tokio::fs::write(path, serde_json::to_vec_pretty(self)?).await?;
+        Ok(())
+    }
+
+    /// New devices start with input injection, commands and file access off.
+    fn initial() -> anyhow::Result<Self> {
+        let sensitive_plugins = |enabled: bool| {
+            serde_json::from_value::<PluginConfigs>(serde_json::json!({
+                "mousepad": { "enabled": enabled },
+                "runcommand": { "enabled": enabled },
+                "sftp": { "enabled": enabled },
+                "share": { "enabled": enabled },
+            }))
+        };
+        Ok(Self {
+            global: sensitive_plugins(false)?,
+            profiles: BTreeMap::from([(TRUSTED_PROFILE.to_string(), sensitive_plugins(true)?)]),
+            ..Default::default()
+        })
+    }
+
+    pub fn ensure_profile(&self, profile: &str) -> anyhow::Result<()> {
+        if !self.profiles.contains_key(profile) {
+            return Err(anyhow::anyhow!("No profile named {profile:?}"));
+        }
+        Ok(())
+    }
+
+    /// Effective configs of a device: its overrides, then its profile, then the global layer.
+    pub fn resolve(&self, profile: Option<&str>, overrides: &PluginConfigs) -> PluginConfigs {
+        let layered = match profile.and_then(|profile| self.profiles.get(profile)) {
+            Some(profile) => profile.or(&self.global),
+            None => self.global.clone(),
+        };
+        overrides.or(&layered)
+    }
+}
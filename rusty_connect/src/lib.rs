@@ -8,10 +8,10 @@ use devices::DeviceManager;
 
 use mdns_sd::ServiceInfo;
 use payloads::PayloadType;
+use plugins::extension::ExtensionPlugin;
 use plugins::{PluginManager, ReceivedPayload};
 use schema::subscription::Subscription;
 use schema::{mutation::Mutation, query::Query, GQSchema};
-use socket2::TcpKeepalive;
 use tokio_native_tls::native_tls::{self, Certificate, Identity, TlsAcceptor};
 use tokio_native_tls::TlsStream;
 
@@ -30,6 +30,7 @@ use crate::payloads::{IdentityPayloadBody, Payload};
 pub mod cert;
 pub mod devices;
 pub mod network;
+pub mod payload_transfer;
 pub mod payloads;
 pub mod plugins;
 pub mod schema;
@@ -64,7 +65,7 @@ impl RustyConnect {
         };
 
         let (tx, rx) = flume::bounded(0);
-        let device_manager =
+        let mut device_manager =
             DeviceManager::load_or_create(data_folder, tx, rx, certs.clone()).await?;
         let plugin_manager = PluginManager::new(
             id.to_string(),
@@ -72,6 +73,7 @@ impl RustyConnect {
             device_type.to_string(),
             &device_manager,
         );
+        plugin_manager.restore_states(&mut device_manager);
         Ok(Self {
             id: id.to_string(),
             name: name.to_string(),
@@ -83,9 +85,28 @@ impl RustyConnect {
         })
     }
 
+    /// Registers a plugin defined outside this crate; call before [`RustyConnect::run`] so its
+    /// capabilities are part of the identity sent to peers.
+    pub fn with_plugin(self, plugin: impl ExtensionPlugin) -> anyhow::Result<Self> {
+        self.plugin_manager.extensions.register(Arc::new(plugin))?;
+        Ok(self)
+    }
+
     pub async fn run(&mut self, gql_port: u32) -> anyhow::Result<()> {
         debug!("Starting RustyConnect on port 1716 and GQL on {gql_port}");
         let certs = (self.cert.clone(), self.key.clone());
+        self.plugin_manager
+            .clipboard
+            .start_sync(self.device_manager.clone());
+        self.plugin_manager
+            .batttery
+            .start_reporting(self.device_manager.clone());
+        self.plugin_manager
+            .ping
+            .start_probing(self.device_manager.clone());
+        self.plugin_manager
+            .systemvolume
+            .start_watching(self.device_manager.clone());
         let tcp_listener = TcpListener::bind("0.0.0.0:1716").await?;
         let tcp_fut = {
             // let certs = certs.clone();
@@ -150,7 +171,7 @@ impl RustyConnect {
                                     );
                                     match identity {
                                         Ok(identity) => {
-                                            let cert = match Certificate::from_pem(&certs.0) {
+                                            let _cert = match Certificate::from_pem(&certs.0) {
                                                 Ok(cert) => cert,
                                                 Err(err) => {
                                                     warn!("Cannot generate cert {err:?}");
@@ -185,11 +206,17 @@ impl RustyConnect {
                                             {
                                                 Ok(tls_stream) => {
                                                     let device_id = identity.device_id.clone();
+                                                    let capabilities =
+                                                        plugin_manager.capabilities_for(&identity);
                                                     let device = {
                                                         let mut device_manager =
                                                             device_manager.write().await;
                                                         device_manager
-                                                            .connected_to(address, identity)
+                                                            .connected_to(
+                                                                address,
+                                                                identity,
+                                                                capabilities,
+                                                            )
                                                             .await
                                                     };
                                                     match device {
@@ -260,6 +287,7 @@ impl RustyConnect {
     async fn run_gql(&self, port: u32) -> anyhow::Result<()> {
         let schema = GQSchema::build(
             Query {
+                plugin_manager: self.plugin_manager.clone(),
                 device_manager: self.device_manager.clone(),
             },
             Mutation {
@@ -394,9 +422,12 @@ impl RustyConnect {
                                 .get_identity_payload_body(Some(kde_port));
                             let certs = (self.cert.clone(), self.key.clone());
                             let device_id = identity.device_id.clone();
+                            let capabilities = self.plugin_manager.capabilities_for(&identity);
                             let device = {
                                 let mut device_manager = self.device_manager.write().await;
-                                device_manager.connected_to(address, identity).await
+                                device_manager
+                                    .connected_to(address, identity, capabilities)
+                                    .await
                             };
                             match device {
                                 Ok((tx, rx, connection_id)) => {
@@ -468,6 +499,21 @@ impl RustyConnect {
     ) -> anyhow::Result<()> {
         debug!("Listening TLS for id {device_id:?}");
 
+        let certificate = match tls_stream.get_ref().peer_certificate() {
+            Ok(Some(certificate)) => certificate.to_der().ok(),
+            Ok(None) => None,
+            Err(err) => {
+                warn!("Cannot read peer certificate {err:?}");
+                None
+            }
+        };
+        {
+            let mut device_manager = device_manager.write().await;
+            device_manager.set_certificate(&device_id, certificate);
+        }
+        // Hooks only run once the certificate is pinned, so they see the verified device.
+        Self::device_connected(&plugin_manager, &device_manager, &device_id).await;
+
         let (mut read_stream, mut write_stream) = tokio::io::split(tls_stream);
 
         let out_sender = async move {
@@ -476,7 +522,7 @@ impl RustyConnect {
                 match data {
                     Ok(data) => {
                         write_stream.write_all(&data).await.unwrap();
-                        write_stream.write_all(&[b'\n']).await.unwrap();
+                        write_stream.write_all(b"\n").await.unwrap();
                     }
                     Err(er) => warn!("Cannot convert payload to json {er:?}"),
                 }
@@ -515,12 +561,13 @@ impl RustyConnect {
                                 )
                                 .await
                                 {
-                                    Ok(payload) => {
+                                    Ok(Some(payload)) => {
                                         match tx.try_send((device_id.to_string(), payload)) {
                                             Err(err) => warn!("Nothing to handle payload {err:?}"),
                                             Ok(_) => debug!("Sent payload to channel"),
                                         }
                                     }
+                                    Ok(None) => debug!("Payload only updated the state"),
                                     Err(e) => warn!("Error processing payload {e:#?}"),
                                 }
                             });
@@ -540,6 +587,7 @@ impl RustyConnect {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn connect_to(
         address: SocketAddr,
         port: u16,
@@ -559,7 +607,7 @@ impl RustyConnect {
         let identity_payload = Payload::generate_new("kdeconnect.identity", value);
         let idenity_bytes = serde_json::to_vec(&identity_payload)?;
         let _sent = stream.write(&idenity_bytes).await?;
-        stream.write_all(&[b'\n']).await?;
+        stream.write_all(b"\n").await?;
         stream.flush().await?;
 
         // let cert = Certificate::from_pem(&certs.0)?;
@@ -605,12 +653,26 @@ impl RustyConnect {
         Ok(())
     }
 
+    /// Runs the plugins' connection hooks for a device that just connected.
+    async fn device_connected(
+        plugin_manager: &PluginManager,
+        device_manager: &RwLock<DeviceManager>,
+        device_id: &str,
+    ) {
+        let device = device_manager.read().await.devices.get(device_id).cloned();
+        if let Some(device) = device {
+            for action in plugin_manager.connected(&device) {
+                action.spawn(&plugin_manager.blocking_queues, device_id);
+            }
+        }
+    }
+
     async fn process_payload(
         device_id: &str,
         payload: Payload,
         plugin_manager: Arc<PluginManager>,
         device_manager: Arc<RwLock<DeviceManager>>,
-    ) -> anyhow::Result<ReceivedPayload> {
+    ) -> anyhow::Result<Option<ReceivedPayload>> {
         let device = {
             let devices = device_manager.read().await;
             devices
@@ -622,13 +684,16 @@ impl RustyConnect {
         debug!("parsing payload");
         let payload = plugin_manager.parse_payload(payload, Some(&device)).await?;
         debug!("parsed payload");
-        {
+        let action = {
             let mut dm = device_manager.write().await;
-            if let Some(device) = dm.devices.get_mut(device_id) {
-                plugin_manager.update_state(&payload, device);
-            }
+            dm.devices
+                .get_mut(device_id)
+                .and_then(|device| plugin_manager.update_state(&payload, device))
+        };
+        if let Some(action) = action {
+            action.spawn(&plugin_manager.blocking_queues, device_id);
         }
         debug!("emitting payload");
-        Ok(payload)
+        Ok(plugin_manager.emits(&payload).then_some(payload))
     }
 }
@@ -0,0 +1,375 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use tokio_native_tls::{
+    native_tls::{self, Identity},
+    TlsStream,
+};
+use tracing::{debug, warn};
+
+use crate::{cert::CertPair, devices::Peer};
+
+/// Ports KDE Connect peers use to offer payloads.
+pub const PAYLOAD_PORT_RANGE: std::ops::RangeInclusive<u16> = 1739..=1764;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TransferProgress {
+    pub transferred: u64,
+    pub total: u64,
+}
+
+pub type ProgressCallback = Arc<dyn Fn(TransferProgress) + Send + Sync>;
+
+/// Cloneable handle used to abort a running transfer.
+#[derive(Clone)]
+pub struct CancellationToken {
+    sender: Arc<tokio::sync::watch::Sender<bool>>,
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        let (sender, _) = tokio::sync::watch::channel(false);
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    pub async fn cancelled(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender lives as long as self, so this only returns once cancelled.
+        let _ = receiver.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+#[derive(Clone)]
+pub struct TransferOptions {
+    /// Timeout for connecting/accepting and for each individual read or write.
+    pub timeout: Duration,
+    /// Transfers announcing more bytes than this are refused.
+    pub max_size: Option<u64>,
+    /// DER encoded certificate the peer must present, if known.
+    pub peer_certificate: Option<Vec<u8>>,
+    /// Only connections from this address may fetch an upload.
+    pub peer_address: Option<IpAddr>,
+    pub progress: Option<ProgressCallback>,
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            max_size: None,
+            peer_certificate: None,
+            peer_address: None,
+            progress: None,
+            cancellation: None,
+        }
+    }
+}
+
+impl TransferOptions {
+    /// Options pinned to the certificate and address of the given peer.
+    pub fn for_peer(peer: &Peer) -> Self {
+        Self {
+            peer_certificate: peer.certificate.clone(),
+            peer_address: Some(peer.address.ip()),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    pub fn with_progress(
+        mut self,
+        progress: impl Fn(TransferProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    fn check_size(&self, size: u64) -> anyhow::Result<()> {
+        if let Some(max_size) = self.max_size {
+            if size > max_size {
+                return Err(anyhow::anyhow!(
+                    "Payload of {size} bytes exceeds limit of {max_size} bytes"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn report(&self, transferred: u64, total: u64) {
+        if let Some(progress) = &self.progress {
+            progress(TransferProgress { transferred, total });
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .map(|c| c.is_cancelled())
+            .unwrap_or(false)
+    }
+
+    async fn with_deadline<T>(
+        &self,
+        fut: impl std::future::Future<Output = std::io::Result<T>>,
+    ) -> anyhow::Result<T> {
+        let cancelled = async {
+            match &self.cancellation {
+                Some(cancellation) => cancellation.cancelled().await,
+                None => futures::future::pending().await,
+            }
+        };
+        tokio::select! {
+            result = tokio::time::timeout(self.timeout, fut) => {
+                Ok(result.map_err(|_| anyhow::anyhow!("Transfer timed out"))??)
+            }
+            _ = cancelled => Err(anyhow::anyhow!("Transfer cancelled")),
+        }
+    }
+}
+
+/// Downloads a payload offered by `peer` on `port` into `path`.
+///
+/// Data is written to a `.part` file next to `path` and only renamed once all
+/// `size` bytes were received, so a failed transfer never leaves a truncated file.
+pub async fn download_to_file(
+    peer: SocketAddr,
+    port: u16,
+    size: u64,
+    path: &Path,
+    certs: &CertPair,
+    options: &TransferOptions,
+) -> anyhow::Result<u64> {
+    options.check_size(size)?;
+    let mut tls_stream = connect(peer, port, certs, options).await?;
+
+    let part_path = part_path(path);
+    let mut file = tokio::fs::File::create(&part_path).await?;
+    let result = copy_exact(&mut tls_stream, &mut file, size, options).await;
+    let result = match result {
+        Ok(read) => file
+            .flush()
+            .await
+            .map(|_| read)
+            .map_err(anyhow::Error::from),
+        Err(err) => Err(err),
+    };
+    drop(file);
+    match result {
+        Ok(read) => {
+            tokio::fs::rename(&part_path, path).await?;
+            debug!("Downloaded {read} bytes to {path:?}");
+            Ok(read)
+        }
+        Err(err) => {
+            if let Err(remove_err) = tokio::fs::remove_file(&part_path).await {
+                warn!("Cannot remove partial download {remove_err:?}");
+            }
+            Err(err)
+        }
+    }
+}
+
+/// Downloads a payload offered by `peer` on `port` into memory.
+pub async fn download_to_memory(
+    peer: SocketAddr,
+    port: u16,
+    size: u64,
+    certs: &CertPair,
+    options: &TransferOptions,
+) -> anyhow::Result<Vec<u8>> {
+    options.check_size(size)?;
+    let mut tls_stream = connect(peer, port, certs, options).await?;
+    let mut data = Vec::with_capacity(size.min(BUFFER_SIZE as u64 * 16) as usize);
+    copy_exact(&mut tls_stream, &mut data, size, options).await?;
+    Ok(data)
+}
+
+/// Offers `size` bytes from `reader` to a single peer.
+///
+/// Connections from other addresses than [`TransferOptions::peer_address`] are closed
+/// before the TLS handshake.
+///
+/// Returns the port to advertise in the packet's `payloadTransferInfo` and a task
+/// resolving to the number of bytes sent once the peer has fetched the payload.
+pub async fn upload_from_reader<R>(
+    mut reader: R,
+    size: u64,
+    certs: &CertPair,
+    options: TransferOptions,
+) -> anyhow::Result<(u16, JoinHandle<anyhow::Result<u64>>)>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    options.check_size(size)?;
+    let (listener, port) = bind_payload_listener().await?;
+    let identity = Identity::from_pkcs8(&certs.0, &certs.1)?;
+    let acceptor = tokio_native_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity)?);
+
+    let task = tokio::spawn(async move {
+        let (stream, address) = loop {
+            let (stream, address) = options.with_deadline(listener.accept()).await?;
+            if options
+                .peer_address
+                .is_none_or(|expected| expected == address.ip())
+            {
+                break (stream, address);
+            }
+            warn!("Refusing payload request from {address:?}");
+        };
+        debug!("Payload requested by {address:?}");
+        let stream = crate::network::set_tcp_timeouts(stream)?;
+        let mut tls_stream = tokio::time::timeout(options.timeout, acceptor.accept(stream))
+            .await
+            .map_err(|_| anyhow::anyhow!("TLS handshake timed out"))??;
+        // native-tls acceptors never request a client certificate, so the
+        // downloading side cannot be pinned here.
+        let sent = copy_exact(&mut reader, &mut tls_stream, size, &options).await?;
+        options.with_deadline(tls_stream.shutdown()).await?;
+        Ok(sent)
+    });
+    Ok((port, task))
+}
+
+async fn bind_payload_listener() -> anyhow::Result<(TcpListener, u16)> {
+    for port in PAYLOAD_PORT_RANGE {
+        if let Ok(listener) = TcpListener::bind(("0.0.0.0", port)).await {
+            return Ok((listener, port));
+        }
+    }
+    Err(anyhow::anyhow!(
+        "No free port available for payload transfer"
+    ))
+}
+
+async fn connect(
+    peer: SocketAddr,
+    port: u16,
+    certs: &CertPair,
+    options: &TransferOptions,
+) -> anyhow::Result<TlsStream<TcpStream>> {
+    debug!("TCP Stream initialising");
+    let stream = options
+        .with_deadline(TcpStream::connect(SocketAddr::new(peer.ip(), port)))
+        .await?;
+
+    debug!("TLS Config initializing");
+    let key = Identity::from_pkcs8(&certs.0, &certs.1)?;
+    let connector = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(true)
+        .identity(key)
+        .build()?;
+    let tls_connector = tokio_native_tls::TlsConnector::from(connector);
+
+    let server_name = peer.to_string();
+    debug!("Upgrading to TLS Stream to server name: {server_name:?}");
+    let tls_stream =
+        tokio::time::timeout(options.timeout, tls_connector.connect(&server_name, stream))
+            .await
+            .map_err(|_| anyhow::anyhow!("TLS handshake timed out"))??;
+    verify_peer(&tls_stream, options)?;
+    Ok(tls_stream)
+}
+
+fn verify_peer<S>(tls_stream: &TlsStream<S>, options: &TransferOptions) -> anyhow::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let Some(expected) = &options.peer_certificate else {
+        return Ok(());
+    };
+    let presented = tls_stream
+        .get_ref()
+        .peer_certificate()?
+        .map(|cert| cert.to_der())
+        .transpose()?;
+    match presented {
+        Some(presented) if &presented == expected => Ok(()),
+        Some(_) => Err(anyhow::anyhow!(
+            "Peer certificate does not match paired device"
+        )),
+        None => Err(anyhow::anyhow!("Peer did not present a certificate")),
+    }
+}
+
+async fn copy_exact<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    size: u64,
+    options: &TransferOptions,
+) -> anyhow::Result<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut transferred = 0u64;
+    options.report(transferred, size);
+    while transferred < size {
+        if options.is_cancelled() {
+            return Err(anyhow::anyhow!("Transfer cancelled"));
+        }
+        let to_read = std::cmp::min(size - transferred, BUFFER_SIZE as u64) as usize;
+        let read = options
+            .with_deadline(reader.read(&mut buffer[..to_read]))
+            .await?;
+        if read == 0 {
+            return Err(anyhow::anyhow!(
+                "Stream ended after {transferred} of {size} bytes"
+            ));
+        }
+        options
+            .with_deadline(writer.write_all(&buffer[..read]))
+            .await?;
+        transferred += read as u64;
+        options.report(transferred, size);
+    }
+    Ok(transferred)
+}
+
+fn part_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".part");
+    path.with_file_name(file_name)
+}
@@ -34,6 +34,13 @@ impl Payload {
             payload_transfer_info: None,
         }
     }
+
+    /// Advertises a payload of `size` bytes offered on `port`, see [`crate::payload_transfer::upload_from_reader`].
+    pub fn with_payload(mut self, size: u64, port: u16) -> Self {
+        self.payload_size = Some(size);
+        self.payload_transfer_info = Some(PayloadTransferInfo { port });
+        self
+    }
 }
 
 #[Object]
@@ -0,0 +1,54 @@
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, SimpleObject, Serialize, Deserialize)]
+pub struct BatterySample {
+    /// Milliseconds since epoch.
+    pub timestamp: u64,
+    pub current_charge: f32,
+    pub is_charging: bool,
+}
+
+/// Time left until the battery is full or empty, extrapolated from recent samples.
+#[derive(Debug, Clone, PartialEq, SimpleObject)]
+pub struct BatteryEstimate {
+    pub is_charging: bool,
+    /// Charge change per hour, positive while charging.
+    pub rate_per_hour: f32,
+    pub seconds_to_full: Option<u64>,
+    pub seconds_to_empty: Option<u64>,
+}
+
+/// Adds `sample` as the newest one, keeping at most `limit` samples.
+pub fn record_sample(history: &mut Vec<BatterySample>, sample: BatterySample, limit: usize) {
+    history.insert(0, sample);
+    history.truncate(limit);
+}
+
+/// Estimates from the samples since charging last started or stopped; `history` is newest first.
+///
+/// Returns `None` until the charge has moved in the expected direction.
+pub fn estimate(history: &[BatterySample]) -> Option<BatteryEstimate> {
+    let newest = history.first()?;
+    let oldest = history
+        .iter()
+        .take_while(|sample| sample.is_charging == newest.is_charging)
+        .last()?;
+    let hours = newest.timestamp.checked_sub(oldest.timestamp)? as f32 / 3_600_000.0;
+    if hours <= 0.0 {
+        return None;
+    }
+    let rate_per_hour = (newest.current_charge - oldest.current_charge) / hours;
+    let seconds = |charge: f32| (charge / rate_per_hour.abs() * 3600.0).round() as u64;
+    let (seconds_to_full, seconds_to_empty) = match newest.is_charging {
+        true if rate_per_hour > 0.0 => (Some(seconds(100.0 - newest.current_charge)), None),
+        false if rate_per_hour < 0.0 => (None, Some(seconds(newest.current_charge))),
+        _ => return None,
+    };
+    Some(BatteryEstimate {
+        is_charging: newest.is_charging,
+        rate_per_hour,
+        seconds_to_full,
+        seconds_to_empty,
+    })
+}
@@ -0,0 +1,491 @@
+use std::{
+    sync::{Arc, RwLock as StdRwLock},
+    time::Duration,
+};
+
+use async_graphql::{Context, Enum, InputObject, Object, SimpleObject};
+use serde::{Deserialize, Deserializer, Serialize};
+use tokio::sync::{broadcast, RwLock};
+use tracing::warn;
+
+use crate::{
+    devices::{DeviceManager, DeviceWithState, Peer},
+    plugins::PluginExt,
+    utils::{get_timestamp, history::HistoryStore, BROADCAST_CHANNEL_SIZE},
+};
+
+pub use self::history::{estimate, BatteryEstimate, BatterySample};
+pub use self::power::{MemoryPowerSource, PowerSource, PowerStatus, SysfsPowerSource};
+
+use self::history::record_sample;
+
+use super::{Plugin, PluginAction, Reply};
+
+pub mod history;
+pub mod power;
+
+/// How often the local battery is read; devices only get changed statuses.
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The history is written at most this often, so a crash loses at most this much of it.
+const HISTORY_SAVE_INTERVAL_MS: u64 = 5 * 60_000;
+
+const DEFAULT_LOW_THRESHOLD: u32 = 15;
+const DEFAULT_HISTORY_SIZE: u32 = 1000;
+const MAX_HISTORY_SIZE: u32 = 10_000;
+
+#[derive(Clone)]
+pub struct Batttery {
+    low_sender: broadcast::Sender<BatteryLow>,
+    history: Arc<HistoryStore<BatterySample>>,
+    power: Arc<StdRwLock<Option<Arc<dyn PowerSource>>>>,
+}
+
+#[Object]
+impl Batttery {
+    pub async fn send_batery<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        current_charge: f32,
+        is_charging: bool,
+        device_id: Option<String>,
+    ) -> anyhow::Result<&str> {
+        let battery_payload = BatteryPayload {
+            is_charging,
+            current_charge,
+            threshold_event: BatteryEventType::None,
+            received: None,
+        };
+        // let payload =
+        self.send_payload(
+            context,
+            device_id.as_deref(),
+            "kdeconnect.battery",
+            battery_payload,
+        )
+        .await?;
+        Ok("success")
+    }
+
+    /// Charge samples received from the device, newest first.
+    pub async fn battery_history<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<BatterySample>> {
+        let mut history = self.get_state(context, &device_id).await?.history;
+        if let Some(limit) = limit {
+            history.truncate(limit);
+        }
+        Ok(history)
+    }
+
+    pub async fn battery_estimate<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<Option<BatteryEstimate>> {
+        Ok(estimate(
+            &self.get_state(context, &device_id).await?.history,
+        ))
+    }
+}
+
+impl Batttery {
+    /// Receives an event whenever a device reports low battery or drops below its threshold.
+    pub fn subscribe_low(&self) -> broadcast::Receiver<BatteryLow> {
+        self.low_sender.subscribe()
+    }
+
+    /// Replaces where the local battery is read from, `None` stops reporting it.
+    pub fn set_power_source(&self, source: Option<Arc<dyn PowerSource>>) {
+        *self.power.write().expect("power source poisoned") = source;
+    }
+
+    /// Status of the local battery, `None` without a source or battery.
+    async fn local_payload(&self) -> Option<BatteryPayload> {
+        let source = self.power.read().expect("power source poisoned").clone()?;
+        match tokio::task::spawn_blocking(move || source.status()).await {
+            Ok(Ok(status)) => status.map(BatteryPayload::from),
+            Ok(Err(err)) => {
+                warn!("Cannot read local battery {err:?}");
+                None
+            }
+            Err(err) => {
+                warn!("Battery task failed {err:?}");
+                None
+            }
+        }
+    }
+
+    /// Sends the local battery to `peer` regardless of what it was sent before.
+    async fn send_local(&self, peer: &Peer) {
+        let Some(payload) = self.local_payload().await else {
+            return;
+        };
+        if let Err(err) = peer.send("kdeconnect.battery", payload).await {
+            warn!("Cannot send battery {err:?}");
+        }
+    }
+
+    /// Reads the local battery periodically and sends changes to paired devices.
+    pub fn start_reporting(&self, device_manager: Arc<RwLock<DeviceManager>>) {
+        let plugin = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(REPORT_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(payload) = plugin.local_payload().await else {
+                    continue;
+                };
+                if let Err(err) = plugin
+                    .send_payload_with(&device_manager, None, "kdeconnect.battery", payload)
+                    .await
+                {
+                    warn!("Cannot report battery {err:?}");
+                }
+            }
+        });
+    }
+}
+
+/// Whether the local battery goes out to the device.
+fn sends(config: &Option<BatteryConfig>) -> bool {
+    config.as_ref().is_none_or(|config| config.send_enabled)
+}
+
+/// Sysfs only exists on Linux, elsewhere the app reports through `sendBatery`.
+fn default_power_source() -> Option<Arc<dyn PowerSource>> {
+    cfg!(target_os = "linux").then(|| Arc::new(SysfsPowerSource::default()) as Arc<dyn PowerSource>)
+}
+
+impl Plugin for Batttery {
+    type PluginPayload = BatteryPayload;
+    type PluginConfig = BatteryConfig;
+    type PluginState = BatteryState;
+
+    fn init(device_mangager: &crate::devices::DeviceManager) -> Self {
+        Self {
+            low_sender: broadcast::channel(BROADCAST_CHANNEL_SIZE).0,
+            history: Arc::new(HistoryStore::new(device_mangager.battery_path.clone())),
+            power: Arc::new(StdRwLock::new(default_power_source())),
+        }
+    }
+
+    fn restore_state(
+        &self,
+        device_id: &str,
+        config: &Option<Self::PluginConfig>,
+        state: &mut Self::PluginState,
+    ) {
+        let limit = config.clone().unwrap_or_default().history_size as usize;
+        match self.history.load(device_id) {
+            Ok(mut history) => {
+                history.truncate(limit);
+                state.history = history;
+            }
+            Err(err) => warn!("Cannot load battery history {err:?}"),
+        }
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.battery".to_string(),
+            "kdeconnect.battery.request".to_string(),
+        ]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec!["kdeconnect.battery".to_string()]
+    }
+
+    async fn parse_payload(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        config: &Option<Self::PluginConfig>,
+    ) -> Option<Self::PluginPayload> {
+        if payload.r#type == "kdeconnect.battery" {
+            let payload = serde_json::from_value::<Self::PluginPayload>(payload.body.clone());
+            if let Ok(mut payload) = payload {
+                payload.received = Some(Received {
+                    device_id: peer.device_id.clone(),
+                    config: config.clone().unwrap_or_default(),
+                    timestamp: get_timestamp() as u64,
+                });
+                return Some(payload);
+            }
+        }
+        None
+    }
+
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        state.last_status = Some(payload.clone());
+        if let Some(received) = &payload.received {
+            if history_save_due(received, state) {
+                state.history_saved_at = Some(received.timestamp);
+            }
+            record_sample(
+                &mut state.history,
+                payload.sample(received.timestamp),
+                received.config.history_size as usize,
+            );
+        }
+    }
+
+    async fn reply(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        config: &Option<Self::PluginConfig>,
+    ) -> anyhow::Result<Option<Reply>> {
+        if payload.r#type != "kdeconnect.battery.request" || !peer.paired || !sends(config) {
+            return Ok(None);
+        }
+        let Some(status) = self.local_payload().await else {
+            return Ok(None);
+        };
+        Ok(Some(Reply::new("kdeconnect.battery", status)?))
+    }
+
+    fn connected(
+        &self,
+        device: &DeviceWithState,
+        config: &Option<Self::PluginConfig>,
+    ) -> Option<PluginAction> {
+        if !device.device.paired
+            || !sends(config)
+            || !device.capabilities.can_send("kdeconnect.battery")
+        {
+            return None;
+        }
+        let peer = device.peer()?;
+        let plugin = self.clone();
+        Some(PluginAction::Async(Box::pin(async move {
+            plugin.send_local(&peer).await;
+        })))
+    }
+
+    fn handle(
+        &self,
+        payload: &Self::PluginPayload,
+        previous_state: &Self::PluginState,
+    ) -> Option<PluginAction> {
+        let received = payload.received.as_ref()?;
+        if let Some(reason) = low_reason(payload, &received.config, previous_state) {
+            // Nobody listening is fine.
+            let _ = self.low_sender.send(BatteryLow {
+                device_id: received.device_id.clone(),
+                current_charge: payload.current_charge,
+                reason,
+            });
+        }
+        if !history_save_due(received, previous_state) {
+            return None;
+        }
+        let mut history = previous_state.history.clone();
+        record_sample(
+            &mut history,
+            payload.sample(received.timestamp),
+            received.config.history_size as usize,
+        );
+        let store = self.history.clone();
+        let device_id = received.device_id.clone();
+        Some(PluginAction::Async(Box::pin(async move {
+            if let Err(err) = store.save(&device_id, &history).await {
+                warn!("Cannot store battery history {err:?}");
+            }
+        })))
+    }
+
+    fn validate_config(&self, config: &Self::PluginConfig) -> anyhow::Result<()> {
+        if config.low_threshold > 100 {
+            return Err(anyhow::anyhow!("Low threshold must be a percentage"));
+        }
+        if config.history_size > MAX_HISTORY_SIZE {
+            return Err(anyhow::anyhow!(
+                "History size cannot exceed {MAX_HISTORY_SIZE}"
+            ));
+        }
+        Ok(())
+    }
+
+    fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+
+    fn should_send(
+        &self,
+        config: &Option<Self::PluginConfig>,
+        state: &mut Self::PluginState,
+        payload: &Self::PluginPayload,
+    ) -> bool {
+        let should_send = sends(config)
+            && state
+                .last_sent_status
+                .as_ref()
+                .is_none_or(|last_payload| last_payload != payload);
+        if should_send {
+            state.last_sent_status = Some(payload.clone());
+        }
+        should_send
+    }
+}
+
+/// Whether the history including `received` is written, throttled to [`HISTORY_SAVE_INTERVAL_MS`].
+fn history_save_due(received: &Received, previous_state: &BatteryState) -> bool {
+    received.config.history_size > 0
+        && previous_state.history_saved_at.is_none_or(|saved_at| {
+            received.timestamp.saturating_sub(saved_at) >= HISTORY_SAVE_INTERVAL_MS
+        })
+}
+
+/// Why a [`BatteryLow`] event fired, from the first payload of a low spell.
+fn low_reason(
+    payload: &BatteryPayload,
+    config: &BatteryConfig,
+    previous_state: &BatteryState,
+) -> Option<LowBatteryReason> {
+    let previous = previous_state.last_status.as_ref();
+    if payload.threshold_event == BatteryEventType::BatteryLow
+        && previous.is_none_or(|previous| previous.threshold_event != BatteryEventType::BatteryLow)
+    {
+        return Some(LowBatteryReason::Device);
+    }
+    let threshold = config.low_threshold as f32;
+    let below = |status: &BatteryPayload| !status.is_charging && status.current_charge <= threshold;
+    (config.low_threshold > 0 && below(payload) && !previous.is_some_and(below))
+        .then_some(LowBatteryReason::Threshold)
+}
+
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatteryPayload {
+    current_charge: f32,
+    is_charging: bool,
+    threshold_event: BatteryEventType,
+
+    #[serde(skip)]
+    #[graphql(skip)]
+    received: Option<Received>,
+}
+
+impl From<PowerStatus> for BatteryPayload {
+    fn from(status: PowerStatus) -> Self {
+        Self {
+            current_charge: status.current_charge,
+            is_charging: status.is_charging,
+            threshold_event: if status.is_low() {
+                BatteryEventType::BatteryLow
+            } else {
+                BatteryEventType::None
+            },
+            received: None,
+        }
+    }
+}
+
+impl BatteryPayload {
+    fn sample(&self, timestamp: u64) -> BatterySample {
+        BatterySample {
+            timestamp,
+            current_charge: self.current_charge,
+            is_charging: self.is_charging,
+        }
+    }
+}
+
+/// Details of a received payload needed to record it.
+#[derive(Debug, Clone, PartialEq)]
+struct Received {
+    device_id: String,
+    config: BatteryConfig,
+    timestamp: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, SimpleObject, InputObject)]
+#[graphql(input_name = "BatteryConfigInput")]
+#[serde(default)]
+pub struct BatteryConfig {
+    enabled: bool,
+    send_enabled: bool,
+    /// Charge percentage at or below which a low battery event fires, 0 turns it off.
+    #[graphql(default = 15)]
+    low_threshold: u32,
+    /// Samples kept per device, 0 turns history off.
+    #[graphql(default = 1000)]
+    history_size: u32,
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            send_enabled: true,
+            low_threshold: DEFAULT_LOW_THRESHOLD,
+            history_size: DEFAULT_HISTORY_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, SimpleObject)]
+pub struct BatteryState {
+    last_status: Option<BatteryPayload>,
+    last_sent_status: Option<BatteryPayload>,
+    /// Newest first.
+    history: Vec<BatterySample>,
+    /// When the history was last written, in milliseconds since epoch.
+    #[graphql(skip)]
+    history_saved_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum LowBatteryReason {
+    /// The device flagged its battery as low.
+    Device,
+    /// The charge dropped below the configured threshold while discharging.
+    Threshold,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct BatteryLow {
+    pub device_id: String,
+    pub current_charge: f32,
+    pub reason: LowBatteryReason,
+}
+
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Enum)]
+pub enum BatteryEventType {
+    None = 0,
+    BatteryLow = 1,
+    Unknown = 2,
+}
+
+impl serde::Serialize for BatteryEventType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for BatteryEventType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = u8::deserialize(deserializer)?;
+        match value {
+            0 => Ok(BatteryEventType::None),
+            1 => Ok(BatteryEventType::BatteryLow),
+            _ => Ok(BatteryEventType::Unknown),
+        }
+    }
+}
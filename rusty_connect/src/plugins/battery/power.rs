@@ -0,0 +1,115 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Where the kernel exposes batteries and chargers.
+pub const SYSFS_POWER_SUPPLY: &str = "/sys/class/power_supply";
+
+/// Charge at or below which a discharging battery is reported as low.
+const LOW_CHARGE: f32 = 15.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerStatus {
+    pub current_charge: f32,
+    pub is_charging: bool,
+}
+
+impl PowerStatus {
+    pub fn is_low(&self) -> bool {
+        !self.is_charging && self.current_charge <= LOW_CHARGE
+    }
+}
+
+/// Battery of the machine the daemon runs on.
+pub trait PowerSource: Send + Sync {
+    /// `None` when the machine has no battery.
+    fn status(&self) -> anyhow::Result<Option<PowerStatus>>;
+}
+
+/// Reads `/sys/class/power_supply`, or a directory laid out the same way.
+///
+/// Batteries of peripherals such as mice are ignored. The charge is averaged over the system
+/// batteries, and the machine counts as charging while a mains or USB supply is online.
+pub struct SysfsPowerSource {
+    root: PathBuf,
+}
+
+impl SysfsPowerSource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl Default for SysfsPowerSource {
+    fn default() -> Self {
+        Self::new(SYSFS_POWER_SUPPLY)
+    }
+}
+
+fn read_attribute(supply: &Path, name: &str) -> Option<String> {
+    std::fs::read_to_string(supply.join(name))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+impl PowerSource for SysfsPowerSource {
+    fn status(&self) -> anyhow::Result<Option<PowerStatus>> {
+        let mut charges = Vec::new();
+        let mut battery_charging = false;
+        let mut has_supply = false;
+        let mut supply_online = false;
+        for entry in std::fs::read_dir(&self.root)? {
+            let supply = entry?.path();
+            match read_attribute(&supply, "type").as_deref() {
+                Some("Battery") => {
+                    if read_attribute(&supply, "scope").as_deref() == Some("Device") {
+                        continue;
+                    }
+                    let Some(capacity) = read_attribute(&supply, "capacity")
+                        .and_then(|capacity| capacity.parse::<f32>().ok())
+                    else {
+                        continue;
+                    };
+                    charges.push(capacity.clamp(0.0, 100.0));
+                    battery_charging |=
+                        read_attribute(&supply, "status").as_deref() == Some("Charging");
+                }
+                Some("Mains") | Some("USB") => {
+                    has_supply = true;
+                    supply_online |= read_attribute(&supply, "online").as_deref() == Some("1");
+                }
+                _ => {}
+            }
+        }
+        if charges.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(PowerStatus {
+            current_charge: charges.iter().sum::<f32>() / charges.len() as f32,
+            is_charging: if has_supply {
+                supply_online
+            } else {
+                battery_charging
+            },
+        }))
+    }
+}
+
+/// Status set by hand, for machines where the app reports the battery itself and for tests.
+#[derive(Default)]
+pub struct MemoryPowerSource {
+    status: Mutex<Option<PowerStatus>>,
+}
+
+impl MemoryPowerSource {
+    pub fn set(&self, status: Option<PowerStatus>) {
+        *self.status.lock().expect("power status poisoned") = status;
+    }
+}
+
+impl PowerSource for MemoryPowerSource {
+    fn status(&self) -> anyhow::Result<Option<PowerStatus>> {
+        Ok(*self.status.lock().expect("power status poisoned"))
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    sync::{mpsc, Arc, Mutex},
+};
+
+use tracing::warn;
+
+/// Reads and writes the text of the local clipboard.
+pub trait ClipboardBackend: Send + Sync {
+    /// Current text, `None` when the clipboard holds no text.
+    fn get(&self) -> anyhow::Result<Option<String>>;
+    fn set(&self, content: &str) -> anyhow::Result<()>;
+}
+
+enum ArboardRequest {
+    Get(mpsc::Sender<anyhow::Result<Option<String>>>),
+    Set(String, mpsc::Sender<anyhow::Result<()>>),
+}
+
+/// Owns an arboard clipboard on a dedicated thread, since it is not `Send` on every platform.
+pub struct ArboardBackend {
+    sender: Mutex<mpsc::Sender<ArboardRequest>>,
+}
+
+impl ArboardBackend {
+    pub fn new() -> anyhow::Result<Self> {
+        let (sender, receiver) = mpsc::channel::<ArboardRequest>();
+        let (ready_sender, ready_receiver) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("arboard-clipboard".to_string())
+            .spawn(move || {
+                let mut clipboard = match arboard::Clipboard::new() {
+                    Ok(clipboard) => {
+                        let _ = ready_sender.send(Ok(()));
+                        clipboard
+                    }
+                    Err(err) => {
+                        let _ = ready_sender.send(Err(err));
+                        return;
+                    }
+                };
+                while let Ok(request) = receiver.recv() {
+                    match request {
+                        ArboardRequest::Get(reply) => {
+                            let content = match clipboard.get_text() {
+                                Ok(content) => Ok(Some(content)),
+                                Err(arboard::Error::ContentNotAvailable) => Ok(None),
+                                Err(err) => Err(err.into()),
+                            };
+                            let _ = reply.send(content);
+                        }
+                        ArboardRequest::Set(content, reply) => {
+                            let _ = reply.send(clipboard.set_text(content).map_err(Into::into));
+                        }
+                    }
+                }
+            })?;
+        ready_receiver.recv()??;
+        Ok(Self {
+            sender: Mutex::new(sender),
+        })
+    }
+
+    fn request<T>(
+        &self,
+        request: impl FnOnce(mpsc::Sender<anyhow::Result<T>>) -> ArboardRequest,
+    ) -> anyhow::Result<T> {
+        let (reply, receiver) = mpsc::channel();
+        self.sender
+            .lock()
+            .expect("arboard sender poisoned")
+            .send(request(reply))
+            .map_err(|_| anyhow::anyhow!("Clipboard thread stopped"))?;
+        receiver
+            .recv()
+            .map_err(|_| anyhow::anyhow!("Clipboard thread stopped"))?
+    }
+}
+
+impl ClipboardBackend for ArboardBackend {
+    fn get(&self) -> anyhow::Result<Option<String>> {
+        self.request(ArboardRequest::Get)
+    }
+
+    fn set(&self, content: &str) -> anyhow::Result<()> {
+        self.request(|reply| ArboardRequest::Set(content.to_string(), reply))
+    }
+}
+
+/// Uses `wl-paste` and `wl-copy` from wl-clipboard, for Wayland compositors.
+pub struct WlClipboardBackend;
+
+impl WlClipboardBackend {
+    /// Whether wl-clipboard can be used in this session.
+    pub fn is_available() -> bool {
+        std::env::var_os("WAYLAND_DISPLAY").is_some()
+            && Command::new("wl-paste")
+                .arg("--version")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success())
+    }
+}
+
+impl ClipboardBackend for WlClipboardBackend {
+    fn get(&self) -> anyhow::Result<Option<String>> {
+        let output = Command::new("wl-paste")
+            .args(["--no-newline", "--type", "text"])
+            .stderr(Stdio::null())
+            .output()?;
+        // wl-paste fails when nothing, or nothing textual, is copied.
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+    }
+
+    fn set(&self, content: &str) -> anyhow::Result<()> {
+        let mut child = Command::new("wl-copy")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        child
+            .stdin
+            .take()
+            .ok_or(anyhow::anyhow!("No stdin for wl-copy"))?
+            .write_all(content.as_bytes())?;
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(anyhow::anyhow!("wl-copy failed with {status}"));
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the clipboard in memory only, for tests and headless machines.
+#[derive(Default)]
+pub struct MemoryBackend {
+    content: Mutex<Option<String>>,
+}
+
+impl ClipboardBackend for MemoryBackend {
+    fn get(&self) -> anyhow::Result<Option<String>> {
+        Ok(self.content.lock().expect("clipboard poisoned").clone())
+    }
+
+    fn set(&self, content: &str) -> anyhow::Result<()> {
+        *self.content.lock().expect("clipboard poisoned") = Some(content.to_string());
+        Ok(())
+    }
+}
+
+/// wl-clipboard on Wayland, arboard otherwise, and memory when neither works.
+pub fn detect_backend() -> Arc<dyn ClipboardBackend> {
+    if WlClipboardBackend::is_available() {
+        return Arc::new(WlClipboardBackend);
+    }
+    match ArboardBackend::new() {
+        Ok(backend) => Arc::new(backend),
+        Err(err) => {
+            warn!("Cannot access the clipboard, syncing only in memory {err:?}");
+            Arc::new(MemoryBackend::default())
+        }
+    }
+}
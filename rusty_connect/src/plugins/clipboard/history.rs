@@ -0,0 +1,60 @@
+use async_graphql::{Enum, SimpleObject};
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::utils::get_timestamp;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, Serialize, Deserialize)]
+pub enum ClipboardSource {
+    /// Copied on this machine and sent to the device.
+    Local,
+    /// Received from the device.
+    Remote,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, SimpleObject, Serialize, Deserialize)]
+pub struct ClipboardEntry {
+    pub id: String,
+    pub content: String,
+    /// Milliseconds since epoch.
+    pub timestamp: u64,
+    pub source: ClipboardSource,
+}
+
+impl ClipboardEntry {
+    pub fn new(content: &str, source: ClipboardSource) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            content: content.to_string(),
+            timestamp: get_timestamp() as u64,
+            source,
+        }
+    }
+}
+
+/// Compiles `patterns` into one set; invalid patterns are ignored.
+pub fn compile_patterns(patterns: &[String]) -> RegexSet {
+    let valid = patterns.iter().filter(|pattern| match Regex::new(pattern) {
+        Ok(_) => true,
+        Err(err) => {
+            warn!("Invalid sensitive clipboard pattern {pattern:?} {err:?}");
+            false
+        }
+    });
+    RegexSet::new(valid).unwrap_or_else(|_| RegexSet::empty())
+}
+
+/// Whether `content` matches one of the compiled `patterns`.
+pub fn is_sensitive(content: &str, patterns: &RegexSet) -> bool {
+    patterns.is_match(content)
+}
+
+/// Adds `entry` as the newest one, keeping at most `limit` entries.
+///
+/// Copying the same content again moves it to the top instead of duplicating it.
+pub fn record(history: &mut Vec<ClipboardEntry>, entry: ClipboardEntry, limit: usize) {
+    history.retain(|existing| existing.content != entry.content);
+    history.insert(0, entry);
+    history.truncate(limit);
+}
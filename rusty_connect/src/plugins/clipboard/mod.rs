@@ -0,0 +1,515 @@
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use async_graphql::{Context, InputObject, Object, SimpleObject};
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+use crate::{
+    devices::{DeviceManager, DeviceWithState, Peer},
+    utils::history::HistoryStore,
+};
+
+pub use self::backend::{
+    detect_backend, ArboardBackend, ClipboardBackend, MemoryBackend, WlClipboardBackend,
+};
+pub use self::history::{ClipboardEntry, ClipboardSource};
+pub use self::sync::ClipboardSync;
+
+use self::history::{compile_patterns, is_sensitive, record};
+
+use super::{Plugin, PluginAction, PluginExt};
+
+pub mod backend;
+pub mod history;
+pub mod sync;
+
+/// How often the local clipboard is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+const DEFAULT_HISTORY_SIZE: u32 = 20;
+const MAX_HISTORY_SIZE: u32 = 500;
+
+#[derive(Clone)]
+pub struct Clipboard {
+    pub sync: Arc<ClipboardSync>,
+    history: Arc<HistoryStore<ClipboardEntry>>,
+}
+
+#[Object]
+impl Clipboard {
+    pub async fn send_clipboard<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: Option<String>,
+        content: String,
+    ) -> anyhow::Result<&str> {
+        let clipboard_payload = ClipboardPayload {
+            content,
+            ..Default::default()
+        };
+        self.send_payload(
+            context,
+            device_id.as_deref(),
+            "kdeconnect.clipboard",
+            clipboard_payload,
+        )
+        .await?;
+        self.save_history(Self::device_manager(context)?, device_id.as_deref())
+            .await;
+        Ok("success")
+    }
+
+    /// Contents sent to and received from the device, newest first.
+    pub async fn clipboard_history<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<ClipboardEntry>> {
+        let mut history = self.get_state(context, &device_id).await?.history;
+        if let Some(limit) = limit {
+            history.truncate(limit);
+        }
+        Ok(history)
+    }
+
+    /// Sends a history entry to the device again.
+    pub async fn recall_clipboard<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        entry_id: String,
+    ) -> anyhow::Result<ClipboardEntry> {
+        let entry = self
+            .get_state(context, &device_id)
+            .await?
+            .history
+            .into_iter()
+            .find(|entry| entry.id == entry_id)
+            .ok_or(anyhow::anyhow!("No clipboard entry with given id"))?;
+        let clipboard_payload = ClipboardPayload {
+            content: entry.content.clone(),
+            ..Default::default()
+        };
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.clipboard",
+            clipboard_payload,
+        )
+        .await?;
+        self.save_history(Self::device_manager(context)?, Some(&device_id))
+            .await;
+        Ok(entry)
+    }
+}
+
+impl Clipboard {
+    fn device_manager<'ctx>(
+        context: &Context<'ctx>,
+    ) -> anyhow::Result<&'ctx RwLock<DeviceManager>> {
+        Ok(context
+            .data::<Arc<RwLock<DeviceManager>>>()
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?)
+    }
+
+    /// Writes the history of the device, or of every device, where persistence is on.
+    async fn save_history(&self, device_manager: &RwLock<DeviceManager>, device_id: Option<&str>) {
+        let histories = {
+            let device_manager = device_manager.read().await;
+            device_manager
+                .devices
+                .values()
+                .filter(|device| device_id.is_none_or(|id| id == device.device.id))
+                .map(|device| {
+                    let persist =
+                        Self::get_config_from_plugin_configs(&device.device.effective_configs)
+                            .as_ref()
+                            .is_some_and(|config| config.persist_history);
+                    let history = device.device.plugin_states.clipboard.history.clone();
+                    (device.device.id.clone(), persist, history)
+                })
+                .collect::<Vec<_>>()
+        };
+        for (device_id, persist, history) in histories {
+            let result = if persist {
+                self.history.save(&device_id, &history).await
+            } else {
+                self.history.remove(&device_id).await
+            };
+            if let Err(err) = result {
+                warn!("Cannot store clipboard history {err:?}");
+            }
+        }
+    }
+
+    /// Watches the local clipboard and sends changes to devices with auto sync on.
+    pub fn start_sync(&self, device_manager: Arc<RwLock<DeviceManager>>) {
+        let plugin = self.clone();
+        tokio::spawn(async move {
+            let sync = plugin.sync.clone();
+            match tokio::task::spawn_blocking(move || sync.prime()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => warn!("Cannot read clipboard {err:?}"),
+                Err(err) => {
+                    warn!("Clipboard task failed {err:?}");
+                    return;
+                }
+            }
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                let sync = plugin.sync.clone();
+                let changed = match tokio::task::spawn_blocking(move || sync.poll_local()).await {
+                    Ok(Ok(Some((content, _)))) => content,
+                    Ok(Ok(None)) => continue,
+                    Ok(Err(err)) => {
+                        debug!("Cannot read clipboard {err:?}");
+                        continue;
+                    }
+                    Err(err) => {
+                        warn!("Clipboard task failed {err:?}");
+                        return;
+                    }
+                };
+                let payload = ClipboardPayload {
+                    content: changed,
+                    synced: true,
+                    ..Default::default()
+                };
+                if let Err(err) = plugin
+                    .send_payload_with(&device_manager, None, "kdeconnect.clipboard", payload)
+                    .await
+                {
+                    warn!("Cannot sync clipboard {err:?}");
+                }
+                plugin.save_history(&device_manager, None).await;
+            }
+        });
+    }
+}
+
+fn auto_sync(config: &Option<ClipboardConfig>) -> bool {
+    config.as_ref().is_none_or(|config| config.auto_sync)
+}
+
+/// Whether local changes are sent to the device without asking.
+fn sends_automatically(config: &Option<ClipboardConfig>) -> bool {
+    config
+        .as_ref()
+        .is_none_or(|config| config.auto_sync && config.send_enabled)
+}
+
+impl Plugin for Clipboard {
+    type PluginPayload = ClipboardPayload;
+    type PluginConfig = ClipboardConfig;
+    type PluginState = ClipboardState;
+
+    fn init(device_mangager: &crate::devices::DeviceManager) -> Self {
+        Self {
+            sync: Arc::new(ClipboardSync::new(detect_backend())),
+            history: Arc::new(HistoryStore::new(device_mangager.clipboard_path.clone())),
+        }
+    }
+
+    fn restore_state(
+        &self,
+        device_id: &str,
+        config: &Option<Self::PluginConfig>,
+        state: &mut Self::PluginState,
+    ) {
+        let Some(config) = config.as_ref().filter(|config| config.persist_history) else {
+            return;
+        };
+        match self.history.load(device_id) {
+            Ok(mut history) => {
+                history.truncate(config.history_size as usize);
+                state.history = history;
+            }
+            Err(err) => warn!("Cannot load clipboard history {err:?}"),
+        }
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.clipboard".to_string(),
+            "kdeconnect.clipboard.connect".to_string(),
+        ]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.clipboard".to_string(),
+            "kdeconnect.clipboard.connect".to_string(),
+        ]
+    }
+
+    async fn parse_payload(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        config: &Option<Self::PluginConfig>,
+    ) -> Option<Self::PluginPayload> {
+        let is_connect = payload.r#type == "kdeconnect.clipboard.connect";
+        if is_connect || payload.r#type == "kdeconnect.clipboard" {
+            let payload = serde_json::from_value::<Self::PluginPayload>(payload.body.clone());
+            match payload {
+                Ok(mut payload) => {
+                    // Only connect packets are ordered by timestamp.
+                    if !is_connect {
+                        payload.timestamp = None;
+                    }
+                    payload.synced = auto_sync(config);
+                    let config = config.clone().unwrap_or_default();
+                    payload.received = Some(Received {
+                        device_id: peer.device_id.clone(),
+                        entry: config.records(&payload.content).then(|| {
+                            ClipboardEntry::new(&payload.content, ClipboardSource::Remote)
+                        }),
+                        config,
+                    });
+                    return Some(payload);
+                }
+                Err(err) => warn!("Cant parse clipboard payload {err:#?}"),
+            }
+        }
+        None
+    }
+
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        if let Some(Received {
+            entry: Some(entry),
+            config,
+            ..
+        }) = &payload.received
+        {
+            record(
+                &mut state.history,
+                entry.clone(),
+                config.history_size as usize,
+            );
+        }
+    }
+
+    fn handle(
+        &self,
+        payload: &Self::PluginPayload,
+        previous_state: &Self::PluginState,
+    ) -> Option<PluginAction> {
+        let apply = payload.synced.then(|| {
+            (
+                self.sync.clone(),
+                payload.content.clone(),
+                payload.timestamp,
+            )
+        });
+        let persist = match &payload.received {
+            Some(Received {
+                device_id,
+                entry: Some(entry),
+                config,
+            }) if config.persist_history => {
+                let mut history = previous_state.history.clone();
+                record(&mut history, entry.clone(), config.history_size as usize);
+                Some((self.history.clone(), device_id.clone(), history))
+            }
+            _ => None,
+        };
+        if apply.is_none() && persist.is_none() {
+            return None;
+        }
+        Some(PluginAction::Async(Box::pin(async move {
+            if let Some((sync, content, timestamp)) = apply {
+                let applied =
+                    tokio::task::spawn_blocking(move || sync.apply_remote(&content, timestamp))
+                        .await;
+                match applied {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(err)) => warn!("Cannot set clipboard {err:?}"),
+                    Err(err) => warn!("Clipboard task failed {err:?}"),
+                }
+            }
+            if let Some((store, device_id, history)) = persist {
+                if let Err(err) = store.save(&device_id, &history).await {
+                    warn!("Cannot store clipboard history {err:?}");
+                }
+            }
+        })))
+    }
+
+    fn connected(
+        &self,
+        device: &DeviceWithState,
+        config: &Option<Self::PluginConfig>,
+    ) -> Option<PluginAction> {
+        if !device.device.paired
+            || !sends_automatically(config)
+            || !device.capabilities.can_send("kdeconnect.clipboard.connect")
+        {
+            return None;
+        }
+        let peer = device.peer()?;
+        let sync = self.sync.clone();
+        Some(PluginAction::Async(Box::pin(async move {
+            let (content, timestamp) =
+                match tokio::task::spawn_blocking(move || sync.current()).await {
+                    Ok(Ok(Some(current))) => current,
+                    Ok(Ok(None)) => return,
+                    Ok(Err(err)) => {
+                        warn!("Cannot read clipboard {err:?}");
+                        return;
+                    }
+                    Err(err) => {
+                        warn!("Clipboard task failed {err:?}");
+                        return;
+                    }
+                };
+            let payload = ClipboardPayload {
+                content,
+                timestamp: Some(timestamp),
+                ..Default::default()
+            };
+            if let Err(err) = peer.send("kdeconnect.clipboard.connect", payload).await {
+                warn!("Cannot send clipboard {err:?}");
+            }
+        })))
+    }
+
+    fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+    fn validate_config(&self, config: &Self::PluginConfig) -> anyhow::Result<()> {
+        if config.history_size > MAX_HISTORY_SIZE {
+            return Err(anyhow::anyhow!(
+                "History size cannot exceed {MAX_HISTORY_SIZE}"
+            ));
+        }
+        let patterns = RegexSet::new(&config.sensitive_patterns)
+            .map_err(|err| anyhow::anyhow!("Invalid pattern: {err}"))?;
+        // Keeps the compiled set with the config that gets stored.
+        let _ = config.compiled_patterns.set(patterns);
+        Ok(())
+    }
+
+    fn should_send(
+        &self,
+        config: &Option<Self::PluginConfig>,
+        state: &mut Self::PluginState,
+        payload: &Self::PluginPayload,
+    ) -> bool {
+        let should_send = if payload.synced {
+            sends_automatically(config)
+        } else if let Some(config) = config {
+            config.send_enabled
+        } else {
+            true
+        };
+        let history_config = config.clone().unwrap_or_default();
+        if should_send && history_config.records(&payload.content) {
+            record(
+                &mut state.history,
+                ClipboardEntry::new(&payload.content, ClipboardSource::Local),
+                history_config.history_size as usize,
+            );
+        }
+        should_send
+    }
+}
+
+#[derive(SimpleObject, Deserialize, Serialize, Debug, Default)]
+pub struct ClipboardPayload {
+    pub content: String,
+
+    /// When the content was copied in milliseconds since epoch, set on connect packets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+
+    /// Whether the packet is part of auto sync: sent by the clipboard watcher, or received from a
+    /// device whose content is applied to the local clipboard.
+    #[serde(skip)]
+    #[graphql(skip)]
+    pub synced: bool,
+
+    #[serde(skip)]
+    #[graphql(skip)]
+    received: Option<Received>,
+}
+
+/// Details of a received payload needed to record it.
+#[derive(Debug)]
+struct Received {
+    device_id: String,
+    config: ClipboardConfig,
+    /// `None` when history is off or the content is sensitive.
+    entry: Option<ClipboardEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "ClipboardConfigInput")]
+#[serde(default)]
+pub struct ClipboardConfig {
+    enabled: bool,
+    send_enabled: bool,
+    /// Whether local changes are sent automatically and received content is copied locally.
+    #[graphql(default = true)]
+    auto_sync: bool,
+    /// Entries kept per device, 0 turns history off.
+    #[graphql(default = 20)]
+    history_size: u32,
+    /// Whether history is kept across restarts.
+    #[graphql(default)]
+    persist_history: bool,
+    /// Regexes of content never kept in history, such as one-time codes.
+    #[graphql(default_with = "default_sensitive_patterns()")]
+    sensitive_patterns: Vec<String>,
+    /// `sensitive_patterns` compiled on first use.
+    #[serde(skip)]
+    #[graphql(skip)]
+    compiled_patterns: OnceLock<RegexSet>,
+}
+
+fn default_sensitive_patterns() -> Vec<String> {
+    vec![r"^\s*\d{4,8}\s*$".to_string()]
+}
+
+impl ClipboardConfig {
+    /// Whether `content` belongs in the history.
+    fn records(&self, content: &str) -> bool {
+        self.history_size > 0
+            && !is_sensitive(
+                content,
+                self.compiled_patterns
+                    .get_or_init(|| compile_patterns(&self.sensitive_patterns)),
+            )
+    }
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            send_enabled: true,
+            auto_sync: true,
+            history_size: DEFAULT_HISTORY_SIZE,
+            persist_history: false,
+            sensitive_patterns: default_sensitive_patterns(),
+            compiled_patterns: OnceLock::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, SimpleObject)]
+pub struct ClipboardState {
+    enabled: bool,
+    /// Newest first.
+    history: Vec<ClipboardEntry>,
+}
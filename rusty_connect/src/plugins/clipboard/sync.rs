@@ -0,0 +1,97 @@
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::utils::get_timestamp;
+
+use super::backend::ClipboardBackend;
+
+/// Last content known to be on the local clipboard.
+#[derive(Default)]
+struct Seen {
+    content: Option<String>,
+    /// When it changed in milliseconds since epoch, 0 when unknown.
+    timestamp: u64,
+}
+
+/// Detects local clipboard changes and applies remote ones without echoing them back.
+///
+/// Backend access happens under one lock, so content written for a peer is always recorded as
+/// seen before the next poll can observe it.
+pub struct ClipboardSync {
+    backend: RwLock<Arc<dyn ClipboardBackend>>,
+    seen: Mutex<Seen>,
+}
+
+impl ClipboardSync {
+    pub fn new(backend: Arc<dyn ClipboardBackend>) -> Self {
+        Self {
+            backend: RwLock::new(backend),
+            seen: Mutex::new(Seen::default()),
+        }
+    }
+
+    pub fn set_backend(&self, backend: Arc<dyn ClipboardBackend>) {
+        *self.backend.write().expect("clipboard backend poisoned") = backend;
+        *self.seen.lock().expect("clipboard sync poisoned") = Seen::default();
+    }
+
+    fn backend(&self) -> Arc<dyn ClipboardBackend> {
+        self.backend
+            .read()
+            .expect("clipboard backend poisoned")
+            .clone()
+    }
+
+    /// Records the current content without reporting it, its change time being unknown.
+    pub fn prime(&self) -> anyhow::Result<()> {
+        let mut seen = self.seen.lock().expect("clipboard sync poisoned");
+        *seen = Seen {
+            content: self.backend().get()?,
+            timestamp: 0,
+        };
+        Ok(())
+    }
+
+    /// Returns the content and its timestamp when it changed locally since the last call.
+    pub fn poll_local(&self) -> anyhow::Result<Option<(String, u64)>> {
+        let mut seen = self.seen.lock().expect("clipboard sync poisoned");
+        let Some(content) = self.backend().get()? else {
+            return Ok(None);
+        };
+        if seen.content.as_ref() == Some(&content) {
+            return Ok(None);
+        }
+        seen.content = Some(content.clone());
+        seen.timestamp = get_timestamp() as u64;
+        Ok(Some((content, seen.timestamp)))
+    }
+
+    /// Content and timestamp to announce in `kdeconnect.clipboard.connect`.
+    pub fn current(&self) -> anyhow::Result<Option<(String, u64)>> {
+        self.poll_local()?;
+        let seen = self.seen.lock().expect("clipboard sync poisoned");
+        Ok(seen
+            .content
+            .clone()
+            .map(|content| (content, seen.timestamp)))
+    }
+
+    /// Writes content received from a peer, returning whether the clipboard changed.
+    ///
+    /// Content with a `timestamp`, from `kdeconnect.clipboard.connect`, is only applied when
+    /// known and newer than the local content.
+    pub fn apply_remote(&self, content: &str, timestamp: Option<u64>) -> anyhow::Result<bool> {
+        let mut seen = self.seen.lock().expect("clipboard sync poisoned");
+        if let Some(timestamp) = timestamp {
+            if timestamp == 0 || timestamp <= seen.timestamp {
+                return Ok(false);
+            }
+        }
+        if seen.content.as_deref() == Some(content) {
+            return Ok(false);
+        }
+        self.backend().set(content)?;
+        seen.content = Some(content.to_string());
+        seen.timestamp = timestamp.unwrap_or_else(|| get_timestamp() as u64);
+        Ok(true)
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use async_graphql::{Context, InputObject, Object, SimpleObject};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::{
+    devices::{DeviceWithState, Peer},
+    utils::{get_timestamp, BROADCAST_CHANNEL_SIZE},
+};
+
+use super::{Plugin, PluginAction, PluginExt};
+
+pub struct ConnectivityReport {
+    change_sender: broadcast::Sender<ConnectivityChanged>,
+}
+
+#[Object]
+impl ConnectivityReport {
+    /// Asks the device for a fresh report.
+    pub async fn request_connectivity_report<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<&str> {
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.connectivity_report.request",
+            ConnectivityReportPayload::default(),
+        )
+        .await?;
+        Ok("success")
+    }
+}
+
+impl ConnectivityReport {
+    /// Receives the new signals whenever a device's report changes.
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<ConnectivityChanged> {
+        self.change_sender.subscribe()
+    }
+}
+
+impl Plugin for ConnectivityReport {
+    type PluginPayload = ConnectivityReportPayload;
+    type PluginConfig = ConnectivityReportConfig;
+    type PluginState = ConnectivityReportState;
+
+    fn init(_device_mangager: &crate::devices::DeviceManager) -> Self {
+        Self {
+            change_sender: broadcast::channel(BROADCAST_CHANNEL_SIZE).0,
+        }
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec!["kdeconnect.connectivity_report".to_string()]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec!["kdeconnect.connectivity_report.request".to_string()]
+    }
+
+    async fn parse_payload(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        _config: &Option<Self::PluginConfig>,
+    ) -> Option<Self::PluginPayload> {
+        if payload.r#type == "kdeconnect.connectivity_report" {
+            let payload = serde_json::from_value::<Self::PluginPayload>(payload.body.clone());
+            if let Ok(mut payload) = payload {
+                payload.signals = payload
+                    .signal_strengths
+                    .iter()
+                    .map(|(subscription_id, signal)| SimSignal {
+                        subscription_id: subscription_id.clone(),
+                        network_type: signal.network_type.clone(),
+                        signal_strength: signal.signal_strength,
+                    })
+                    .collect();
+                payload.device_id = peer.device_id.clone();
+                return Some(payload);
+            }
+        }
+        None
+    }
+
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        state.signals = payload.signals.clone();
+        state.updated_at = Some(get_timestamp() as u64);
+    }
+
+    /// Asks for a report right away instead of waiting for the next change on the phone.
+    fn connected(
+        &self,
+        device: &DeviceWithState,
+        _config: &Option<Self::PluginConfig>,
+    ) -> Option<PluginAction> {
+        if !device.device.paired
+            || !device
+                .capabilities
+                .can_send("kdeconnect.connectivity_report.request")
+        {
+            return None;
+        }
+        let peer = device.peer()?;
+        Some(PluginAction::Async(Box::pin(async move {
+            if let Err(err) = peer
+                .send(
+                    "kdeconnect.connectivity_report.request",
+                    ConnectivityReportPayload::default(),
+                )
+                .await
+            {
+                warn!("Cannot request connectivity report {err:?}");
+            }
+        })))
+    }
+
+    fn handle(
+        &self,
+        payload: &Self::PluginPayload,
+        previous_state: &Self::PluginState,
+    ) -> Option<PluginAction> {
+        if payload.signals != previous_state.signals {
+            // Nobody listening is fine.
+            let _ = self.change_sender.send(ConnectivityChanged {
+                device_id: payload.device_id.clone(),
+                signals: payload.signals.clone(),
+            });
+        }
+        None
+    }
+
+    fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+
+    fn should_send(
+        &self,
+        config: &Option<Self::PluginConfig>,
+        _state: &mut Self::PluginState,
+        _payload: &Self::PluginPayload,
+    ) -> bool {
+        if let Some(config) = config {
+            config.send_enabled
+        } else {
+            true
+        }
+    }
+}
+
+//https://github.com/KDE/kdeconnect-kde/tree/master/plugins/connectivity-report
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectivityReportPayload {
+    /// Keyed by SIM subscription id.
+    #[graphql(skip)]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    signal_strengths: BTreeMap<String, SignalStrength>,
+
+    /// One entry per SIM subscription, ordered by subscription id.
+    #[serde(skip)]
+    signals: Vec<SimSignal>,
+
+    #[serde(skip)]
+    #[graphql(skip)]
+    device_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SignalStrength {
+    network_type: String,
+    signal_strength: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, SimpleObject, Serialize, Deserialize)]
+pub struct SimSignal {
+    pub subscription_id: String,
+    /// Such as `4G` or `5G`, as reported by the phone.
+    pub network_type: String,
+    /// From 0 to 4.
+    pub signal_strength: i32,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ConnectivityChanged {
+    pub device_id: String,
+    pub signals: Vec<SimSignal>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "ConnectivityReportConfigInput")]
+pub struct ConnectivityReportConfig {
+    enabled: bool,
+    send_enabled: bool,
+}
+
+impl Default for ConnectivityReportConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            send_enabled: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, SimpleObject)]
+pub struct ConnectivityReportState {
+    pub signals: Vec<SimSignal>,
+    /// When the latest report arrived, in milliseconds since epoch.
+    pub updated_at: Option<u64>,
+}
@@ -0,0 +1,329 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use async_graphql::{Context, InputObject, Object, SimpleObject};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{devices::Peer, payloads::PayloadType, utils::get_timestamp};
+
+use self::store::{vcard_properties, ContactStore};
+
+use super::{Plugin, PluginAction, PluginExt, ReceivedPayload};
+
+pub mod store;
+
+#[derive(Clone)]
+pub struct Contacts {
+    pub store: Arc<ContactStore>,
+    /// Timestamps of requested vCards, keyed by device id and uid, until the vCards arrive.
+    pending: Arc<Mutex<HashMap<(String, String), i64>>>,
+    /// Emits the uids each sync step stored or removed.
+    payload_sender: flume::Sender<PayloadType>,
+}
+
+#[Object]
+impl Contacts {
+    /// Starts an incremental sync; only vCards changed since the last sync are fetched.
+    pub async fn sync_contacts<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<&str> {
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.contacts.request_all_uids_timestamps",
+            ContactsPayload::default(),
+        )
+        .await?;
+        Ok("success")
+    }
+
+    pub async fn contacts(
+        &self,
+        device_id: String,
+        query: Option<String>,
+    ) -> anyhow::Result<Vec<Contact>> {
+        self.store
+            .search(&device_id, query.as_deref().unwrap_or_default())
+            .await
+    }
+}
+
+impl Contacts {
+    /// Drops contacts the peer no longer has and requests the ones that changed.
+    async fn handle_timestamps(&self, peer: &Peer, payload: &mut ContactsPayload) {
+        let uids = payload.uids.clone().unwrap_or_default();
+        let local = match self.store.timestamps(&peer.device_id).await {
+            Ok(local) => local,
+            Err(err) => {
+                warn!("Cannot read contacts {err:?}");
+                return;
+            }
+        };
+        for uid in local.keys().filter(|uid| !uids.contains(uid)) {
+            match self.store.remove(&peer.device_id, uid).await {
+                Ok(()) => payload.removed.push(uid.clone()),
+                Err(err) => warn!("Cannot remove contact {uid} {err:?}"),
+            }
+        }
+        let mut changed = vec![];
+        {
+            let mut pending = self.pending.lock().await;
+            for uid in uids {
+                let timestamp = payload
+                    .entries
+                    .get(&uid)
+                    .and_then(|value| value.as_i64())
+                    .unwrap_or_default();
+                if local.get(&uid) != Some(&timestamp) {
+                    pending.insert((peer.device_id.clone(), uid.clone()), timestamp);
+                    changed.push(uid);
+                }
+            }
+        }
+        if changed.is_empty() {
+            return;
+        }
+        info!("Requesting {} changed contacts", changed.len());
+        let request = ContactsPayload {
+            uids: Some(changed),
+            ..Default::default()
+        };
+        if let Err(err) = peer
+            .send("kdeconnect.contacts.request_vcards_by_uid", request)
+            .await
+        {
+            warn!("Cannot request vcards {err:?}");
+        }
+    }
+
+    async fn handle_vcards(&self, peer: &Peer, payload: &mut ContactsPayload) {
+        for uid in payload.uids.clone().unwrap_or_default() {
+            let Some(vcard) = payload.entries.get(&uid).and_then(|value| value.as_str()) else {
+                continue;
+            };
+            let timestamp = self
+                .pending
+                .lock()
+                .await
+                .remove(&(peer.device_id.clone(), uid.clone()))
+                .unwrap_or_default();
+            match self
+                .store
+                .save(&peer.device_id, &uid, vcard, timestamp)
+                .await
+            {
+                Ok(()) => payload.updated.push(uid),
+                Err(err) => warn!("Cannot store contact {uid} {err:?}"),
+            }
+        }
+    }
+}
+
+impl Plugin for Contacts {
+    type PluginPayload = ContactsPayload;
+    type PluginConfig = ContactsConfig;
+    type PluginState = ContactsState;
+
+    fn init(device_mangager: &crate::devices::DeviceManager) -> Self {
+        Self {
+            store: Arc::new(ContactStore::new(device_mangager.contacts_path.clone())),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            payload_sender: device_mangager.sender.clone(),
+        }
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.contacts.response_uids_timestamps".to_string(),
+            "kdeconnect.contacts.response_vcards".to_string(),
+        ]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.contacts.request_all_uids_timestamps".to_string(),
+            "kdeconnect.contacts.request_vcards_by_uid".to_string(),
+        ]
+    }
+
+    async fn parse_payload(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        _config: &Option<Self::PluginConfig>,
+    ) -> Option<Self::PluginPayload> {
+        let is_timestamps = payload.r#type == "kdeconnect.contacts.response_uids_timestamps";
+        if !is_timestamps && payload.r#type != "kdeconnect.contacts.response_vcards" {
+            return None;
+        }
+        let mut contacts_payload =
+            match serde_json::from_value::<Self::PluginPayload>(payload.body.clone()) {
+                Ok(contacts_payload) => contacts_payload,
+                Err(err) => {
+                    warn!("Cant parse contacts payload {err:#?}");
+                    return None;
+                }
+            };
+        if !peer.paired {
+            warn!("Ignoring contacts from unpaired device");
+            return Some(contacts_payload);
+        }
+        contacts_payload.sync_step = Some(if is_timestamps {
+            SyncStep::Timestamps(peer.clone())
+        } else {
+            SyncStep::Vcards(peer.clone())
+        });
+        Some(contacts_payload)
+    }
+
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        if payload.uids.is_some() {
+            state.last_synced_at = Some(get_timestamp() as u64);
+        }
+    }
+
+    /// Updates the stored contacts and emits what changed once done.
+    fn handle(
+        &self,
+        payload: &Self::PluginPayload,
+        _previous_state: &Self::PluginState,
+    ) -> Option<PluginAction> {
+        let sync_step = payload.sync_step.clone()?;
+        let plugin = self.clone();
+        let mut synced = ContactsPayload {
+            uids: payload.uids.clone(),
+            entries: payload.entries.clone(),
+            ..Default::default()
+        };
+        Some(PluginAction::Async(Box::pin(async move {
+            let peer = match &sync_step {
+                SyncStep::Timestamps(peer) => {
+                    plugin.handle_timestamps(peer, &mut synced).await;
+                    peer
+                }
+                SyncStep::Vcards(peer) => {
+                    plugin.handle_vcards(peer, &mut synced).await;
+                    peer
+                }
+            };
+            if synced.updated.is_empty() && synced.removed.is_empty() {
+                return;
+            }
+            let changes = ContactsPayload {
+                updated: synced.updated,
+                removed: synced.removed,
+                ..Default::default()
+            };
+            if let Err(err) = plugin
+                .payload_sender
+                .try_send((peer.device_id.clone(), ReceivedPayload::Contacts(changes)))
+            {
+                warn!("Nothing to handle contact changes {err:?}");
+            }
+        })))
+    }
+
+    fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+
+    fn should_send(
+        &self,
+        config: &Option<Self::PluginConfig>,
+        _state: &mut Self::PluginState,
+        _payload: &Self::PluginPayload,
+    ) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+}
+
+//https://github.com/KDE/kdeconnect-kde/tree/master/plugins/contacts
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Default)]
+pub struct ContactsPayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uids: Option<Vec<String>>,
+
+    /// Timestamp or vCard of each uid, keyed by the uid itself.
+    #[serde(flatten)]
+    #[graphql(skip)]
+    entries: BTreeMap<String, serde_json::Value>,
+
+    /// Uids stored, set on the payload emitted once a sync step is done.
+    #[serde(skip)]
+    updated: Vec<String>,
+
+    /// Uids removed because the peer no longer has them.
+    #[serde(skip)]
+    removed: Vec<String>,
+
+    #[serde(skip)]
+    #[graphql(skip)]
+    sync_step: Option<SyncStep>,
+}
+
+/// Store update a paired device's response calls for, run in [`Plugin::handle`].
+#[derive(Debug, Clone)]
+enum SyncStep {
+    Timestamps(Peer),
+    Vcards(Peer),
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct Contact {
+    pub uid: String,
+    pub name: String,
+    pub phone_numbers: Vec<String>,
+    pub emails: Vec<String>,
+}
+
+impl Contact {
+    pub fn from_vcard(uid: String, vcard: &str) -> Self {
+        let mut contact = Self {
+            uid,
+            name: String::new(),
+            phone_numbers: vec![],
+            emails: vec![],
+        };
+        for (name, value) in vcard_properties(vcard) {
+            match name.as_str() {
+                "FN" => contact.name = value,
+                "TEL" => contact.phone_numbers.push(value),
+                "EMAIL" => contact.emails.push(value),
+                _ => {}
+            }
+        }
+        contact
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "ContactsConfigInput")]
+pub struct ContactsConfig {
+    enabled: bool,
+}
+
+impl Default for ContactsConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Default, Clone, SimpleObject)]
+pub struct ContactsState {
+    /// When contacts were last received, in milliseconds since epoch.
+    last_synced_at: Option<u64>,
+}
@@ -0,0 +1,155 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use tokio::sync::Mutex;
+
+use crate::utils::is_safe_file_name;
+
+use super::Contact;
+
+/// Property used to remember the peer's last-modified timestamp inside each vCard.
+const TIMESTAMP_PROPERTY: &str = "X-KDECONNECT-TIMESTAMP";
+/// Property keeping the peer's uid, as file names only carry it encoded.
+const UID_PROPERTY: &str = "X-KDECONNECT-UID";
+
+/// Per-device folder of vCards, named after the URL safe base64 of their uid.
+pub struct ContactStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl ContactStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn device_folder(&self, device_id: &str) -> anyhow::Result<PathBuf> {
+        if !is_safe_file_name(device_id) {
+            return Err(anyhow::anyhow!("Invalid device id {device_id:?}"));
+        }
+        Ok(self.path.join(device_id))
+    }
+
+    fn contact_file(&self, device_id: &str, uid: &str) -> anyhow::Result<PathBuf> {
+        if uid.is_empty() || uid.contains(char::is_control) {
+            return Err(anyhow::anyhow!("Invalid contact uid {uid:?}"));
+        }
+        let file_name = BASE64_URL_SAFE_NO_PAD.encode(uid);
+        Ok(self
+            .device_folder(device_id)?
+            .join(format!("{file_name}.vcf")))
+    }
+
+    /// Stored vCards keyed by uid.
+    async fn read_all(&self, device_id: &str) -> anyhow::Result<BTreeMap<String, String>> {
+        let mut vcards = BTreeMap::new();
+        let mut entries = match tokio::fs::read_dir(self.device_folder(device_id)?).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vcards),
+            Err(err) => return Err(err.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "vcf") {
+                let vcard = tokio::fs::read_to_string(&path).await?;
+                // Ours is appended last, after anything the peer sent.
+                if let Some((_, uid)) = vcard_properties(&vcard)
+                    .filter(|(name, _)| name == UID_PROPERTY)
+                    .last()
+                {
+                    vcards.insert(uid, vcard);
+                }
+            }
+        }
+        Ok(vcards)
+    }
+
+    pub async fn timestamps(&self, device_id: &str) -> anyhow::Result<BTreeMap<String, i64>> {
+        let _guard = self.lock.lock().await;
+        Ok(self
+            .read_all(device_id)
+            .await?
+            .into_iter()
+            .map(|(uid, vcard)| {
+                // Ours is appended last, like the uid in `read_all`.
+                let timestamp = vcard_properties(&vcard)
+                    .filter(|(name, _)| name == TIMESTAMP_PROPERTY)
+                    .last()
+                    .and_then(|(_, value)| value.parse().ok())
+                    .unwrap_or_default();
+                (uid, timestamp)
+            })
+            .collect())
+    }
+
+    pub async fn save(
+        &self,
+        device_id: &str,
+        uid: &str,
+        vcard: &str,
+        timestamp: i64,
+    ) -> anyhow::Result<()> {
+        let _guard = self.lock.lock().await;
+        let file = self.contact_file(device_id, uid)?;
+        if let Some(parent) = file.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let body = vcard.trim_end();
+        let body = body.strip_suffix("END:VCARD").unwrap_or(body).trim_end();
+        let vcard = format!(
+            "{body}\r\n{UID_PROPERTY}:{uid}\r\n{TIMESTAMP_PROPERTY}:{timestamp}\r\nEND:VCARD\r\n"
+        );
+        tokio::fs::write(file, vcard).await?;
+        Ok(())
+    }
+
+    pub async fn remove(&self, device_id: &str, uid: &str) -> anyhow::Result<()> {
+        let _guard = self.lock.lock().await;
+        tokio::fs::remove_file(self.contact_file(device_id, uid)?).await?;
+        Ok(())
+    }
+
+    /// Contacts whose name, phone number or email contains `query`, ignoring case.
+    pub async fn search(&self, device_id: &str, query: &str) -> anyhow::Result<Vec<Contact>> {
+        let _guard = self.lock.lock().await;
+        let query = query.to_lowercase();
+        let mut contacts = self
+            .read_all(device_id)
+            .await?
+            .into_iter()
+            .map(|(uid, vcard)| Contact::from_vcard(uid, &vcard))
+            .filter(|contact| {
+                query.is_empty()
+                    || contact.name.to_lowercase().contains(&query)
+                    || contact
+                        .phone_numbers
+                        .iter()
+                        .chain(contact.emails.iter())
+                        .any(|value| value.to_lowercase().contains(&query))
+            })
+            .collect::<Vec<_>>();
+        contacts.sort_by_key(|contact| contact.name.to_lowercase());
+        Ok(contacts)
+    }
+}
+
+/// Unfolded `(NAME, value)` pairs of a vCard, without parameters or groups.
+pub fn vcard_properties(vcard: &str) -> impl Iterator<Item = (String, String)> {
+    let mut lines: Vec<String> = vec![];
+    for line in vcard.lines() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines.into_iter().filter_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        let name = key.split(';').next()?;
+        let name = name.rsplit('.').next()?;
+        Some((name.to_uppercase(), value.to_string()))
+    })
+}
@@ -0,0 +1,318 @@
+use std::sync::{Arc, RwLock as StdRwLock};
+
+use async_graphql::{Context, Json, Object, SimpleObject};
+use futures::future::BoxFuture;
+use serde_json::Value;
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::{
+    devices::{DeviceManager, DeviceState, DeviceWithState, Peer},
+    payloads::Payload,
+};
+
+use super::{PluginAction, Reply};
+
+/// Object-safe plugin registered at runtime with [`crate::RustyConnect::with_plugin`].
+///
+/// Unlike [`super::Plugin`], configs, states and payloads are plain JSON so plugins can live in
+/// other crates. Configs are persisted per device and states are kept in memory, both keyed by
+/// [`ExtensionPlugin::id`].
+///
+/// GraphQL types are fixed when the schema is built, so extensions cannot add typed fields;
+/// their operations go through [`ExtensionPlugin::call`] with JSON arguments instead.
+pub trait ExtensionPlugin: Send + Sync + 'static {
+    /// Unique key for this plugin, used in the GraphQL api and to store its config and state.
+    fn id(&self) -> &str;
+
+    fn incoming_capabilities(&self) -> Vec<String>;
+    fn outgoing_capabilities(&self) -> Vec<String>;
+
+    /// Enabled unless the config has `"enabled": false`.
+    fn is_enabled(&self, config: &Option<Value>) -> bool {
+        config
+            .as_ref()
+            .and_then(|config| config.get("enabled"))
+            .and_then(Value::as_bool)
+            .unwrap_or(true)
+    }
+
+    /// Rejects configs set through the api before they are stored.
+    fn validate_config(&self, _config: &Value) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn should_send(&self, config: &Option<Value>, _payload: &Value) -> bool {
+        self.is_enabled(config)
+    }
+
+    /// Same contract as [`super::Plugin::connected`].
+    fn connected(
+        &self,
+        _device: &DeviceWithState,
+        _config: &Option<Value>,
+    ) -> Option<PluginAction> {
+        None
+    }
+
+    /// Same contract as [`super::Plugin::reply`].
+    fn reply<'a>(
+        &'a self,
+        _payload: &'a Payload,
+        _peer: &'a Peer,
+        _config: &'a Option<Value>,
+    ) -> BoxFuture<'a, anyhow::Result<Option<Reply>>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// Returns the parsed body when the payload belongs to this plugin.
+    fn parse_payload<'a>(
+        &'a self,
+        payload: &'a Payload,
+        peer: &'a Peer,
+        config: &'a Option<Value>,
+    ) -> BoxFuture<'a, Option<Value>>;
+
+    /// Same contract as [`super::Plugin::update_state`]; the state starts as `null`.
+    fn update_state(&self, _payload: &Value, _state: &mut Value) {}
+
+    /// Same contract as [`super::Plugin::handle`].
+    fn handle(&self, _payload: &Value, _previous_state: &Value) -> Option<PluginAction> {
+        None
+    }
+
+    /// Entry point for the plugin's GraphQL operations, exposed as `plugins.extension(id).call`.
+    fn call<'a>(
+        &'a self,
+        handle: ExtensionHandle,
+        method: &'a str,
+        device_id: Option<String>,
+        args: Value,
+    ) -> BoxFuture<'a, anyhow::Result<Value>> {
+        let _ = (handle, device_id, args);
+        Box::pin(async move { Err(anyhow::anyhow!("Unknown method {method}")) })
+    }
+}
+
+/// Plugins registered at runtime, in registration order.
+#[derive(Default)]
+pub struct Extensions {
+    plugins: StdRwLock<Vec<Arc<dyn ExtensionPlugin>>>,
+}
+
+impl Extensions {
+    pub fn register(&self, plugin: Arc<dyn ExtensionPlugin>) -> anyhow::Result<()> {
+        let mut plugins = self.plugins.write().expect("extensions poisoned");
+        if plugins.iter().any(|existing| existing.id() == plugin.id()) {
+            return Err(anyhow::anyhow!("Plugin {} already registered", plugin.id()));
+        }
+        plugins.push(plugin);
+        Ok(())
+    }
+
+    pub fn all(&self) -> Vec<Arc<dyn ExtensionPlugin>> {
+        self.plugins.read().expect("extensions poisoned").clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<dyn ExtensionPlugin>> {
+        self.plugins
+            .read()
+            .expect("extensions poisoned")
+            .iter()
+            .find(|plugin| plugin.id() == id)
+            .cloned()
+    }
+}
+
+/// Gives an extension access to its per-device config and state and lets it send packets.
+#[derive(Clone)]
+pub struct ExtensionHandle {
+    plugin: Arc<dyn ExtensionPlugin>,
+    device_manager: Arc<RwLock<DeviceManager>>,
+}
+
+impl ExtensionHandle {
+    pub fn new(
+        plugin: Arc<dyn ExtensionPlugin>,
+        device_manager: Arc<RwLock<DeviceManager>>,
+    ) -> Self {
+        Self {
+            plugin,
+            device_manager,
+        }
+    }
+
+    pub async fn config(&self, device_id: &str) -> anyhow::Result<Option<Value>> {
+        let device_manager = self.device_manager.read().await;
+        let device = device_manager
+            .devices
+            .get(device_id)
+            .ok_or(anyhow::anyhow!("Device not found with given id"))?;
+        Ok(device
+            .device
+            .extension_configs
+            .get(self.plugin.id())
+            .cloned())
+    }
+
+    /// Replaces the device's config for this plugin and persists it.
+    pub async fn set_config(&self, device_id: &str, config: Value) -> anyhow::Result<()> {
+        self.plugin.validate_config(&config)?;
+        let mut device_manager = self.device_manager.write().await;
+        let device = device_manager
+            .devices
+            .get_mut(device_id)
+            .ok_or(anyhow::anyhow!("Device not found with given id"))?;
+        device
+            .device
+            .extension_configs
+            .insert(self.plugin.id().to_string(), config);
+        device_manager.save().await?;
+        device_manager.notify_config_changed(device_id, vec![self.plugin.id().to_string()]);
+        Ok(())
+    }
+
+    pub async fn state(&self, device_id: &str) -> anyhow::Result<Value> {
+        let device_manager = self.device_manager.read().await;
+        let device = device_manager
+            .devices
+            .get(device_id)
+            .ok_or(anyhow::anyhow!("Device not found with given id"))?;
+        Ok(device
+            .device
+            .extension_states
+            .get(self.plugin.id())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    /// Sends to one device, or to every paired device when `device_id` is `None`.
+    pub async fn send(
+        &self,
+        device_id: Option<&str>,
+        payload_type: &str,
+        body: Value,
+    ) -> anyhow::Result<()> {
+        let device_manager = self.device_manager.read().await;
+        let payload = Payload::generate_new(payload_type, body.clone());
+        if let Some(device_id) = device_id {
+            let device = device_manager
+                .devices
+                .get(device_id)
+                .ok_or(anyhow::anyhow!("No device with given id"))?;
+            if !device.device.paired {
+                return Err(anyhow::anyhow!("Device not paired"));
+            }
+            if !device.capabilities.can_send(payload_type) {
+                return Err(anyhow::anyhow!("Device does not support {payload_type}"));
+            }
+            let config = device
+                .device
+                .extension_configs
+                .get(self.plugin.id())
+                .cloned();
+            if !self.plugin.should_send(&config, &body) {
+                return Err(anyhow::anyhow!("Plugin disabled for config"));
+            }
+            if let DeviceState::Active(_, _, sender) = &device.state {
+                sender.send_async(payload).await?;
+            } else {
+                return Err(anyhow::anyhow!("Device not connected"));
+            }
+        } else {
+            for device in device_manager.devices.values() {
+                let config = device
+                    .device
+                    .extension_configs
+                    .get(self.plugin.id())
+                    .cloned();
+                if device.device.paired
+                    && device.capabilities.can_send(payload_type)
+                    && self.plugin.should_send(&config, &body)
+                {
+                    if let DeviceState::Active(_, _, sender) = &device.state {
+                        if let Err(err) = sender.send_async(payload.clone()).await {
+                            warn!("Failed to send {err:?}")
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// GraphQL view of a registered [`ExtensionPlugin`].
+pub struct Extension(pub Arc<dyn ExtensionPlugin>);
+
+impl Extension {
+    fn handle(&self, context: &Context<'_>) -> anyhow::Result<ExtensionHandle> {
+        let device_manager = context
+            .data::<Arc<RwLock<DeviceManager>>>()
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        Ok(ExtensionHandle::new(self.0.clone(), device_manager.clone()))
+    }
+}
+
+#[Object]
+impl Extension {
+    pub async fn id(&self) -> &str {
+        self.0.id()
+    }
+
+    pub async fn incoming_capabilities(&self) -> Vec<String> {
+        self.0.incoming_capabilities()
+    }
+
+    pub async fn outgoing_capabilities(&self) -> Vec<String> {
+        self.0.outgoing_capabilities()
+    }
+
+    pub async fn config<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<Json<Option<Value>>> {
+        Ok(Json(self.handle(context)?.config(&device_id).await?))
+    }
+
+    pub async fn update_config<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        config: Json<Value>,
+    ) -> anyhow::Result<Json<Value>> {
+        self.handle(context)?
+            .set_config(&device_id, config.0.clone())
+            .await?;
+        Ok(config)
+    }
+
+    pub async fn state<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<Json<Value>> {
+        Ok(Json(self.handle(context)?.state(&device_id).await?))
+    }
+
+    /// Runs one of the plugin's own operations, see [`ExtensionPlugin::call`].
+    pub async fn call<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        method: String,
+        device_id: Option<String>,
+        args: Option<Json<Value>>,
+    ) -> anyhow::Result<Json<Value>> {
+        let handle = self.handle(context)?;
+        let args = args.map(|args| args.0).unwrap_or_default();
+        Ok(Json(self.0.call(handle, &method, device_id, args).await?))
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ExtensionPayload {
+    /// Id of the extension that parsed the payload.
+    pub plugin: String,
+    pub body: Json<Value>,
+}
@@ -0,0 +1,164 @@
+use std::sync::{Arc, RwLock};
+
+use async_graphql::{Context, InputObject, Object, SimpleObject};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{devices::Peer, utils::get_timestamp};
+
+use super::{Plugin, PluginAction, PluginExt};
+
+/// Called when a peer asks to find this machine, e.g. to play a sound.
+pub trait RingHandler: Send + Sync {
+    fn ring(&self, device_id: &str);
+}
+
+#[derive(Default)]
+pub struct FindMyPhone {
+    ring_handler: RwLock<Option<Arc<dyn RingHandler>>>,
+}
+
+#[Object]
+impl FindMyPhone {
+    pub async fn ring_device<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<&str> {
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.findmyphone.request",
+            FindMyPhonePayload::default(),
+        )
+        .await?;
+        Ok("success")
+    }
+}
+
+impl FindMyPhone {
+    pub fn set_ring_handler(&self, handler: Option<Arc<dyn RingHandler>>) {
+        *self.ring_handler.write().expect("ring handler poisoned") = handler;
+    }
+}
+
+impl Plugin for FindMyPhone {
+    type PluginPayload = FindMyPhonePayload;
+    type PluginConfig = FindMyPhoneConfig;
+    type PluginState = FindMyPhoneState;
+
+    fn init(_device_mangager: &crate::devices::DeviceManager) -> Self {
+        Self::default()
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec!["kdeconnect.findmyphone.request".to_string()]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec!["kdeconnect.findmyphone.request".to_string()]
+    }
+
+    async fn parse_payload(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        _config: &Option<Self::PluginConfig>,
+    ) -> Option<Self::PluginPayload> {
+        if payload.r#type == "kdeconnect.findmyphone.request" {
+            let mut payload = FindMyPhonePayload::default();
+            if !peer.paired {
+                warn!("Ignoring find request from unpaired device");
+                return Some(payload);
+            }
+            payload.handled = self
+                .ring_handler
+                .read()
+                .expect("ring handler poisoned")
+                .is_some();
+            payload.device_id = Some(peer.device_id.clone());
+            return Some(payload);
+        }
+        None
+    }
+
+    /// Only requests from paired devices are recorded.
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        if payload.device_id.is_some() {
+            state.last_request_at = Some(get_timestamp() as u64);
+        }
+    }
+
+    fn handle(
+        &self,
+        payload: &Self::PluginPayload,
+        _previous_state: &Self::PluginState,
+    ) -> Option<PluginAction> {
+        let device_id = payload.device_id.clone()?;
+        let handler = self
+            .ring_handler
+            .read()
+            .expect("ring handler poisoned")
+            .clone()?;
+        Some(PluginAction::Blocking(Box::new(move || {
+            info!("Ringing for {device_id}");
+            handler.ring(&device_id);
+        })))
+    }
+
+    fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+
+    fn should_send(
+        &self,
+        config: &Option<Self::PluginConfig>,
+        _state: &mut Self::PluginState,
+        _payload: &Self::PluginPayload,
+    ) -> bool {
+        if let Some(config) = config {
+            config.send_enabled
+        } else {
+            true
+        }
+    }
+}
+
+//https://github.com/KDE/kdeconnect-kde/tree/master/plugins/findmyphone
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Default)]
+pub struct FindMyPhonePayload {
+    /// Whether a ring handler is invoked for this request.
+    #[serde(skip)]
+    handled: bool,
+
+    /// Paired device that asked to ring, set when the request is honoured.
+    #[serde(skip)]
+    #[graphql(skip)]
+    device_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "FindMyPhoneConfigInput")]
+pub struct FindMyPhoneConfig {
+    enabled: bool,
+    send_enabled: bool,
+}
+
+impl Default for FindMyPhoneConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            send_enabled: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, SimpleObject)]
+pub struct FindMyPhoneState {
+    /// When the peer last asked to find this machine, in milliseconds since epoch.
+    pub last_request_at: Option<u64>,
+}
@@ -1,33 +1,57 @@
-use std::net::SocketAddr;
-use std::sync::Arc;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
 
 use anyhow::Ok;
-use async_graphql::{Context, OutputType, Union};
+use async_graphql::{Context, InputObject, InputType, OutputType, Union};
 use async_graphql::{Object, ObjectType, SimpleObject};
+use futures::future::BoxFuture;
 use paste::paste;
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 use tracing::warn;
 
-use crate::devices::{DeviceManager, DeviceState, DeviceWithState};
+use crate::devices::{DeviceManager, DeviceState, DeviceWithState, Peer};
 use crate::payloads::{IdentityPayloadBody, PairPayloadBody, Payload};
 
 use self::battery::Batttery;
+use self::connectivity_report::ConnectivityReport;
+use self::contacts::Contacts;
+use self::extension::{Extension, ExtensionPayload, Extensions};
+use self::findmyphone::FindMyPhone;
 use self::mousepad::Mousepad;
+use self::mpris::Mpris;
 use self::notification::Notification;
+use self::presenter::Presenter;
+use self::runcommand::RunCommand;
+use self::sftp::Sftp;
 use self::share::Share;
+use self::sms::Sms;
+use self::systemvolume::SystemVolume;
+use self::telephony::Telephony;
 use self::{clipboard::Clipboard, ping::Ping};
 
 pub mod battery;
 pub mod clipboard;
+pub mod connectivity_report;
+pub mod contacts;
+pub mod extension;
+pub mod findmyphone;
 pub mod mousepad;
+pub mod mpris;
 pub mod notification;
 pub mod ping;
+pub mod presenter;
+pub mod runcommand;
+pub mod sftp;
 pub mod share;
+pub mod sms;
+pub mod systemvolume;
+pub mod telephony;
 
 pub trait Plugin: async_graphql::ObjectType + Sized {
     type PluginPayload: ObjectType + Serialize;
-    type PluginConfig: OutputType + Clone + Serialize + Deserialize<'static> + Default;
+    type PluginConfig: OutputType + InputType + Clone + Serialize + Deserialize<'static> + Default;
     type PluginState: OutputType + Clone + Default;
 
     fn init(device_mangager: &DeviceManager) -> Self;
@@ -36,6 +60,11 @@ pub trait Plugin: async_graphql::ObjectType + Sized {
     fn outgoing_capabilities(&self) -> Vec<String>;
 
     fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool;
+
+    /// Rejects configs set through the api before they are stored.
+    fn validate_config(&self, _config: &Self::PluginConfig) -> anyhow::Result<()> {
+        Ok(())
+    }
     fn should_send(
         &self,
         config: &Option<Self::PluginConfig>,
@@ -43,20 +72,169 @@ pub trait Plugin: async_graphql::ObjectType + Sized {
         payload: &Self::PluginPayload,
     ) -> bool;
 
+    /// Records a payload once it was handed to the device's connection.
+    fn sent(&self, _payload: &Self::PluginPayload, _state: &mut Self::PluginState) {}
+
     fn parse_payload(
         &self,
         payload: &Payload,
-        device_address: SocketAddr,
+        peer: &Peer,
+        config: &Option<Self::PluginConfig>,
     ) -> impl std::future::Future<Output = Option<Self::PluginPayload>> + Send;
 
+    /// Reduces a received payload into the device state.
+    ///
+    /// Runs while the device manager is write locked, so it must not block or perform side
+    /// effects; those belong in [`Plugin::handle`].
     fn update_state(&self, _payload: &Self::PluginPayload, _state: &mut Self::PluginState) {}
+
+    /// Loads state kept across restarts, called once per known device at startup.
+    fn restore_state(
+        &self,
+        _device_id: &str,
+        _config: &Option<Self::PluginConfig>,
+        _state: &mut Self::PluginState,
+    ) {
+    }
+
+    /// Work to run when `device` connects, such as announcing local state.
+    fn connected(
+        &self,
+        _device: &DeviceWithState,
+        _config: &Option<Self::PluginConfig>,
+    ) -> Option<PluginAction> {
+        None
+    }
+
+    /// Answers request packets such as `kdeconnect.battery.request` before they are parsed.
+    ///
+    /// The reply goes back to the device the request came from; `None` passes the packet on to
+    /// [`Plugin::parse_payload`].
+    fn reply(
+        &self,
+        _payload: &Payload,
+        _peer: &Peer,
+        _config: &Option<Self::PluginConfig>,
+    ) -> impl std::future::Future<Output = anyhow::Result<Option<Reply>>> + Send {
+        async { Ok(None) }
+    }
+
+    /// Side effects of a received payload, executed after the locks are released.
+    ///
+    /// `previous_state` is the state the payload was reduced from.
+    fn handle(
+        &self,
+        _payload: &Self::PluginPayload,
+        _previous_state: &Self::PluginState,
+    ) -> Option<PluginAction> {
+        None
+    }
+
+    /// Whether subscribers see the payload, `false` for packets that only update the state.
+    fn emits(&self, _payload: &Self::PluginPayload) -> bool {
+        true
+    }
+}
+
+/// Work returned by [`Plugin::handle`].
+pub enum PluginAction {
+    Async(BoxFuture<'static, ()>),
+    /// Blocking work such as input injection, run on the blocking pool in order per device.
+    Blocking(BlockingWork),
+}
+
+type BlockingWork = Box<dyn FnOnce() + Send>;
+
+impl PluginAction {
+    /// Starts the action, queueing blocking work behind earlier work for `device_id`.
+    pub fn spawn(self, queues: &BlockingQueues, device_id: &str) {
+        match self {
+            PluginAction::Async(future) => {
+                tokio::spawn(future);
+            }
+            PluginAction::Blocking(work) => queues.push(device_id, work),
+        }
+    }
+}
+
+/// Per-device queues of blocking work, each drained by at most one blocking task at a time.
+#[derive(Default, Clone)]
+pub struct BlockingQueues {
+    /// A device has an entry while its queue is being drained.
+    queues: Arc<Mutex<HashMap<String, VecDeque<BlockingWork>>>>,
+}
+
+impl BlockingQueues {
+    pub fn push(&self, device_id: &str, work: BlockingWork) {
+        {
+            let mut queues = self.queues.lock().expect("blocking queues poisoned");
+            if let Some(queue) = queues.get_mut(device_id) {
+                queue.push_back(work);
+                return;
+            }
+            queues.insert(device_id.to_string(), VecDeque::new());
+        }
+        let queues = self.queues.clone();
+        let device_id = device_id.to_string();
+        tokio::task::spawn_blocking(move || {
+            let mut next = Some(work);
+            while let Some(work) = next {
+                if std::panic::catch_unwind(AssertUnwindSafe(work)).is_err() {
+                    warn!("Blocking work for {device_id} panicked");
+                }
+                let mut queues = queues.lock().expect("blocking queues poisoned");
+                next = queues.get_mut(&device_id).and_then(VecDeque::pop_front);
+                if next.is_none() {
+                    queues.remove(&device_id);
+                }
+            }
+        });
+    }
+}
+
+/// Packet returned by [`Plugin::reply`].
+pub struct Reply {
+    pub payload_type: String,
+    pub body: serde_json::Value,
+}
+
+impl Reply {
+    pub fn new(payload_type: &str, body: impl Serialize) -> anyhow::Result<Self> {
+        Ok(Self {
+            payload_type: payload_type.to_string(),
+            body: serde_json::to_value(body)?,
+        })
+    }
+
+    async fn send(self, request: &Payload, peer: &Peer) -> anyhow::Result<AnsweredRequest> {
+        peer.send(&self.payload_type, self.body).await?;
+        Ok(AnsweredRequest {
+            request_type: request.r#type.clone(),
+            reply_type: self.payload_type,
+        })
+    }
+}
+
+/// A request packet that was answered by [`Plugin::reply`].
+#[derive(SimpleObject)]
+pub struct AnsweredRequest {
+    pub request_type: String,
+    pub reply_type: String,
 }
 
 trait PluginExt: Plugin {
     fn get_config_from_plugin_configs(configs: &PluginConfigs) -> &Option<Self::PluginConfig>;
 
+    fn get_config_mut_from_plugin_configs(
+        configs: &mut PluginConfigs,
+    ) -> &mut Option<Self::PluginConfig>;
+
     fn get_state_from_plugin_states(configs: &mut PluginStates) -> &mut Self::PluginState;
 
+    /// Name of the plugin's field in [`PluginConfigs`].
+    fn config_key() -> &'static str;
+
+    #[allow(dead_code)]
     fn get_config<'ctx>(
         &self,
         context: &Context<'ctx>,
@@ -75,7 +253,67 @@ trait PluginExt: Plugin {
             let device = device_manager
                 .get(device_id)
                 .ok_or(anyhow::anyhow!("Device not found with given id"))?;
-            Ok(Self::get_config_from_plugin_configs(&device.device.plugin_configs).clone())
+            Ok(Self::get_config_from_plugin_configs(&device.device.effective_configs).clone())
+        }
+    }
+
+    fn get_state<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: &str,
+    ) -> impl std::future::Future<Output = anyhow::Result<Self::PluginState>> + Send {
+        async move {
+            let mut plugin_states = {
+                context
+                    .data::<Arc<RwLock<DeviceManager>>>()
+                    .map_err(|e| anyhow::anyhow!("{e:?}"))?
+                    .read()
+                    .await
+                    .devices
+                    .get(device_id)
+                    .ok_or(anyhow::anyhow!("Device not found with given id"))?
+                    .device
+                    .plugin_states
+                    .clone()
+            };
+            Ok(Self::get_state_from_plugin_states(&mut plugin_states).clone())
+        }
+    }
+
+    /// Applies `update` to the device's effective config and persists the result as an override.
+    ///
+    /// Devices without a config start from [`Default`], which like `None` is enabled.
+    fn update_config<'ctx, F>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: &str,
+        update: F,
+    ) -> impl std::future::Future<Output = anyhow::Result<Self::PluginConfig>> + Send
+    where
+        F: FnOnce(&mut Self::PluginConfig) -> anyhow::Result<()> + Send,
+    {
+        async move {
+            let mut device_manager = context
+                .data::<Arc<RwLock<DeviceManager>>>()
+                .map_err(|e| anyhow::anyhow!("{e:?}"))?
+                .write()
+                .await;
+            let device = device_manager
+                .devices
+                .get_mut(device_id)
+                .ok_or(anyhow::anyhow!("Device not found with given id"))?;
+            let mut updated =
+                Self::get_config_from_plugin_configs(&device.device.effective_configs)
+                    .clone()
+                    .unwrap_or_default();
+            update(&mut updated)?;
+            self.validate_config(&updated)?;
+            *Self::get_config_mut_from_plugin_configs(&mut device.device.plugin_configs) =
+                Some(updated.clone());
+            device_manager.resolve_configs(device_id);
+            device_manager.save().await?;
+            device_manager.notify_config_changed(device_id, vec![Self::config_key().to_string()]);
+            Ok(updated)
         }
     }
 
@@ -87,11 +325,24 @@ trait PluginExt: Plugin {
         payload: Self::PluginPayload,
     ) -> impl std::future::Future<Output = anyhow::Result<()>> + Send {
         async move {
-            let mut device_manager = context
+            let device_manager = context
                 .data::<Arc<RwLock<DeviceManager>>>()
-                .map_err(|e| anyhow::anyhow!("{e:?}"))?
-                .write()
-                .await;
+                .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+            self.send_payload_with(device_manager, device_id, payload_type, payload)
+                .await
+        }
+    }
+
+    /// [`PluginExt::send_payload`] for work running outside a GraphQL request.
+    fn send_payload_with(
+        &self,
+        device_manager: &RwLock<DeviceManager>,
+        device_id: Option<&str>,
+        payload_type: &str,
+        payload: Self::PluginPayload,
+    ) -> impl std::future::Future<Output = anyhow::Result<()>> + Send {
+        async move {
+            let mut device_manager = device_manager.write().await;
             let devices = &mut device_manager.devices;
             let serialized_value = serde_json::to_value(&payload)?;
             let serialized_payload = Payload::generate_new(payload_type, serialized_value);
@@ -102,8 +353,11 @@ trait PluginExt: Plugin {
                 if !device.device.paired {
                     return Err(anyhow::anyhow!("Device not paired"));
                 }
+                if !device.capabilities.can_send(payload_type) {
+                    return Err(anyhow::anyhow!("Device does not support {payload_type}"));
+                }
                 if !self.should_send(
-                    Self::get_config_from_plugin_configs(&device.device.plugin_configs),
+                    Self::get_config_from_plugin_configs(&device.device.effective_configs),
                     Self::get_state_from_plugin_states(&mut device.device.plugin_states),
                     &payload,
                 ) {
@@ -111,14 +365,19 @@ trait PluginExt: Plugin {
                 }
                 if let DeviceState::Active(_, _, sender) = &device.state {
                     sender.send_async(serialized_payload).await?;
+                    self.sent(
+                        &payload,
+                        Self::get_state_from_plugin_states(&mut device.device.plugin_states),
+                    );
                 } else {
                     return Err(anyhow::anyhow!("Device not connected"));
                 }
             } else {
                 for (_, device) in devices.iter_mut() {
                     if device.device.paired
+                        && device.capabilities.can_send(payload_type)
                         && self.should_send(
-                            Self::get_config_from_plugin_configs(&device.device.plugin_configs),
+                            Self::get_config_from_plugin_configs(&device.device.effective_configs),
                             Self::get_state_from_plugin_states(&mut device.device.plugin_states),
                             &payload,
                         )
@@ -126,6 +385,13 @@ trait PluginExt: Plugin {
                         if let DeviceState::Active(_, _, sender) = &device.state {
                             if let Err(err) = sender.send_async(serialized_payload.clone()).await {
                                 warn!("Failed to send {err:?}")
+                            } else {
+                                self.sent(
+                                    &payload,
+                                    Self::get_state_from_plugin_states(
+                                        &mut device.device.plugin_states,
+                                    ),
+                                );
                             }
                         }
                     }
@@ -144,12 +410,25 @@ macro_rules! register_plugins {
                 pub device_name: String,
                 pub device_id: String,
                 pub device_type: String,
+                /// Plugins registered at runtime, see [`extension::ExtensionPlugin`].
+                pub extensions: Extensions,
+                pub blocking_queues: BlockingQueues,
                 $(
                     pub [<$type:lower>]: $type,
                 )*
             }
 
-            #[derive(Debug,Serialize,Deserialize,Default,Clone, SimpleObject)]
+            /// Plugin handling a packet type, see [`DeviceCapabilities`].
+            #[derive(Debug, Clone, PartialEq, Eq)]
+            pub enum PluginRoute {
+                $(
+                    $type,
+                )*
+                Extension(String),
+            }
+
+            #[derive(Debug,Serialize,Deserialize,Default,Clone, SimpleObject, InputObject)]
+            #[graphql(input_name = "PluginConfigsInput")]
             #[serde(default)]
             pub struct PluginConfigs{
                 $(
@@ -157,6 +436,39 @@ macro_rules! register_plugins {
                 )*
             }
 
+            impl PluginConfigs {
+                /// Takes each plugin's config from `self`, or from `fallback` when unset.
+                pub fn or(&self, fallback: &PluginConfigs) -> PluginConfigs {
+                    PluginConfigs {
+                        $(
+                            [<$type:lower>]: self.[<$type:lower>].clone().or_else(|| fallback.[<$type:lower>].clone()),
+                        )*
+                    }
+                }
+
+                /// Keys of the plugins with a config set.
+                pub fn keys(&self) -> Vec<String> {
+                    let mut keys = vec![];
+                    $(
+                        if self.[<$type:lower>].is_some() {
+                            keys.push(stringify!([<$type:lower>]).to_string());
+                        }
+                    )*
+                    keys
+                }
+
+                /// Unsets the config of the plugin named `key`, returning whether such a plugin exists.
+                pub fn clear(&mut self, key: &str) -> bool {
+                    $(
+                        if key == stringify!([<$type:lower>]) {
+                            self.[<$type:lower>] = None;
+                            return true;
+                        }
+                    )*
+                    false
+                }
+            }
+
             #[derive(Debug, Default,Clone, SimpleObject)]
             pub struct PluginStates{
                 $(
@@ -171,6 +483,78 @@ macro_rules! register_plugins {
                         &self.[<$type:lower>]
                     }
                 )*
+
+                /// Replaces the configs of the plugins set in `configs`, leaving the others as they are.
+                pub async fn update_plugin_config<'ctx>(
+                    &self,
+                    context: &Context<'ctx>,
+                    device_id: String,
+                    configs: PluginConfigs,
+                ) -> anyhow::Result<PluginConfigs> {
+                    self.validate_configs(&configs)?;
+                    let mut device_manager = context
+                        .data::<Arc<RwLock<DeviceManager>>>()
+                        .map_err(|e| anyhow::anyhow!("{e:?}"))?
+                        .write()
+                        .await;
+                    let device = device_manager
+                        .devices
+                        .get_mut(&device_id)
+                        .ok_or(anyhow::anyhow!("Device not found with given id"))?;
+                    let changed = configs.keys();
+                    device.device.plugin_configs = configs.or(&device.device.plugin_configs);
+                    device_manager.resolve_configs(&device_id);
+                    device_manager.save().await?;
+                    device_manager.notify_config_changed(&device_id, changed);
+                    device_manager
+                        .devices
+                        .get(&device_id)
+                        .map(|device| device.device.effective_configs.clone())
+                        .ok_or(anyhow::anyhow!("Device not found with given id"))
+                }
+
+                /// Drops the device's own configs of `plugins`, falling back to its profile and the
+                /// global defaults.
+                pub async fn clear_plugin_config<'ctx>(
+                    &self,
+                    context: &Context<'ctx>,
+                    device_id: String,
+                    plugins: Vec<String>,
+                ) -> anyhow::Result<PluginConfigs> {
+                    let mut device_manager = context
+                        .data::<Arc<RwLock<DeviceManager>>>()
+                        .map_err(|e| anyhow::anyhow!("{e:?}"))?
+                        .write()
+                        .await;
+                    let device = device_manager
+                        .devices
+                        .get_mut(&device_id)
+                        .ok_or(anyhow::anyhow!("Device not found with given id"))?;
+                    for plugin in &plugins {
+                        if !device.device.plugin_configs.clear(plugin) {
+                            return Err(anyhow::anyhow!("No plugin named {plugin:?}"));
+                        }
+                    }
+                    device_manager.resolve_configs(&device_id);
+                    device_manager.save().await?;
+                    device_manager.notify_config_changed(&device_id, plugins);
+                    device_manager
+                        .devices
+                        .get(&device_id)
+                        .map(|device| device.device.effective_configs.clone())
+                        .ok_or(anyhow::anyhow!("Device not found with given id"))
+                }
+
+                pub async fn extensions(&self) -> Vec<Extension> {
+                    self.extensions.all().into_iter().map(Extension).collect()
+                }
+
+                pub async fn extension(&self, id: String) -> anyhow::Result<Extension> {
+                    self.extensions
+                        .get(&id)
+                        .map(Extension)
+                        .ok_or(anyhow::anyhow!("No extension with given id"))
+                }
             }
 
             impl PluginManager {
@@ -180,13 +564,49 @@ macro_rules! register_plugins {
                     $(
                         capabilities.extend(self.[<$type:lower>].incoming_capabilities());
                     )*
+                    for extension in self.extensions.all() {
+                        capabilities.extend(extension.incoming_capabilities());
+                    }
                     capabilities
                 }
+                /// Checks every config set in `configs` with its plugin.
+                pub fn validate_configs(&self, configs: &PluginConfigs) -> anyhow::Result<()> {
+                    $(
+                        if let Some(config) = &configs.[<$type:lower>] {
+                            self.[<$type:lower>]
+                                .validate_config(config)
+                                .map_err(|e| anyhow::anyhow!("Invalid {} config: {e}", $type::config_key()))?;
+                        }
+                    )*
+                    Ok(())
+                }
+
+                /// Negotiates the capabilities of a connection with the peer's identity.
+                pub fn capabilities_for(&self, identity: &IdentityPayloadBody) -> DeviceCapabilities {
+                    let mut routes = HashMap::new();
+                    $(
+                        for capability in self.[<$type:lower>].incoming_capabilities() {
+                            routes.entry(capability).or_insert(PluginRoute::$type);
+                        }
+                    )*
+                    for extension in self.extensions.all() {
+                        for capability in extension.incoming_capabilities() {
+                            routes
+                                .entry(capability)
+                                .or_insert_with(|| PluginRoute::Extension(extension.id().to_string()));
+                        }
+                    }
+                    DeviceCapabilities::negotiate(routes, self.outgoing_capabilities(), identity)
+                }
+
                 pub fn outgoing_capabilities(&self) -> Vec<String>{
                     let mut capabilities = vec![];
                     $(
                         capabilities.extend(self.[<$type:lower>].outgoing_capabilities());
                     )*
+                    for extension in self.extensions.all() {
+                        capabilities.extend(extension.outgoing_capabilities());
+                    }
                     capabilities
                 }
             }
@@ -200,6 +620,8 @@ macro_rules! register_plugins {
                 $(
                     $type(<$type as Plugin>::PluginPayload),
                 )*
+                Extension(ExtensionPayload),
+                Request(AnsweredRequest),
                 Unknown(Payload)
             }
 
@@ -220,6 +642,8 @@ macro_rules! register_plugins {
                         device_id,
                         device_name,
                         device_type,
+                        extensions: Extensions::default(),
+                        blocking_queues: BlockingQueues::default(),
                         $(
                             [<$type:lower>]: <$type as Plugin>::init(&device_manager),
                         )*
@@ -235,30 +659,105 @@ macro_rules! register_plugins {
                         let pair = serde_json::from_value::<PairPayloadBody>(payload.body)?;
                       return  Ok(ReceivedPayload::Pair(pair))
                     }
-                    if let Some(device) = device {
-                        $(
-                            if let DeviceState::Active(_,address,_) = device.state {
-                                if self.[<$type:lower>].is_enabled($type::get_config_from_plugin_configs(&device.device.plugin_configs)) {
-                                    if let Some([<$type:lower _payload>]) = self.[<$type:lower>].parse_payload(&payload,address).await {
-                                        return Ok(ReceivedPayload::$type([<$type:lower _payload>]))
+                    if let Some((device, peer)) = device.and_then(|device| Some((device, device.peer()?))) {
+                        match device.capabilities.route(&payload.r#type) {
+                            $(
+                                Some(PluginRoute::$type) => {
+                                    let config = $type::get_config_from_plugin_configs(&device.device.effective_configs);
+                                    if self.[<$type:lower>].is_enabled(config) {
+                                        if let Some(reply) = self.[<$type:lower>].reply(&payload, &peer, config).await? {
+                                            return Ok(ReceivedPayload::Request(reply.send(&payload, &peer).await?))
+                                        }
+                                        if let Some([<$type:lower _payload>]) = self.[<$type:lower>].parse_payload(&payload,&peer,config).await {
+                                            return Ok(ReceivedPayload::$type([<$type:lower _payload>]))
+                                        }
+                                    }
+                                }
+                            )*
+                            Some(PluginRoute::Extension(id)) => {
+                                if let Some(extension) = self.extensions.get(id) {
+                                    let config = device.device.extension_configs.get(id).cloned();
+                                    if extension.is_enabled(&config) {
+                                        if let Some(reply) = extension.reply(&payload, &peer, &config).await? {
+                                            return Ok(ReceivedPayload::Request(reply.send(&payload, &peer).await?))
+                                        }
+                                        if let Some(body) = extension.parse_payload(&payload, &peer, &config).await {
+                                            return Ok(ReceivedPayload::Extension(ExtensionPayload {
+                                                plugin: id.clone(),
+                                                body: async_graphql::Json(body),
+                                            }))
+                                        }
                                     }
                                 }
                             }
-                        )*
+                            None => {}
+                        }
                     }
                     Ok(ReceivedPayload::Unknown(payload))
                 }
 
-                pub fn update_state(&self,payload:&ReceivedPayload, device:&mut DeviceWithState){
+                /// Restores persisted plugin states of every known device.
+                pub fn restore_states(&self, device_manager: &mut DeviceManager) {
+                    for device in device_manager.devices.values_mut() {
+                        $(
+                            let config = $type::get_config_from_plugin_configs(&device.device.effective_configs);
+                            self.[<$type:lower>].restore_state(
+                                &device.device.id,
+                                config,
+                                &mut device.device.plugin_states.[<$type:lower>],
+                            );
+                        )*
+                    }
+                }
+
+                /// Connection hooks of the enabled plugins for a device that just connected.
+                pub fn connected(&self, device: &DeviceWithState) -> Vec<PluginAction> {
+                    let mut actions = vec![];
+                    $(
+                        let config = $type::get_config_from_plugin_configs(&device.device.effective_configs);
+                        if self.[<$type:lower>].is_enabled(config) {
+                            actions.extend(self.[<$type:lower>].connected(device, config));
+                        }
+                    )*
+                    for extension in self.extensions.all() {
+                        let config = device.device.extension_configs.get(extension.id()).cloned();
+                        if extension.is_enabled(&config) {
+                            actions.extend(extension.connected(device, &config));
+                        }
+                    }
+                    actions
+                }
+
+                /// Reduces the payload into the device state, returning side effects to run once unlocked.
+                pub fn update_state(&self,payload:&ReceivedPayload, device:&mut DeviceWithState) -> Option<PluginAction> {
                     match payload{
                         $(
                             ReceivedPayload::$type(data) => {
                                 let state = $type::get_state_from_plugin_states(&mut device.device.plugin_states);
+                                let previous_state = state.clone();
                                 self.[<$type:lower>].update_state(&data, state);
+                                self.[<$type:lower>].handle(&data, &previous_state)
                             }
-                        )*,
-                        _ => {}
+                        )*
+                        ReceivedPayload::Extension(data) => {
+                            let extension = self.extensions.get(&data.plugin)?;
+                            let state = device.device.extension_states.entry(data.plugin.clone()).or_default();
+                            let previous_state = state.clone();
+                            extension.update_state(&data.body, state);
+                            extension.handle(&data.body, &previous_state)
+                        }
+                        _ => None
+
+                    }
+                }
 
+                /// Whether the payload is passed on to subscribers, see [`Plugin::emits`].
+                pub fn emits(&self, payload: &ReceivedPayload) -> bool {
+                    match payload {
+                        $(
+                            ReceivedPayload::$type(data) => self.[<$type:lower>].emits(data),
+                        )*
+                        _ => true
                     }
                 }
             }
@@ -269,10 +768,18 @@ macro_rules! register_plugins {
                         &configs.[<$type:lower>]
                     }
 
+                    fn get_config_mut_from_plugin_configs(configs: &mut PluginConfigs) -> &mut Option<Self::PluginConfig> {
+                        &mut configs.[<$type:lower>]
+                    }
+
                     fn get_state_from_plugin_states(states: &mut PluginStates) -> &mut Self::PluginState {
                         &mut states.[<$type:lower>]
                     }
 
+                    fn config_key() -> &'static str {
+                        stringify!([<$type:lower>])
+                    }
+
                 }
             )*
 
@@ -282,6 +789,50 @@ macro_rules! register_plugins {
     };
 }
 
+/// Packet types usable on a connection: what local plugins handle intersected with what the peer
+/// declared in its identity.
+#[derive(Debug, Clone, Default, SimpleObject)]
+pub struct DeviceCapabilities {
+    /// Packets the peer may send that a local plugin handles.
+    pub incoming: BTreeSet<String>,
+    /// Packets local plugins may send that the peer handles.
+    pub outgoing: BTreeSet<String>,
+    #[graphql(skip)]
+    routes: HashMap<String, PluginRoute>,
+}
+
+impl DeviceCapabilities {
+    /// Peers declaring no capabilities at all predate capability lists and get everything.
+    fn negotiate(
+        mut routes: HashMap<String, PluginRoute>,
+        outgoing: Vec<String>,
+        identity: &IdentityPayloadBody,
+    ) -> Self {
+        let legacy =
+            identity.incoming_capabilities.is_empty() && identity.outgoing_capabilities.is_empty();
+        if !legacy {
+            routes.retain(|capability, _| identity.outgoing_capabilities.contains(capability));
+        }
+        let outgoing = outgoing
+            .into_iter()
+            .filter(|capability| legacy || identity.incoming_capabilities.contains(capability))
+            .collect();
+        Self {
+            incoming: routes.keys().cloned().collect(),
+            outgoing,
+            routes,
+        }
+    }
+
+    pub fn route(&self, payload_type: &str) -> Option<&PluginRoute> {
+        self.routes.get(payload_type)
+    }
+
+    pub fn can_send(&self, payload_type: &str) -> bool {
+        self.outgoing.contains(payload_type)
+    }
+}
+
 impl PluginManager {
     pub fn get_identity_payload(&self, port: Option<u16>) -> anyhow::Result<Payload> {
         let value = serde_json::to_value(self.get_identity_payload_body(port))
@@ -302,4 +853,21 @@ impl PluginManager {
     }
 }
 
-register_plugins!(Ping, Clipboard, Batttery, Notification, Share, Mousepad);
+register_plugins!(
+    Ping,
+    Clipboard,
+    Batttery,
+    Notification,
+    Share,
+    Mousepad,
+    Mpris,
+    RunCommand,
+    FindMyPhone,
+    Sftp,
+    Telephony,
+    Sms,
+    Contacts,
+    Presenter,
+    ConnectivityReport,
+    SystemVolume
+);
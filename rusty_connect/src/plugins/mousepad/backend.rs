@@ -0,0 +1,134 @@
+use std::sync::{mpsc, Arc, Mutex};
+
+use async_graphql::Enum;
+use enigo::{Key, KeyboardControllable, MouseButton, MouseControllable};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// A single input event to inject, see [`super::input_actions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputAction {
+    KeyDown(Key),
+    KeyUp(Key),
+    KeyClick(Key),
+    Text(String),
+    MouseDown(MouseButton),
+    MouseUp(MouseButton),
+    MouseClick(MouseButton),
+    MoveRelative {
+        dx: i32,
+        dy: i32,
+    },
+    /// Positive scrolls right.
+    ScrollX(i32),
+    /// Positive scrolls down.
+    ScrollY(i32),
+}
+
+/// Injects input events into the local session.
+pub trait InputBackend: Send + Sync {
+    fn apply(&self, actions: &[InputAction]) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Enum, Serialize, Deserialize)]
+pub enum InputBackendKind {
+    /// X11, Windows and macOS through enigo.
+    #[default]
+    Enigo,
+    /// A virtual `/dev/uinput` device, works on Wayland compositors.
+    Uinput,
+    /// Only records events, nothing is injected.
+    Recording,
+}
+
+/// Owns an enigo instance on a dedicated thread, since it is not `Send` on every platform.
+pub struct EnigoBackend {
+    sender: Mutex<mpsc::Sender<Vec<InputAction>>>,
+}
+
+impl EnigoBackend {
+    pub fn new() -> anyhow::Result<Self> {
+        let (sender, receiver) = mpsc::channel::<Vec<InputAction>>();
+        std::thread::Builder::new()
+            .name("enigo-input".to_string())
+            .spawn(move || {
+                let mut enigo = enigo::Enigo::new();
+                while let Ok(actions) = receiver.recv() {
+                    for action in actions {
+                        Self::run(&mut enigo, action);
+                    }
+                }
+            })?;
+        Ok(Self {
+            sender: Mutex::new(sender),
+        })
+    }
+
+    fn run(enigo: &mut enigo::Enigo, action: InputAction) {
+        match action {
+            InputAction::KeyDown(key) => enigo.key_down(key),
+            InputAction::KeyUp(key) => enigo.key_up(key),
+            InputAction::KeyClick(key) => enigo.key_click(key),
+            InputAction::Text(text) => enigo.key_sequence(&text),
+            InputAction::MouseDown(button) => enigo.mouse_down(button),
+            InputAction::MouseUp(button) => enigo.mouse_up(button),
+            InputAction::MouseClick(button) => enigo.mouse_click(button),
+            InputAction::MoveRelative { dx, dy } => enigo.mouse_move_relative(dx, dy),
+            InputAction::ScrollX(length) => enigo.mouse_scroll_x(length),
+            InputAction::ScrollY(length) => enigo.mouse_scroll_y(length),
+        }
+    }
+}
+
+impl InputBackend for EnigoBackend {
+    fn apply(&self, actions: &[InputAction]) -> anyhow::Result<()> {
+        self.sender
+            .lock()
+            .expect("enigo sender poisoned")
+            .send(actions.to_vec())
+            .map_err(|_| anyhow::anyhow!("Enigo input thread stopped"))
+    }
+}
+
+#[derive(Default)]
+pub struct RecordingBackend {
+    events: Mutex<Vec<InputAction>>,
+}
+
+impl RecordingBackend {
+    /// Returns and clears everything recorded so far.
+    pub fn take(&self) -> Vec<InputAction> {
+        std::mem::take(&mut *self.events.lock().expect("recorded events poisoned"))
+    }
+}
+
+impl InputBackend for RecordingBackend {
+    fn apply(&self, actions: &[InputAction]) -> anyhow::Result<()> {
+        self.events
+            .lock()
+            .expect("recorded events poisoned")
+            .extend_from_slice(actions);
+        Ok(())
+    }
+}
+
+/// Creates the backend for `kind`, falling back to enigo when it is unavailable.
+pub fn create_backend(kind: InputBackendKind) -> anyhow::Result<Arc<dyn InputBackend>> {
+    match kind {
+        InputBackendKind::Enigo => Ok(Arc::new(EnigoBackend::new()?)),
+        InputBackendKind::Recording => Ok(Arc::new(RecordingBackend::default())),
+        #[cfg(target_os = "linux")]
+        InputBackendKind::Uinput => match super::uinput::UinputBackend::new() {
+            Ok(backend) => Ok(Arc::new(backend)),
+            Err(err) => {
+                warn!("Cannot create uinput device, using enigo {err:?}");
+                Ok(Arc::new(EnigoBackend::new()?))
+            }
+        },
+        #[cfg(not(target_os = "linux"))]
+        InputBackendKind::Uinput => {
+            warn!("uinput is only available on linux, using enigo");
+            Ok(Arc::new(EnigoBackend::new()?))
+        }
+    }
+}
@@ -0,0 +1,571 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_graphql::{Context, Enum, InputObject, Object, SimpleObject};
+use enigo::{Key, MouseButton};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::devices::Peer;
+
+use super::{Plugin, PluginAction, PluginExt};
+
+pub use self::backend::{
+    create_backend, EnigoBackend, InputAction, InputBackend, InputBackendKind, RecordingBackend,
+};
+
+pub mod backend;
+#[cfg(target_os = "linux")]
+pub mod uinput;
+
+type Backends = Arc<Mutex<HashMap<InputBackendKind, Arc<dyn InputBackend>>>>;
+
+#[derive(Default)]
+pub struct Mousepad {
+    /// Backends created so far, shared by every device using the same kind.
+    backends: Backends,
+}
+
+fn backend_for(
+    backends: &Backends,
+    kind: InputBackendKind,
+) -> anyhow::Result<Arc<dyn InputBackend>> {
+    let mut backends = backends.lock().expect("input backends poisoned");
+    if let Some(backend) = backends.get(&kind) {
+        return Ok(backend.clone());
+    }
+    let backend = create_backend(kind)?;
+    backends.insert(kind, backend.clone());
+    Ok(backend)
+}
+
+/// KDE Connect special key codes. Code 3 is the X11 linefeed, which peers send as return,
+/// 15 and 16 are platform specific and 17 to 20 are reserved by the protocol.
+static SPECIAL_KEYS_MAP: phf::Map<u32, Key> = phf::phf_map! {
+    1_u32 => Key::Backspace,
+    2_u32 => Key::Tab,
+    3_u32 => Key::Return,
+
+    4_u32 => Key::LeftArrow,
+    5_u32 => Key::UpArrow,
+    6_u32 => Key::RightArrow,
+    7_u32 => Key::DownArrow,
+
+    8_u32 => Key::PageUp,
+    9_u32 => Key::PageDown,
+
+    10_u32 => Key::Home,
+    11_u32 => Key::End,
+
+    12_u32 => Key::Return,
+
+    13_u32 => Key::Delete,
+
+    14_u32 => Key::Escape,
+
+    21_u32 => Key::F1,
+    22_u32 => Key::F2,
+    23_u32 => Key::F3,
+    24_u32 => Key::F4,
+    25_u32 => Key::F5,
+    26_u32 => Key::F6,
+    27_u32 => Key::F7,
+    28_u32 => Key::F8,
+    29_u32 => Key::F9,
+    30_u32 => Key::F10,
+    31_u32 => Key::F11,
+    32_u32 => Key::F12,
+};
+
+/// Looks up a special key code, including keys enigo only has on some platforms.
+pub fn special_key(code: u32) -> Option<Key> {
+    if let Some(key) = SPECIAL_KEYS_MAP.get(&code) {
+        return Some(*key);
+    }
+    match code {
+        #[cfg(target_os = "linux")]
+        15 => Some(Key::SysReq),
+        #[cfg(target_os = "linux")]
+        16 => Some(Key::ScrollLock),
+        #[cfg(target_os = "windows")]
+        16 => Some(Key::Scroll),
+        _ => None,
+    }
+}
+
+/// Translates a `kdeconnect.mousepad.request` into the input events to inject.
+pub fn input_actions(payload: &MousepadPayload, state: &MousepadState) -> Vec<InputAction> {
+    let mut actions = vec![];
+    if payload.config.handle_mouse_events {
+        if payload.singleclick == Some(true) {
+            actions.push(InputAction::MouseClick(MouseButton::Left));
+        }
+        if payload.rightclick == Some(true) {
+            actions.push(InputAction::MouseClick(MouseButton::Right));
+        }
+        if payload.middleclick == Some(true) {
+            actions.push(InputAction::MouseClick(MouseButton::Middle));
+        }
+        if payload.doubleclick == Some(true) {
+            actions.push(InputAction::MouseClick(MouseButton::Left));
+            actions.push(InputAction::MouseClick(MouseButton::Left));
+        }
+        if payload.singlehold == Some(true) && !state.left_button_held {
+            actions.push(InputAction::MouseDown(MouseButton::Left));
+        }
+        if payload.singlerelease == Some(true) && state.left_button_held {
+            actions.push(InputAction::MouseUp(MouseButton::Left));
+        }
+        let (dx, dy) = (
+            payload.dx.unwrap_or_default(),
+            payload.dy.unwrap_or_default(),
+        );
+        if payload.scroll == Some(true) {
+            // One notch per packet along the dominant axis; peers send positive dy to scroll up.
+            if dy.abs() >= dx.abs() && dy != 0.0 {
+                actions.push(InputAction::ScrollY(-dy.signum() as i32));
+            } else if dx != 0.0 {
+                actions.push(InputAction::ScrollX(dx.signum() as i32));
+            }
+        } else if dx != 0.0 || dy != 0.0 {
+            actions.push(InputAction::MoveRelative {
+                dx: dx.round() as i32,
+                dy: dy.round() as i32,
+            });
+        }
+    }
+    if payload.config.handle_keyboard_events {
+        let key = payload.special_key.and_then(special_key);
+        let text = payload.key.as_ref().filter(|key| !key.is_empty());
+        if key.is_none() && text.is_none() {
+            return actions;
+        }
+        let modifiers = [
+            (payload.ctrl, Key::Control),
+            (payload.alt, Key::Alt),
+            (payload.shift, Key::Shift),
+            (payload.super_key, Key::Meta),
+        ]
+        .into_iter()
+        .filter_map(|(pressed, key)| (pressed == Some(true)).then_some(key))
+        .collect::<Vec<_>>();
+        actions.extend(modifiers.iter().map(|key| InputAction::KeyDown(*key)));
+        if let Some(key) = key {
+            actions.push(InputAction::KeyClick(key));
+        } else if let Some(text) = text {
+            actions.push(InputAction::Text(text.clone()));
+        }
+        actions.extend(modifiers.iter().rev().map(|key| InputAction::KeyUp(*key)));
+    }
+    actions
+}
+
+#[Object]
+impl Mousepad {
+    pub async fn send_mouse_move<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        dx: f32,
+        dy: f32,
+    ) -> anyhow::Result<&str> {
+        let payload = MousepadPayload {
+            dx: Some(dx),
+            dy: Some(dy),
+            ..Default::default()
+        };
+        self.send_request(context, &device_id, payload).await
+    }
+
+    pub async fn send_scroll<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        dx: f32,
+        dy: f32,
+    ) -> anyhow::Result<&str> {
+        let payload = MousepadPayload {
+            dx: Some(dx),
+            dy: Some(dy),
+            scroll: Some(true),
+            ..Default::default()
+        };
+        self.send_request(context, &device_id, payload).await
+    }
+
+    pub async fn send_click<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        click: MouseClick,
+    ) -> anyhow::Result<&str> {
+        let mut payload = MousepadPayload::default();
+        match click {
+            MouseClick::Single => payload.singleclick = Some(true),
+            MouseClick::Double => payload.doubleclick = Some(true),
+            MouseClick::Middle => payload.middleclick = Some(true),
+            MouseClick::Right => payload.rightclick = Some(true),
+            MouseClick::Hold => payload.singlehold = Some(true),
+            MouseClick::Release => payload.singlerelease = Some(true),
+        }
+        self.send_request(context, &device_id, payload).await
+    }
+
+    /// Types `key` or presses `special_key`; with `send_ack` the peer echoes the packet back.
+    pub async fn send_key<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        key: Option<String>,
+        special_key: Option<u32>,
+        modifiers: Option<KeyModifiers>,
+        send_ack: Option<bool>,
+    ) -> anyhow::Result<&str> {
+        if key.is_none() && special_key.is_none() {
+            return Err(anyhow::anyhow!("Either key or specialKey is required"));
+        }
+        let modifiers = modifiers.unwrap_or_default();
+        let payload = MousepadPayload {
+            key,
+            special_key,
+            shift: modifiers.shift,
+            ctrl: modifiers.ctrl,
+            alt: modifiers.alt,
+            super_key: modifiers.super_key,
+            send_ack,
+            ..Default::default()
+        };
+        self.send_request(context, &device_id, payload).await
+    }
+
+    /// Tells the peer whether this machine currently accepts keyboard input.
+    pub async fn send_keyboard_state<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        state: bool,
+    ) -> anyhow::Result<&str> {
+        let payload = MousepadPayload {
+            state: Some(state),
+            ..Default::default()
+        };
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.mousepad.keyboardstate",
+            payload,
+        )
+        .await?;
+        Ok("success")
+    }
+}
+
+impl Mousepad {
+    /// Returns the backend of `kind`, creating it on first use.
+    pub fn backend(&self, kind: InputBackendKind) -> anyhow::Result<Arc<dyn InputBackend>> {
+        backend_for(&self.backends, kind)
+    }
+
+    /// Replaces the backend used for `kind`, e.g. with a [`RecordingBackend`].
+    pub fn set_backend(&self, kind: InputBackendKind, backend: Arc<dyn InputBackend>) {
+        self.backends
+            .lock()
+            .expect("input backends poisoned")
+            .insert(kind, backend);
+    }
+
+    async fn send_request<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: &str,
+        payload: MousepadPayload,
+    ) -> anyhow::Result<&str> {
+        self.send_payload(
+            context,
+            Some(device_id),
+            "kdeconnect.mousepad.request",
+            payload,
+        )
+        .await?;
+        Ok("success")
+    }
+}
+
+impl Plugin for Mousepad {
+    type PluginPayload = MousepadPayload;
+    type PluginConfig = MousepadConfig;
+    type PluginState = MousepadState;
+
+    fn init(_device_mangager: &crate::devices::DeviceManager) -> Self {
+        Self::default()
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.mousepad.request".to_string(),
+            "kdeconnect.mousepad.echo".to_string(),
+            "kdeconnect.mousepad.keyboardstate".to_string(),
+        ]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.mousepad.request".to_string(),
+            "kdeconnect.mousepad.echo".to_string(),
+            "kdeconnect.mousepad.keyboardstate".to_string(),
+        ]
+    }
+
+    async fn parse_payload(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        config: &Option<Self::PluginConfig>,
+    ) -> Option<Self::PluginPayload> {
+        if payload.r#type == "kdeconnect.mousepad.request" {
+            let payload = serde_json::from_value::<Self::PluginPayload>(payload.body.clone());
+            match payload {
+                Ok(mut payload) => {
+                    info!("Parsed payload {payload:?}");
+                    payload.config = config.clone().unwrap_or_default();
+                    if payload.send_ack == Some(true) {
+                        payload.ack_to = Some(peer.clone());
+                    }
+                    return Some(payload);
+                }
+                Err(err) => {
+                    warn!("Error parsing mouse payload {err:?}");
+                }
+            }
+        } else if payload.r#type == "kdeconnect.mousepad.echo"
+            || payload.r#type == "kdeconnect.mousepad.keyboardstate"
+        {
+            match serde_json::from_value::<Self::PluginPayload>(payload.body.clone()) {
+                Ok(payload) => return Some(payload),
+                Err(err) => warn!("Error parsing mousepad payload {err:?}"),
+            }
+        }
+        None
+    }
+
+    fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+
+    fn should_send(
+        &self,
+        config: &Option<Self::PluginConfig>,
+        _state: &mut Self::PluginState,
+        _payload: &Self::PluginPayload,
+    ) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        if let Some(keyboard_active) = payload.state {
+            state.peer_keyboard_active = keyboard_active;
+            return;
+        }
+        if payload.is_ack == Some(true) {
+            // Echo of a key we sent, nothing to inject.
+            return;
+        }
+        state.track_held_buttons(&input_actions(payload, state));
+    }
+
+    fn handle(
+        &self,
+        payload: &Self::PluginPayload,
+        previous_state: &Self::PluginState,
+    ) -> Option<PluginAction> {
+        if payload.state.is_some() || payload.is_ack == Some(true) {
+            return None;
+        }
+        let actions = input_actions(payload, previous_state);
+        let echo = payload.ack_to.clone().map(|peer| {
+            let echo = MousepadPayload {
+                send_ack: None,
+                is_ack: Some(true),
+                ack_to: None,
+                ..payload.clone()
+            };
+            (peer, echo)
+        });
+        if actions.is_empty() && echo.is_none() {
+            return None;
+        }
+        let backends = self.backends.clone();
+        let kind = payload.config.backend;
+        // Echoed once the input was applied, as KDE Connect does.
+        Some(PluginAction::Blocking(Box::new(move || {
+            if !actions.is_empty() {
+                let result =
+                    backend_for(&backends, kind).and_then(|backend| backend.apply(&actions));
+                if let Err(err) = result {
+                    warn!("Cannot inject input {err:?}");
+                }
+            }
+            if let Some((peer, echo)) = echo {
+                if let Err(err) = peer.send_blocking("kdeconnect.mousepad.echo", echo) {
+                    warn!("Cannot send mousepad echo {err:?}");
+                }
+            }
+        })))
+    }
+}
+
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MousepadPayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dx: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dy: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    singleclick: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    singlehold: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    singlerelease: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    doubleclick: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    middleclick: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rightclick: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scroll: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    special_key: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shift: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ctrl: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alt: Option<bool>,
+
+    #[serde(rename = "super", default, skip_serializing_if = "Option::is_none")]
+    super_key: Option<bool>,
+
+    /// Asks the receiver to echo the packet back once handled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    send_ack: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    is_ack: Option<bool>,
+
+    /// Keyboard state, whether the sender currently accepts key input.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    state: Option<bool>,
+
+    /// Config of the sending device.
+    #[serde(skip)]
+    #[graphql(skip)]
+    config: MousepadConfig,
+
+    /// Device expecting an echo once the request was applied.
+    #[serde(skip)]
+    #[graphql(skip)]
+    ack_to: Option<Peer>,
+}
+
+#[derive(Debug, Default, InputObject)]
+pub struct KeyModifiers {
+    shift: Option<bool>,
+    ctrl: Option<bool>,
+    alt: Option<bool>,
+    super_key: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum MouseClick {
+    Single,
+    Double,
+    Middle,
+    Right,
+    Hold,
+    Release,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "MousepadConfigInput")]
+#[serde(default)]
+pub struct MousepadConfig {
+    enabled: bool,
+    #[graphql(default)]
+    pub backend: InputBackendKind,
+    /// Whether pointer, click and scroll requests are injected.
+    #[graphql(default = true)]
+    handle_mouse_events: bool,
+    /// Whether key requests are injected.
+    #[graphql(default = true)]
+    handle_keyboard_events: bool,
+}
+
+impl Default for MousepadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            backend: InputBackendKind::default(),
+            handle_mouse_events: true,
+            handle_keyboard_events: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject)]
+pub struct MousepadState {
+    enabled: bool,
+    /// Whether the peer last reported it accepts keyboard input.
+    peer_keyboard_active: bool,
+    /// Whether a `singlehold` pressed the left button without a `singlerelease` yet.
+    left_button_held: bool,
+}
+
+impl MousepadState {
+    /// Remembers presses and releases so holds are released exactly once.
+    pub fn track_held_buttons(&mut self, actions: &[InputAction]) {
+        for action in actions {
+            match action {
+                InputAction::MouseDown(MouseButton::Left) => self.left_button_held = true,
+                InputAction::MouseUp(MouseButton::Left) => self.left_button_held = false,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Default for MousepadState {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            peer_keyboard_active: false,
+            left_button_held: false,
+        }
+    }
+}
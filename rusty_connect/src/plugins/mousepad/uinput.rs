@@ -0,0 +1,283 @@
+use std::sync::Mutex;
+
+use enigo::MouseButton;
+use evdev::{
+    uinput::{VirtualDevice, VirtualDeviceBuilder},
+    AttributeSet, EventType, InputEvent, Key as EvKey, RelativeAxisType,
+};
+use tracing::warn;
+
+use super::backend::{InputAction, InputBackend};
+
+const KEY_RELEASE: i32 = 0;
+const KEY_PRESS: i32 = 1;
+
+/// Printable characters of a US layout, with whether shift is needed.
+static CHAR_KEYS: &[(char, EvKey, bool)] = &[
+    ('a', EvKey::KEY_A, false),
+    ('b', EvKey::KEY_B, false),
+    ('c', EvKey::KEY_C, false),
+    ('d', EvKey::KEY_D, false),
+    ('e', EvKey::KEY_E, false),
+    ('f', EvKey::KEY_F, false),
+    ('g', EvKey::KEY_G, false),
+    ('h', EvKey::KEY_H, false),
+    ('i', EvKey::KEY_I, false),
+    ('j', EvKey::KEY_J, false),
+    ('k', EvKey::KEY_K, false),
+    ('l', EvKey::KEY_L, false),
+    ('m', EvKey::KEY_M, false),
+    ('n', EvKey::KEY_N, false),
+    ('o', EvKey::KEY_O, false),
+    ('p', EvKey::KEY_P, false),
+    ('q', EvKey::KEY_Q, false),
+    ('r', EvKey::KEY_R, false),
+    ('s', EvKey::KEY_S, false),
+    ('t', EvKey::KEY_T, false),
+    ('u', EvKey::KEY_U, false),
+    ('v', EvKey::KEY_V, false),
+    ('w', EvKey::KEY_W, false),
+    ('x', EvKey::KEY_X, false),
+    ('y', EvKey::KEY_Y, false),
+    ('z', EvKey::KEY_Z, false),
+    ('1', EvKey::KEY_1, false),
+    ('2', EvKey::KEY_2, false),
+    ('3', EvKey::KEY_3, false),
+    ('4', EvKey::KEY_4, false),
+    ('5', EvKey::KEY_5, false),
+    ('6', EvKey::KEY_6, false),
+    ('7', EvKey::KEY_7, false),
+    ('8', EvKey::KEY_8, false),
+    ('9', EvKey::KEY_9, false),
+    ('0', EvKey::KEY_0, false),
+    ('!', EvKey::KEY_1, true),
+    ('@', EvKey::KEY_2, true),
+    ('#', EvKey::KEY_3, true),
+    ('$', EvKey::KEY_4, true),
+    ('%', EvKey::KEY_5, true),
+    ('^', EvKey::KEY_6, true),
+    ('&', EvKey::KEY_7, true),
+    ('*', EvKey::KEY_8, true),
+    ('(', EvKey::KEY_9, true),
+    (')', EvKey::KEY_0, true),
+    (' ', EvKey::KEY_SPACE, false),
+    ('\n', EvKey::KEY_ENTER, false),
+    ('\t', EvKey::KEY_TAB, false),
+    ('-', EvKey::KEY_MINUS, false),
+    ('_', EvKey::KEY_MINUS, true),
+    ('=', EvKey::KEY_EQUAL, false),
+    ('+', EvKey::KEY_EQUAL, true),
+    ('[', EvKey::KEY_LEFTBRACE, false),
+    ('{', EvKey::KEY_LEFTBRACE, true),
+    (']', EvKey::KEY_RIGHTBRACE, false),
+    ('}', EvKey::KEY_RIGHTBRACE, true),
+    ('\\', EvKey::KEY_BACKSLASH, false),
+    ('|', EvKey::KEY_BACKSLASH, true),
+    (';', EvKey::KEY_SEMICOLON, false),
+    (':', EvKey::KEY_SEMICOLON, true),
+    ('\'', EvKey::KEY_APOSTROPHE, false),
+    ('"', EvKey::KEY_APOSTROPHE, true),
+    (',', EvKey::KEY_COMMA, false),
+    ('<', EvKey::KEY_COMMA, true),
+    ('.', EvKey::KEY_DOT, false),
+    ('>', EvKey::KEY_DOT, true),
+    ('/', EvKey::KEY_SLASH, false),
+    ('?', EvKey::KEY_SLASH, true),
+    ('`', EvKey::KEY_GRAVE, false),
+    ('~', EvKey::KEY_GRAVE, true),
+];
+
+/// Non-character keys the device can press.
+static NAMED_KEYS: &[(enigo::Key, EvKey)] = &[
+    (enigo::Key::Backspace, EvKey::KEY_BACKSPACE),
+    (enigo::Key::Tab, EvKey::KEY_TAB),
+    (enigo::Key::Return, EvKey::KEY_ENTER),
+    (enigo::Key::Linefeed, EvKey::KEY_ENTER),
+    (enigo::Key::LeftArrow, EvKey::KEY_LEFT),
+    (enigo::Key::UpArrow, EvKey::KEY_UP),
+    (enigo::Key::RightArrow, EvKey::KEY_RIGHT),
+    (enigo::Key::DownArrow, EvKey::KEY_DOWN),
+    (enigo::Key::PageUp, EvKey::KEY_PAGEUP),
+    (enigo::Key::PageDown, EvKey::KEY_PAGEDOWN),
+    (enigo::Key::Home, EvKey::KEY_HOME),
+    (enigo::Key::End, EvKey::KEY_END),
+    (enigo::Key::Delete, EvKey::KEY_DELETE),
+    (enigo::Key::Escape, EvKey::KEY_ESC),
+    (enigo::Key::SysReq, EvKey::KEY_SYSRQ),
+    (enigo::Key::ScrollLock, EvKey::KEY_SCROLLLOCK),
+    (enigo::Key::Space, EvKey::KEY_SPACE),
+    (enigo::Key::F1, EvKey::KEY_F1),
+    (enigo::Key::F2, EvKey::KEY_F2),
+    (enigo::Key::F3, EvKey::KEY_F3),
+    (enigo::Key::F4, EvKey::KEY_F4),
+    (enigo::Key::F5, EvKey::KEY_F5),
+    (enigo::Key::F6, EvKey::KEY_F6),
+    (enigo::Key::F7, EvKey::KEY_F7),
+    (enigo::Key::F8, EvKey::KEY_F8),
+    (enigo::Key::F9, EvKey::KEY_F9),
+    (enigo::Key::F10, EvKey::KEY_F10),
+    (enigo::Key::F11, EvKey::KEY_F11),
+    (enigo::Key::F12, EvKey::KEY_F12),
+    (enigo::Key::Control, EvKey::KEY_LEFTCTRL),
+    (enigo::Key::Alt, EvKey::KEY_LEFTALT),
+    (enigo::Key::Shift, EvKey::KEY_LEFTSHIFT),
+    (enigo::Key::Meta, EvKey::KEY_LEFTMETA),
+];
+
+/// Injects input through a virtual `/dev/uinput` device, independent of the display server.
+pub struct UinputBackend {
+    device: Mutex<VirtualDevice>,
+}
+
+impl UinputBackend {
+    pub fn new() -> anyhow::Result<Self> {
+        let mut keys = AttributeSet::<EvKey>::new();
+        for (_, key, _) in CHAR_KEYS {
+            keys.insert(*key);
+        }
+        for (_, key) in NAMED_KEYS {
+            keys.insert(*key);
+        }
+        for key in [EvKey::BTN_LEFT, EvKey::BTN_RIGHT, EvKey::BTN_MIDDLE] {
+            keys.insert(key);
+        }
+        let mut axes = AttributeSet::<RelativeAxisType>::new();
+        for axis in [
+            RelativeAxisType::REL_X,
+            RelativeAxisType::REL_Y,
+            RelativeAxisType::REL_WHEEL,
+            RelativeAxisType::REL_HWHEEL,
+        ] {
+            axes.insert(axis);
+        }
+        let device = VirtualDeviceBuilder::new()?
+            .name("rusty-connect virtual input")
+            .with_keys(&keys)?
+            .with_relative_axes(&axes)?
+            .build()?;
+        Ok(Self {
+            device: Mutex::new(device),
+        })
+    }
+
+    fn key(key: &enigo::Key) -> Option<(EvKey, bool)> {
+        if let enigo::Key::Layout(c) = key {
+            return Self::char_key(*c);
+        }
+        NAMED_KEYS
+            .iter()
+            .find(|(named, _)| named == key)
+            .map(|(_, ev_key)| (*ev_key, false))
+    }
+
+    fn char_key(c: char) -> Option<(EvKey, bool)> {
+        let lower = c.to_ascii_lowercase();
+        CHAR_KEYS
+            .iter()
+            .find(|(key_char, _, _)| *key_char == lower)
+            .map(|(_, key, shift)| (*key, *shift || c.is_ascii_uppercase()))
+    }
+
+    fn button(button: &MouseButton) -> Option<EvKey> {
+        match button {
+            MouseButton::Left => Some(EvKey::BTN_LEFT),
+            MouseButton::Right => Some(EvKey::BTN_RIGHT),
+            MouseButton::Middle => Some(EvKey::BTN_MIDDLE),
+            _ => None,
+        }
+    }
+
+    fn key_event(key: EvKey, value: i32) -> InputEvent {
+        InputEvent::new(EventType::KEY, key.code(), value)
+    }
+
+    fn relative_event(axis: RelativeAxisType, value: i32) -> InputEvent {
+        InputEvent::new(EventType::RELATIVE, axis.0, value)
+    }
+
+    /// Press and release batches, shifted when needed; each batch is synced separately.
+    fn tap(key: EvKey, shift: bool) -> Vec<Vec<InputEvent>> {
+        let mut batches = vec![];
+        if shift {
+            batches.push(vec![Self::key_event(EvKey::KEY_LEFTSHIFT, KEY_PRESS)]);
+        }
+        batches.push(vec![Self::key_event(key, KEY_PRESS)]);
+        batches.push(vec![Self::key_event(key, KEY_RELEASE)]);
+        if shift {
+            batches.push(vec![Self::key_event(EvKey::KEY_LEFTSHIFT, KEY_RELEASE)]);
+        }
+        batches
+    }
+
+    fn batches(action: &InputAction) -> Vec<Vec<InputEvent>> {
+        match action {
+            InputAction::KeyDown(key) | InputAction::KeyUp(key) => match Self::key(key) {
+                Some((ev_key, _)) => {
+                    let value = if matches!(action, InputAction::KeyDown(_)) {
+                        KEY_PRESS
+                    } else {
+                        KEY_RELEASE
+                    };
+                    vec![vec![Self::key_event(ev_key, value)]]
+                }
+                None => {
+                    warn!("Key {key:?} not supported by uinput");
+                    vec![]
+                }
+            },
+            InputAction::KeyClick(key) => match Self::key(key) {
+                Some((ev_key, shift)) => Self::tap(ev_key, shift),
+                None => {
+                    warn!("Key {key:?} not supported by uinput");
+                    vec![]
+                }
+            },
+            InputAction::Text(text) => text
+                .chars()
+                .flat_map(|c| match Self::char_key(c) {
+                    Some((ev_key, shift)) => Self::tap(ev_key, shift),
+                    None => {
+                        warn!("Character {c:?} not supported by uinput");
+                        vec![]
+                    }
+                })
+                .collect(),
+            InputAction::MouseDown(button)
+            | InputAction::MouseUp(button)
+            | InputAction::MouseClick(button) => {
+                let Some(ev_key) = Self::button(button) else {
+                    warn!("Button {button:?} not supported by uinput");
+                    return vec![];
+                };
+                match action {
+                    InputAction::MouseDown(_) => vec![vec![Self::key_event(ev_key, KEY_PRESS)]],
+                    InputAction::MouseUp(_) => vec![vec![Self::key_event(ev_key, KEY_RELEASE)]],
+                    _ => Self::tap(ev_key, false),
+                }
+            }
+            InputAction::MoveRelative { dx, dy } => vec![vec![
+                Self::relative_event(RelativeAxisType::REL_X, *dx),
+                Self::relative_event(RelativeAxisType::REL_Y, *dy),
+            ]],
+            InputAction::ScrollX(length) => vec![vec![Self::relative_event(
+                RelativeAxisType::REL_HWHEEL,
+                *length,
+            )]],
+            // Wheel values are positive upwards.
+            InputAction::ScrollY(length) => vec![vec![Self::relative_event(
+                RelativeAxisType::REL_WHEEL,
+                -*length,
+            )]],
+        }
+    }
+}
+
+impl InputBackend for UinputBackend {
+    fn apply(&self, actions: &[InputAction]) -> anyhow::Result<()> {
+        let mut device = self.device.lock().expect("uinput device poisoned");
+        for batch in actions.iter().flat_map(Self::batches) {
+            device.emit(&batch)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use async_graphql::{Enum, SimpleObject};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+/// Source of local media players exposed to peers.
+pub trait MediaBackend: Send + Sync {
+    fn players(&self) -> BoxFuture<'_, anyhow::Result<Vec<String>>>;
+
+    fn status<'a>(&'a self, player: &'a str) -> BoxFuture<'a, anyhow::Result<PlayerStatus>>;
+
+    fn action<'a>(
+        &'a self,
+        player: &'a str,
+        action: MediaAction,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Sets the volume in percent, `0..=100`.
+    fn set_volume<'a>(&'a self, player: &'a str, volume: i64) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Seeks relative to the current position, in microseconds.
+    fn seek<'a>(&'a self, player: &'a str, offset: i64) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Sets the absolute position, in milliseconds.
+    fn set_position<'a>(
+        &'a self,
+        player: &'a str,
+        position: i64,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum MediaAction {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+}
+
+impl MediaAction {
+    /// Name used for the action in `kdeconnect.mpris.request` and on D-Bus.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Play => "Play",
+            Self::Pause => "Pause",
+            Self::PlayPause => "PlayPause",
+            Self::Stop => "Stop",
+            Self::Next => "Next",
+            Self::Previous => "Previous",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Play" => Some(Self::Play),
+            "Pause" => Some(Self::Pause),
+            "PlayPause" => Some(Self::PlayPause),
+            "Stop" => Some(Self::Stop),
+            "Next" => Some(Self::Next),
+            "Previous" => Some(Self::Previous),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, SimpleObject, Serialize, Deserialize)]
+pub struct PlayerStatus {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub album_art_url: Option<String>,
+    pub is_playing: bool,
+    /// Position in milliseconds.
+    pub position: i64,
+    /// Track length in milliseconds.
+    pub length: i64,
+    /// Volume in percent.
+    pub volume: i64,
+    pub can_play: bool,
+    pub can_pause: bool,
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub can_seek: bool,
+}
+
+/// Backend keeping players in memory, used for tests and platforms without MPRIS.
+#[derive(Default)]
+pub struct InMemoryMediaBackend {
+    pub players: Mutex<BTreeMap<String, PlayerStatus>>,
+}
+
+impl InMemoryMediaBackend {
+    pub fn with_player(self, name: &str, status: PlayerStatus) -> Self {
+        self.insert(name, status);
+        self
+    }
+
+    pub fn insert(&self, name: &str, status: PlayerStatus) {
+        self.players
+            .lock()
+            .expect("media players poisoned")
+            .insert(name.to_string(), status);
+    }
+
+    pub fn get(&self, name: &str) -> Option<PlayerStatus> {
+        self.players
+            .lock()
+            .expect("media players poisoned")
+            .get(name)
+            .cloned()
+    }
+
+    fn update(&self, player: &str, update: impl FnOnce(&mut PlayerStatus)) -> anyhow::Result<()> {
+        let mut players = self.players.lock().expect("media players poisoned");
+        let status = players
+            .get_mut(player)
+            .ok_or(anyhow::anyhow!("No player named {player}"))?;
+        update(status);
+        Ok(())
+    }
+}
+
+impl MediaBackend for InMemoryMediaBackend {
+    fn players(&self) -> BoxFuture<'_, anyhow::Result<Vec<String>>> {
+        let players = self
+            .players
+            .lock()
+            .expect("media players poisoned")
+            .keys()
+            .cloned()
+            .collect();
+        Box::pin(async move { Ok(players) })
+    }
+
+    fn status<'a>(&'a self, player: &'a str) -> BoxFuture<'a, anyhow::Result<PlayerStatus>> {
+        let status = self
+            .get(player)
+            .ok_or(anyhow::anyhow!("No player named {player}"));
+        Box::pin(async move { status })
+    }
+
+    fn action<'a>(
+        &'a self,
+        player: &'a str,
+        action: MediaAction,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        let result = self.update(player, |status| match action {
+            MediaAction::Play => status.is_playing = true,
+            MediaAction::Pause => status.is_playing = false,
+            MediaAction::PlayPause => status.is_playing = !status.is_playing,
+            MediaAction::Stop => {
+                status.is_playing = false;
+                status.position = 0;
+            }
+            MediaAction::Next | MediaAction::Previous => status.position = 0,
+        });
+        Box::pin(async move { result })
+    }
+
+    fn set_volume<'a>(&'a self, player: &'a str, volume: i64) -> BoxFuture<'a, anyhow::Result<()>> {
+        let result = self.update(player, |status| status.volume = volume.clamp(0, 100));
+        Box::pin(async move { result })
+    }
+
+    fn seek<'a>(&'a self, player: &'a str, offset: i64) -> BoxFuture<'a, anyhow::Result<()>> {
+        let result = self.update(player, |status| {
+            status.position = (status.position + offset / 1000).clamp(0, status.length.max(0));
+        });
+        Box::pin(async move { result })
+    }
+
+    fn set_position<'a>(
+        &'a self,
+        player: &'a str,
+        position: i64,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        let result = self.update(player, |status| {
+            status.position = position.clamp(0, status.length.max(0));
+        });
+        Box::pin(async move { result })
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use futures::future::BoxFuture;
+use tokio::sync::OnceCell;
+use zbus::{
+    fdo::{DBusProxy, PropertiesProxy},
+    names::InterfaceName,
+    zvariant::{ObjectPath, OwnedValue, Value},
+    Connection,
+};
+
+use super::backend::{MediaAction, MediaBackend, PlayerStatus};
+
+const BUS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// Media backend talking to MPRIS players on the D-Bus session bus.
+///
+/// Players are identified by their bus name without the `org.mpris.MediaPlayer2.` prefix.
+#[derive(Default)]
+pub struct DbusMediaBackend {
+    connection: OnceCell<Connection>,
+}
+
+impl DbusMediaBackend {
+    async fn connection(&self) -> anyhow::Result<&Connection> {
+        Ok(self
+            .connection
+            .get_or_try_init(|| async { Connection::session().await })
+            .await?)
+    }
+
+    async fn properties(&self, player: &str) -> anyhow::Result<PropertiesProxy<'static>> {
+        let connection = self.connection().await?;
+        Ok(PropertiesProxy::builder(connection)
+            .destination(format!("{BUS_PREFIX}{player}"))?
+            .path(OBJECT_PATH)?
+            .build()
+            .await?)
+    }
+
+    async fn call<B>(&self, player: &str, method: &str, body: &B) -> anyhow::Result<()>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        let connection = self.connection().await?;
+        connection
+            .call_method(
+                Some(format!("{BUS_PREFIX}{player}")),
+                OBJECT_PATH,
+                Some(PLAYER_INTERFACE),
+                method,
+                body,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn list_players(&self) -> anyhow::Result<Vec<String>> {
+        let connection = self.connection().await?;
+        let names = DBusProxy::new(connection).await?.list_names().await?;
+        Ok(names
+            .iter()
+            .filter_map(|name| name.as_str().strip_prefix(BUS_PREFIX))
+            .map(|name| name.to_string())
+            .collect())
+    }
+
+    async fn player_status(&self, player: &str) -> anyhow::Result<PlayerStatus> {
+        let properties = self
+            .properties(player)
+            .await?
+            .get_all(Some(InterfaceName::from_static_str_unchecked(PLAYER_INTERFACE)).into())
+            .await?;
+        let metadata = properties
+            .get("Metadata")
+            .and_then(|value| value.try_clone().ok())
+            .and_then(|value| HashMap::<String, OwnedValue>::try_from(value).ok())
+            .unwrap_or_default();
+
+        Ok(PlayerStatus {
+            title: get(&metadata, "xesam:title").unwrap_or_default(),
+            artist: get::<Vec<String>>(&metadata, "xesam:artist")
+                .map(|artists| artists.join(", "))
+                .unwrap_or_default(),
+            album: get(&metadata, "xesam:album").unwrap_or_default(),
+            album_art_url: get(&metadata, "mpris:artUrl"),
+            is_playing: get::<String>(&properties, "PlaybackStatus").as_deref() == Some("Playing"),
+            position: get::<i64>(&properties, "Position").unwrap_or_default() / 1000,
+            length: get::<i64>(&metadata, "mpris:length")
+                .or_else(|| get::<u64>(&metadata, "mpris:length").map(|length| length as i64))
+                .unwrap_or_default()
+                / 1000,
+            volume: (get::<f64>(&properties, "Volume").unwrap_or(1.0) * 100.0).round() as i64,
+            can_play: get(&properties, "CanPlay").unwrap_or_default(),
+            can_pause: get(&properties, "CanPause").unwrap_or_default(),
+            can_go_next: get(&properties, "CanGoNext").unwrap_or_default(),
+            can_go_previous: get(&properties, "CanGoPrevious").unwrap_or_default(),
+            can_seek: get(&properties, "CanSeek").unwrap_or_default(),
+        })
+    }
+
+    async fn track_id(&self, player: &str) -> anyhow::Result<ObjectPath<'static>> {
+        let metadata = self
+            .properties(player)
+            .await?
+            .get(
+                InterfaceName::from_static_str_unchecked(PLAYER_INTERFACE),
+                "Metadata",
+            )
+            .await?;
+        let metadata = HashMap::<String, OwnedValue>::try_from(metadata)?;
+        let track_id = metadata
+            .get("mpris:trackid")
+            .ok_or(anyhow::anyhow!("Player has no current track"))?;
+        Ok(ObjectPath::try_from(track_id.try_clone()?)?.into_owned())
+    }
+}
+
+fn get<T>(map: &HashMap<String, OwnedValue>, key: &str) -> Option<T>
+where
+    T: TryFrom<OwnedValue>,
+{
+    let value = map.get(key)?.try_clone().ok()?;
+    T::try_from(value).ok()
+}
+
+impl MediaBackend for DbusMediaBackend {
+    fn players(&self) -> BoxFuture<'_, anyhow::Result<Vec<String>>> {
+        Box::pin(self.list_players())
+    }
+
+    fn status<'a>(&'a self, player: &'a str) -> BoxFuture<'a, anyhow::Result<PlayerStatus>> {
+        Box::pin(self.player_status(player))
+    }
+
+    fn action<'a>(
+        &'a self,
+        player: &'a str,
+        action: MediaAction,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(self.call(player, action.name(), &()))
+    }
+
+    fn set_volume<'a>(&'a self, player: &'a str, volume: i64) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let volume = volume.clamp(0, 100) as f64 / 100.0;
+            self.properties(player)
+                .await?
+                .set(
+                    InterfaceName::from_static_str_unchecked(PLAYER_INTERFACE),
+                    "Volume",
+                    &Value::from(volume),
+                )
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn seek<'a>(&'a self, player: &'a str, offset: i64) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move { self.call(player, "Seek", &(offset,)).await })
+    }
+
+    fn set_position<'a>(
+        &'a self,
+        player: &'a str,
+        position: i64,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let track_id = self.track_id(player).await?;
+            self.call(player, "SetPosition", &(track_id, position * 1000))
+                .await
+        })
+    }
+}
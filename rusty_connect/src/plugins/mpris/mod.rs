@@ -0,0 +1,459 @@
+use std::sync::Arc;
+
+use async_graphql::{Context, InputObject, Object, SimpleObject};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::devices::Peer;
+
+use super::{Plugin, PluginAction, PluginExt};
+
+pub use self::backend::{InMemoryMediaBackend, MediaAction, MediaBackend, PlayerStatus};
+#[cfg(target_os = "linux")]
+pub use self::dbus::DbusMediaBackend;
+
+pub mod backend;
+#[cfg(target_os = "linux")]
+pub mod dbus;
+
+pub struct Mpris {
+    pub backend: Arc<dyn MediaBackend>,
+}
+
+#[Object]
+impl Mpris {
+    /// Players available on this machine.
+    pub async fn local_players(&self) -> anyhow::Result<Vec<String>> {
+        self.backend.players().await
+    }
+
+    pub async fn request_players<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<&str> {
+        let payload = MprisPayload {
+            request_player_list: Some(true),
+            ..Default::default()
+        };
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.mpris.request",
+            payload,
+        )
+        .await?;
+        Ok("success")
+    }
+
+    pub async fn request_now_playing<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        player: String,
+    ) -> anyhow::Result<&str> {
+        let payload = MprisPayload {
+            player: Some(player),
+            request_now_playing: Some(true),
+            request_volume: Some(true),
+            ..Default::default()
+        };
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.mpris.request",
+            payload,
+        )
+        .await?;
+        Ok("success")
+    }
+
+    pub async fn send_media_action<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        player: String,
+        action: MediaAction,
+    ) -> anyhow::Result<&str> {
+        let payload = MprisPayload {
+            player: Some(player),
+            action: Some(action.name().to_string()),
+            ..Default::default()
+        };
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.mpris.request",
+            payload,
+        )
+        .await?;
+        Ok("success")
+    }
+
+    pub async fn set_media_volume<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        player: String,
+        volume: i64,
+    ) -> anyhow::Result<&str> {
+        let payload = MprisPayload {
+            player: Some(player),
+            set_volume: Some(volume.clamp(0, 100)),
+            ..Default::default()
+        };
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.mpris.request",
+            payload,
+        )
+        .await?;
+        Ok("success")
+    }
+
+    /// Seeks the remote player by `offset` milliseconds.
+    pub async fn seek_media<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        player: String,
+        offset: i64,
+    ) -> anyhow::Result<&str> {
+        let payload = MprisPayload {
+            player: Some(player),
+            seek: Some(offset * 1000),
+            ..Default::default()
+        };
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.mpris.request",
+            payload,
+        )
+        .await?;
+        Ok("success")
+    }
+}
+
+impl Mpris {
+    pub fn with_backend(backend: Arc<dyn MediaBackend>) -> Self {
+        Self { backend }
+    }
+}
+
+/// Answers a `kdeconnect.mpris.request` from the peer using the local backend.
+async fn handle_request(
+    backend: &dyn MediaBackend,
+    request: &MprisPayload,
+    peer: &Peer,
+) -> anyhow::Result<()> {
+    if request.request_player_list == Some(true) {
+        let players = backend.players().await?;
+        let reply = MprisPayload {
+            player_list: Some(players),
+            support_album_art_payload: Some(false),
+            ..Default::default()
+        };
+        peer.send("kdeconnect.mpris", reply).await?;
+    }
+    let Some(player) = &request.player else {
+        return Ok(());
+    };
+    if let Some(action) = &request.action {
+        match MediaAction::from_name(action) {
+            Some(action) => backend.action(player, action).await?,
+            None => warn!("Unknown media action {action}"),
+        }
+    }
+    if let Some(volume) = request.set_volume {
+        backend.set_volume(player, volume).await?;
+    }
+    if let Some(offset) = request.seek {
+        backend.seek(player, offset).await?;
+    }
+    if let Some(position) = request.set_position {
+        backend.set_position(player, position).await?;
+    }
+    if request.request_now_playing == Some(true) || request.request_volume == Some(true) {
+        let status = backend.status(player).await?;
+        peer.send(
+            "kdeconnect.mpris",
+            MprisPayload::from_status(player, status),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+impl Plugin for Mpris {
+    type PluginPayload = MprisPayload;
+    type PluginConfig = MprisConfig;
+    type PluginState = MprisState;
+
+    fn init(_device_mangager: &crate::devices::DeviceManager) -> Self {
+        #[cfg(target_os = "linux")]
+        let backend: Arc<dyn MediaBackend> = Arc::new(DbusMediaBackend::default());
+        #[cfg(not(target_os = "linux"))]
+        let backend: Arc<dyn MediaBackend> = Arc::new(InMemoryMediaBackend::default());
+        Self::with_backend(backend)
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.mpris".to_string(),
+            "kdeconnect.mpris.request".to_string(),
+        ]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.mpris".to_string(),
+            "kdeconnect.mpris.request".to_string(),
+        ]
+    }
+
+    async fn parse_payload(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        _config: &Option<Self::PluginConfig>,
+    ) -> Option<Self::PluginPayload> {
+        if payload.r#type == "kdeconnect.mpris" || payload.r#type == "kdeconnect.mpris.request" {
+            let mpris_payload = serde_json::from_value::<Self::PluginPayload>(payload.body.clone());
+            match mpris_payload {
+                Ok(mut mpris_payload) => {
+                    if payload.r#type == "kdeconnect.mpris.request" {
+                        if peer.paired {
+                            mpris_payload.requested_by = Some(peer.clone());
+                        } else {
+                            warn!("Ignoring media request from unpaired device");
+                        }
+                    }
+                    return Some(mpris_payload);
+                }
+                Err(err) => warn!("Cant parse mpris payload {err:#?}"),
+            }
+        }
+        None
+    }
+
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        if let Some(players) = &payload.player_list {
+            state
+                .players
+                .retain(|player| players.contains(&player.name));
+            for name in players {
+                if !state.players.iter().any(|player| &player.name == name) {
+                    state.players.push(RemotePlayer {
+                        name: name.clone(),
+                        status: None,
+                    });
+                }
+            }
+        }
+        if let (Some(name), Some(status)) = (&payload.player, payload.status()) {
+            match state.players.iter_mut().find(|player| &player.name == name) {
+                Some(player) => player.status = Some(status),
+                None => state.players.push(RemotePlayer {
+                    name: name.clone(),
+                    status: Some(status),
+                }),
+            }
+        }
+    }
+
+    /// Drives the local players for requests from paired devices and answers them.
+    fn handle(
+        &self,
+        payload: &Self::PluginPayload,
+        _previous_state: &Self::PluginState,
+    ) -> Option<PluginAction> {
+        let peer = payload.requested_by.clone()?;
+        let backend = self.backend.clone();
+        let request = payload.clone();
+        Some(PluginAction::Async(Box::pin(async move {
+            if let Err(err) = handle_request(backend.as_ref(), &request, &peer).await {
+                warn!("Cannot handle media request {err:?}");
+            }
+        })))
+    }
+
+    fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+
+    fn should_send(
+        &self,
+        config: &Option<Self::PluginConfig>,
+        _state: &mut Self::PluginState,
+        _payload: &Self::PluginPayload,
+    ) -> bool {
+        if let Some(config) = config {
+            config.send_enabled
+        } else {
+            true
+        }
+    }
+}
+
+//https://github.com/KDE/kdeconnect-kde/tree/master/plugins/mpriscontrol
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MprisPayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    player_list: Option<Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    support_album_art_payload: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    player: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    artist: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    album: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    album_art_url: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    is_playing: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pos: Option<i64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    length: Option<i64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    volume: Option<i64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    can_play: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    can_pause: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    can_go_next: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    can_go_previous: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    can_seek: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_player_list: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_now_playing: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_volume: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    action: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    set_volume: Option<i64>,
+
+    /// Relative seek in microseconds.
+    #[serde(default, rename = "Seek", skip_serializing_if = "Option::is_none")]
+    seek: Option<i64>,
+
+    /// Absolute position in milliseconds.
+    #[serde(
+        default,
+        rename = "SetPosition",
+        skip_serializing_if = "Option::is_none"
+    )]
+    set_position: Option<i64>,
+
+    /// Paired device a request came from, answered in [`Plugin::handle`].
+    #[serde(skip)]
+    #[graphql(skip)]
+    requested_by: Option<Peer>,
+}
+
+impl MprisPayload {
+    fn from_status(player: &str, status: PlayerStatus) -> Self {
+        Self {
+            player: Some(player.to_string()),
+            title: Some(status.title),
+            artist: Some(status.artist),
+            album: Some(status.album),
+            album_art_url: status.album_art_url,
+            is_playing: Some(status.is_playing),
+            pos: Some(status.position),
+            length: Some(status.length),
+            volume: Some(status.volume),
+            can_play: Some(status.can_play),
+            can_pause: Some(status.can_pause),
+            can_go_next: Some(status.can_go_next),
+            can_go_previous: Some(status.can_go_previous),
+            can_seek: Some(status.can_seek),
+            ..Default::default()
+        }
+    }
+
+    /// Player status carried by a `kdeconnect.mpris` packet, if it has any.
+    fn status(&self) -> Option<PlayerStatus> {
+        if self.is_playing.is_none() && self.title.is_none() && self.volume.is_none() {
+            return None;
+        }
+        Some(PlayerStatus {
+            title: self.title.clone().unwrap_or_default(),
+            artist: self.artist.clone().unwrap_or_default(),
+            album: self.album.clone().unwrap_or_default(),
+            album_art_url: self.album_art_url.clone(),
+            is_playing: self.is_playing.unwrap_or_default(),
+            position: self.pos.unwrap_or_default(),
+            length: self.length.unwrap_or_default(),
+            volume: self.volume.unwrap_or(100),
+            can_play: self.can_play.unwrap_or_default(),
+            can_pause: self.can_pause.unwrap_or_default(),
+            can_go_next: self.can_go_next.unwrap_or_default(),
+            can_go_previous: self.can_go_previous.unwrap_or_default(),
+            can_seek: self.can_seek.unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "MprisConfigInput")]
+pub struct MprisConfig {
+    enabled: bool,
+    send_enabled: bool,
+}
+
+impl Default for MprisConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            send_enabled: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, SimpleObject)]
+pub struct MprisState {
+    /// Players reported by the peer.
+    players: Vec<RemotePlayer>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject)]
+pub struct RemotePlayer {
+    name: String,
+    status: Option<PlayerStatus>,
+}
@@ -1,28 +1,19 @@
-use std::{
-    net::SocketAddr,
-    path::{Path, PathBuf},
-    sync::Arc,
-};
+use std::path::PathBuf;
 
-use async_graphql::{Object, SimpleObject};
+use async_graphql::{InputObject, Object, SimpleObject};
 use serde::{Deserialize, Serialize};
-use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::TcpStream,
-};
-use tokio_native_tls::{
-    // rustls::{
-    //     pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer, ServerName},
-    //     ClientConfig,
-    // },
-    native_tls::{self, Identity},
-    TlsConnector,
+use tracing::{info, warn};
+
+use crate::{
+    cert::CertPair,
+    devices::Peer,
+    payload_transfer::{self, TransferOptions},
 };
-use tracing::{debug, info, warn};
 
-use crate::cert::CertPair;
+use super::{Plugin, PluginAction};
 
-use super::Plugin;
+/// Notification icons larger than this are not downloaded.
+const MAX_ICON_SIZE: u64 = 8 * 1024 * 1024;
 
 pub struct Notification {
     pub icons_path: PathBuf,
@@ -36,6 +27,30 @@ impl Notification {
     }
 }
 
+/// Icon announced by a `kdeconnect.notification` packet, fetched in [`Plugin::handle`].
+#[derive(Debug, Clone)]
+struct IconDownload {
+    peer: Peer,
+    port: u16,
+    size: u64,
+    file_path: PathBuf,
+}
+
+impl IconDownload {
+    async fn run(self, certs: &CertPair) -> anyhow::Result<u64> {
+        let options = TransferOptions::for_peer(&self.peer).with_max_size(MAX_ICON_SIZE);
+        payload_transfer::download_to_file(
+            self.peer.address,
+            self.port,
+            self.size,
+            &self.file_path,
+            certs,
+            &options,
+        )
+        .await
+    }
+}
+
 impl Plugin for Notification {
     type PluginPayload = NotificationPayload;
     type PluginConfig = NotificationConfig;
@@ -59,7 +74,8 @@ impl Plugin for Notification {
     async fn parse_payload(
         &self,
         payload: &crate::payloads::Payload,
-        address: SocketAddr,
+        peer: &Peer,
+        _config: &Option<Self::PluginConfig>,
     ) -> Option<Self::PluginPayload> {
         if payload.r#type == "kdeconnect.notification" {
             // info!("Received notification payload {payload:#?}");
@@ -71,23 +87,12 @@ impl Plugin for Notification {
                         &payload.payload_transfer_info,
                         &notif_payload.payload_hash,
                     ) {
-                        let file_path = self.icons_path.join(hash);
-                        if let Ok(true) = tokio::fs::try_exists(&file_path).await {
-                            info!("Icon already exists");
-                        } else {
-                            let port = transfer_info.port;
-                            if let Err(err) = Self::receive_icon(
-                                address,
-                                port,
-                                size as usize,
-                                file_path.as_path(),
-                                self.certs.clone(),
-                            )
-                            .await
-                            {
-                                warn!("Cannot get icon {err:?}")
-                            }
-                        }
+                        notif_payload.icon_download = Some(IconDownload {
+                            peer: peer.clone(),
+                            port: transfer_info.port,
+                            size,
+                            file_path: self.icons_path.join(hash),
+                        });
                         notif_payload.icon_path = Some(hash.to_string());
                     }
                     info!("Returning notification payload");
@@ -99,6 +104,25 @@ impl Plugin for Notification {
         None
     }
 
+    /// Downloads the icon of the notification unless it is already stored.
+    fn handle(
+        &self,
+        payload: &Self::PluginPayload,
+        _previous_state: &Self::PluginState,
+    ) -> Option<PluginAction> {
+        let download = payload.icon_download.clone()?;
+        let certs = self.certs.clone();
+        Some(PluginAction::Async(Box::pin(async move {
+            if let Ok(true) = tokio::fs::try_exists(&download.file_path).await {
+                info!("Icon already exists");
+                return;
+            }
+            if let Err(err) = download.run(&certs).await {
+                warn!("Cannot get icon {err:?}")
+            }
+        })))
+    }
+
     fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
         if let Some(config) = config {
             config.enabled
@@ -117,57 +141,6 @@ impl Plugin for Notification {
     }
 }
 
-impl Notification {
-    pub async fn receive_icon(
-        address: SocketAddr,
-        port: u16,
-        size: usize,
-        path: &Path,
-        certs: CertPair,
-    ) -> anyhow::Result<()> {
-        const BUFFER_SIZE: usize = 1024;
-
-        debug!("TCP Stream initialising");
-        let stream = TcpStream::connect(SocketAddr::new(address.ip(), port)).await?;
-
-        debug!("TLS Config initializing");
-        let key = Identity::from_pkcs8(&certs.0, &certs.1)?;
-        let connector = native_tls::TlsConnector::builder()
-            .danger_accept_invalid_certs(true)
-            .identity(key)
-            .build()?;
-        let tls_connector = tokio_native_tls::TlsConnector::from(connector);
-
-        // let server_name = ServerName::IpAddress(address.ip().into());
-        let server_name = address.to_string();
-        debug!("Upgrading to TLS Stream to server name: {server_name:?}");
-        let mut tls_stream = tls_connector.connect(&server_name, stream).await?;
-
-        // debug!("Creating file");
-        let mut file = tokio::fs::File::create(path).await?;
-
-        let mut buffer = vec![0u8; BUFFER_SIZE];
-
-        let mut total_bytes_read = 0;
-        while total_bytes_read < size {
-            let bytes_to_read = std::cmp::min(size - total_bytes_read, BUFFER_SIZE);
-            let bytes_read = tls_stream.read(&mut buffer[..bytes_to_read]).await?;
-            if bytes_read == 0 {
-                // End of stream reached
-                break;
-            }
-
-            // Write the read data to the file
-            file.write_all(&buffer[..bytes_read]).await?;
-
-            // Update the total bytes read
-            total_bytes_read += bytes_read;
-        }
-        debug!("Receive icon completed");
-        Ok(())
-    }
-}
-
 //https://github.com/KDE/kdeconnect-kde/blob/705a72c0779babae809928fef4ad018c8562470e/plugins/notifications/README#L13C1-L22C1
 #[derive(SimpleObject, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -203,13 +176,24 @@ pub struct NotificationPayload {
 
     #[serde(default)]
     icon_path: Option<String>,
+
+    #[serde(skip)]
+    #[graphql(skip)]
+    icon_download: Option<IconDownload>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone, SimpleObject)]
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "NotificationConfigInput")]
 pub struct NotificationConfig {
     enabled: bool,
 }
 
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Default, Clone, SimpleObject)]
 pub struct NotificationState {
     enabled: bool,
@@ -1,11 +1,25 @@
-use std::net::SocketAddr;
+use std::{sync::Arc, time::Duration};
 
-use async_graphql::{Context, Object, SimpleObject};
+use async_graphql::{Context, InputObject, Object, SimpleObject};
 use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::warn;
 
-use super::{Plugin, PluginExt};
+use crate::{
+    devices::{DeviceManager, Peer},
+    utils::get_timestamp,
+};
 
-#[derive(Default)]
+use super::{Plugin, PluginExt, Reply};
+
+/// How often devices with `measure_latency` on are probed.
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+/// Probes unanswered for longer count as lost.
+const PROBE_TIMEOUT_MS: u64 = 5_000;
+/// Probes the rolling stats are computed over.
+const LATENCY_WINDOW: usize = 20;
+
+#[derive(Default, Clone, Copy)]
 pub struct Ping;
 
 #[Object]
@@ -14,16 +28,91 @@ impl Ping {
         &self,
         context: &Context<'ctx>,
         device_id: Option<String>,
+        message: Option<String>,
     ) -> anyhow::Result<&str> {
         self.send_payload(
             context,
             device_id.as_deref(),
-            "kdeconnect.battery",
-            PingPayload { message: None },
+            "kdeconnect.ping",
+            PingPayload {
+                message,
+                ..Default::default()
+            },
+        )
+        .await?;
+        Ok("success")
+    }
+
+    /// Sends one latency probe, answered only by peers running this daemon.
+    pub async fn probe_ping_latency<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<&str> {
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.ping.request",
+            PingPayload::probe(),
         )
         .await?;
         Ok("success")
     }
+
+    /// Round trip stats over the last probes sent to the device.
+    pub async fn ping_latency<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<Option<PingLatency>> {
+        let state = self.get_state(context, &device_id).await?;
+        Ok(PingLatency::from_probes(
+            &state.probes,
+            get_timestamp() as u64,
+        ))
+    }
+}
+
+impl Ping {
+    /// Probes connected devices with `measure_latency` on.
+    pub fn start_probing(&self, device_manager: Arc<RwLock<DeviceManager>>) {
+        let plugin = *self;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PROBE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let device_ids = {
+                    let device_manager = device_manager.read().await;
+                    device_manager
+                        .devices
+                        .values()
+                        .filter(|device| {
+                            device.peer().is_some()
+                                && Self::get_config_from_plugin_configs(
+                                    &device.device.effective_configs,
+                                )
+                                .as_ref()
+                                .is_some_and(|config| config.measure_latency)
+                        })
+                        .map(|device| device.device.id.clone())
+                        .collect::<Vec<_>>()
+                };
+                for device_id in device_ids {
+                    if let Err(err) = plugin
+                        .send_payload_with(
+                            &device_manager,
+                            Some(&device_id),
+                            "kdeconnect.ping.request",
+                            PingPayload::probe(),
+                        )
+                        .await
+                    {
+                        warn!("Cannot probe latency {err:?}");
+                    }
+                }
+            }
+        });
+    }
 }
 
 impl Plugin for Ping {
@@ -36,27 +125,74 @@ impl Plugin for Ping {
     }
 
     fn incoming_capabilities(&self) -> Vec<String> {
-        vec!["kdeconnect.ping".to_string()]
+        vec![
+            "kdeconnect.ping".to_string(),
+            "kdeconnect.ping.request".to_string(),
+        ]
     }
 
     fn outgoing_capabilities(&self) -> Vec<String> {
-        vec!["kdeconnect.ping".to_string()]
+        vec![
+            "kdeconnect.ping".to_string(),
+            "kdeconnect.ping.request".to_string(),
+        ]
+    }
+
+    /// Echoes the `sentAt` of `kdeconnect.ping.request` packets back in a ping, understood by our
+    /// own peers.
+    async fn reply(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        config: &Option<Self::PluginConfig>,
+    ) -> anyhow::Result<Option<Reply>> {
+        let sends = config.as_ref().is_none_or(|config| config.send_enabled);
+        if payload.r#type != "kdeconnect.ping.request" || !peer.paired || !sends {
+            return Ok(None);
+        }
+        let request = serde_json::from_value::<Self::PluginPayload>(payload.body.clone())?;
+        let echo = PingPayload {
+            sent_at: request.sent_at,
+            ..Default::default()
+        };
+        Ok(Some(Reply::new("kdeconnect.ping", echo)?))
     }
 
     async fn parse_payload(
         &self,
         payload: &crate::payloads::Payload,
-        _address: SocketAddr,
+        _peer: &Peer,
+        _config: &Option<Self::PluginConfig>,
     ) -> Option<Self::PluginPayload> {
         if payload.r#type == "kdeconnect.ping" {
             let payload = serde_json::from_value::<Self::PluginPayload>(payload.body.clone());
-            if let Ok(payload) = payload {
+            if let Ok(mut payload) = payload {
+                payload.received_at = Some(get_timestamp() as u64);
                 return Some(payload);
             }
         }
         None
     }
 
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        let (Some(sent_at), Some(received_at)) = (payload.sent_at, payload.received_at) else {
+            return;
+        };
+        // Only echoes of our own unanswered probes, so replays cannot skew the stats.
+        if let Some(probe) = state
+            .probes
+            .iter_mut()
+            .find(|probe| probe.sent_at == sent_at && probe.round_trip_ms.is_none())
+        {
+            probe.round_trip_ms = Some(received_at.saturating_sub(sent_at));
+        }
+    }
+
+    /// Echoed probes only feed the latency stats.
+    fn emits(&self, payload: &Self::PluginPayload) -> bool {
+        payload.sent_at.is_none()
+    }
+
     fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
         if let Some(config) = config {
             config.enabled
@@ -77,20 +213,129 @@ impl Plugin for Ping {
             true
         }
     }
+
+    /// Only probes that left count, so failed sends are not reported as lost.
+    fn sent(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        if let Some(sent_at) = payload.sent_at {
+            state.probes.insert(
+                0,
+                Probe {
+                    sent_at,
+                    round_trip_ms: None,
+                },
+            );
+            state.probes.truncate(LATENCY_WINDOW);
+        }
+    }
 }
 
-#[derive(SimpleObject, Serialize, Deserialize)]
+#[derive(SimpleObject, Serialize, Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct PingPayload {
     message: Option<String>,
+    /// Milliseconds since epoch on our clock, set on latency probes and echoed back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sent_at: Option<u64>,
+
+    #[serde(skip)]
+    #[graphql(skip)]
+    received_at: Option<u64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone, SimpleObject)]
+impl PingPayload {
+    fn probe() -> Self {
+        Self {
+            sent_at: Some(get_timestamp() as u64),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "PingConfigInput")]
+#[serde(default)]
 pub struct PingConfig {
     enabled: bool,
     send_enabled: bool,
+    /// Probes the device periodically, only answered by peers running this daemon.
+    #[graphql(default)]
+    measure_latency: bool,
+}
+
+impl Default for PingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            send_enabled: true,
+            measure_latency: false,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone, SimpleObject)]
 pub struct PingState {
     enabled: bool,
+    /// Latest probes, newest first.
+    #[graphql(skip)]
+    probes: Vec<Probe>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Probe {
+    pub sent_at: u64,
+    pub round_trip_ms: Option<u64>,
+}
+
+/// Round trip stats over the latest probes; probes still within the timeout are left out.
+#[derive(Debug, Clone, PartialEq, SimpleObject)]
+pub struct PingLatency {
+    pub sent: u32,
+    pub lost: u32,
+    pub last_ms: Option<u64>,
+    pub min_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+    pub average_ms: Option<f64>,
+    /// Average difference between consecutive round trips.
+    pub jitter_ms: Option<f64>,
+}
+
+impl PingLatency {
+    /// `probes` is newest first, `now` in milliseconds since epoch.
+    pub fn from_probes(probes: &[Probe], now: u64) -> Option<Self> {
+        let settled = probes
+            .iter()
+            .filter(|probe| {
+                probe.round_trip_ms.is_some()
+                    || now.saturating_sub(probe.sent_at) > PROBE_TIMEOUT_MS
+            })
+            .collect::<Vec<_>>();
+        if settled.is_empty() {
+            return None;
+        }
+        let round_trips = settled
+            .iter()
+            .filter_map(|probe| probe.round_trip_ms)
+            .collect::<Vec<_>>();
+        let average = |values: &[f64]| {
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        };
+        let jitters = round_trips
+            .windows(2)
+            .map(|pair| pair[0].abs_diff(pair[1]) as f64)
+            .collect::<Vec<_>>();
+        Some(Self {
+            sent: settled.len() as u32,
+            lost: (settled.len() - round_trips.len()) as u32,
+            last_ms: round_trips.first().copied(),
+            min_ms: round_trips.iter().min().copied(),
+            max_ms: round_trips.iter().max().copied(),
+            average_ms: average(
+                &round_trips
+                    .iter()
+                    .map(|round_trip| *round_trip as f64)
+                    .collect::<Vec<_>>(),
+            ),
+            jitter_ms: average(&jitters),
+        })
+    }
 }
@@ -0,0 +1,240 @@
+use std::sync::Arc;
+
+use async_graphql::{Context, Enum, InputObject, Object, SimpleObject};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, RwLock};
+use tracing::warn;
+
+use crate::devices::{DeviceManager, Peer};
+
+use super::{
+    mousepad::{special_key, InputAction, Mousepad},
+    Plugin, PluginAction, PluginExt, PluginManager,
+};
+
+/// Pointer updates are frequent, so more of them are buffered than other broadcasts.
+const POINTER_CHANNEL_SIZE: usize = 64;
+
+pub struct Presenter {
+    pointer_sender: broadcast::Sender<PointerState>,
+}
+
+#[Object]
+impl Presenter {
+    /// Sends a slide control key to the focused presentation on this machine.
+    ///
+    /// Uses the mousepad input backend configured for `device_id`, or the global one.
+    pub async fn slide_action<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        action: SlideAction,
+        device_id: Option<String>,
+    ) -> anyhow::Result<&str> {
+        let key = special_key(action.special_key()).ok_or(anyhow::anyhow!("Key not supported"))?;
+        let kind = {
+            let device_manager = context
+                .data::<Arc<RwLock<DeviceManager>>>()
+                .map_err(|e| anyhow::anyhow!("{e:?}"))?
+                .read()
+                .await;
+            let configs = match &device_id {
+                Some(device_id) => {
+                    &device_manager
+                        .devices
+                        .get(device_id)
+                        .ok_or(anyhow::anyhow!("Device not found with given id"))?
+                        .device
+                        .effective_configs
+                }
+                None => &device_manager.defaults.global,
+            };
+            Mousepad::get_config_from_plugin_configs(configs)
+                .clone()
+                .unwrap_or_default()
+                .backend
+        };
+        let plugin_manager = context
+            .data::<Arc<PluginManager>>()
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?
+            .clone();
+        tokio::task::spawn_blocking(move || {
+            plugin_manager
+                .mousepad
+                .backend(kind)?
+                .apply(&[InputAction::KeyClick(key)])
+        })
+        .await??;
+        Ok("success")
+    }
+}
+
+impl Presenter {
+    /// Receives every pointer change so a UI can draw the laser pointer overlay.
+    pub fn subscribe_pointer(&self) -> broadcast::Receiver<PointerState> {
+        self.pointer_sender.subscribe()
+    }
+}
+
+/// Pointer of the payload's device after applying it to `previous`.
+pub fn pointer_after(payload: &PresenterPayload, previous: &PresenterState) -> PointerState {
+    if payload.stop == Some(true) {
+        return PointerState::new(&payload.device_id);
+    }
+    let mut pointer = previous
+        .pointer
+        .clone()
+        .unwrap_or_else(|| PointerState::new(&payload.device_id));
+    pointer.active = true;
+    pointer.x = (pointer.x + payload.dx.unwrap_or_default()).clamp(0.0, 1.0);
+    pointer.y = (pointer.y + payload.dy.unwrap_or_default()).clamp(0.0, 1.0);
+    pointer
+}
+
+impl Plugin for Presenter {
+    type PluginPayload = PresenterPayload;
+    type PluginConfig = PresenterConfig;
+    type PluginState = PresenterState;
+
+    fn init(_device_mangager: &crate::devices::DeviceManager) -> Self {
+        Self {
+            pointer_sender: broadcast::channel(POINTER_CHANNEL_SIZE).0,
+        }
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec!["kdeconnect.presenter".to_string()]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec![]
+    }
+
+    async fn parse_payload(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        _config: &Option<Self::PluginConfig>,
+    ) -> Option<Self::PluginPayload> {
+        if payload.r#type == "kdeconnect.presenter" {
+            match serde_json::from_value::<Self::PluginPayload>(payload.body.clone()) {
+                Ok(mut presenter_payload) => {
+                    presenter_payload.device_id = peer.device_id.clone();
+                    return Some(presenter_payload);
+                }
+                Err(err) => warn!("Cant parse presenter payload {err:#?}"),
+            }
+        }
+        None
+    }
+
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        state.pointer = Some(pointer_after(payload, state));
+    }
+
+    fn handle(
+        &self,
+        payload: &Self::PluginPayload,
+        previous_state: &Self::PluginState,
+    ) -> Option<PluginAction> {
+        // No receivers just means nothing is drawing the overlay.
+        let _ = self
+            .pointer_sender
+            .send(pointer_after(payload, previous_state));
+        None
+    }
+
+    fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+
+    fn should_send(
+        &self,
+        _config: &Option<Self::PluginConfig>,
+        _state: &mut Self::PluginState,
+        _payload: &Self::PluginPayload,
+    ) -> bool {
+        false
+    }
+}
+
+//https://github.com/KDE/kdeconnect-kde/tree/master/plugins/presenter
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PresenterPayload {
+    /// Horizontal motion as a fraction of the screen width.
+    #[serde(default)]
+    dx: Option<f32>,
+
+    /// Vertical motion as a fraction of the screen height.
+    #[serde(default)]
+    dy: Option<f32>,
+
+    #[serde(default)]
+    stop: Option<bool>,
+
+    #[serde(skip)]
+    #[graphql(skip)]
+    pub device_id: String,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct PointerState {
+    pub device_id: String,
+    /// Whether the pointer should be drawn.
+    pub active: bool,
+    /// Position from 0 to 1, starting at the center of the screen.
+    pub x: f32,
+    pub y: f32,
+}
+
+impl PointerState {
+    fn new(device_id: &str) -> Self {
+        Self {
+            device_id: device_id.to_string(),
+            active: false,
+            x: 0.5,
+            y: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum SlideAction {
+    Next,
+    Previous,
+    Start,
+    Stop,
+}
+
+impl SlideAction {
+    /// Mousepad special key code sent for this action.
+    fn special_key(&self) -> u32 {
+        match self {
+            SlideAction::Next => 9,
+            SlideAction::Previous => 8,
+            SlideAction::Start => 25,
+            SlideAction::Stop => 14,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "PresenterConfigInput")]
+pub struct PresenterConfig {
+    enabled: bool,
+}
+
+impl Default for PresenterConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Default, Clone, SimpleObject)]
+pub struct PresenterState {
+    pub pointer: Option<PointerState>,
+}
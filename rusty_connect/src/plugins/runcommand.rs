@@ -0,0 +1,449 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use async_graphql::{Context, InputObject, Object, SimpleObject};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::{
+    devices::{DeviceWithState, Peer},
+    utils::BROADCAST_CHANNEL_SIZE,
+};
+
+use super::{Plugin, PluginAction, PluginExt, Reply};
+
+/// Commands still running after this long are killed.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct RunCommand {
+    output_sender: broadcast::Sender<CommandOutput>,
+}
+
+#[Object]
+impl RunCommand {
+    pub async fn add_command<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        name: String,
+        command: String,
+    ) -> anyhow::Result<RunCommandConfig> {
+        let config = self
+            .update_config(context, &device_id, |config| {
+                config.commands.push(CommandEntry {
+                    key: uuid::Uuid::new_v4().to_string(),
+                    name,
+                    command,
+                });
+                Ok(())
+            })
+            .await?;
+        self.advertise_commands(context, &device_id, &config).await;
+        Ok(config)
+    }
+
+    pub async fn remove_command<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        key: String,
+    ) -> anyhow::Result<RunCommandConfig> {
+        let config = self
+            .update_config(context, &device_id, |config| {
+                let count = config.commands.len();
+                config.commands.retain(|command| command.key != key);
+                if config.commands.len() == count {
+                    return Err(anyhow::anyhow!("No command with given key"));
+                }
+                Ok(())
+            })
+            .await?;
+        self.advertise_commands(context, &device_id, &config).await;
+        Ok(config)
+    }
+
+    /// Asks the peer to send the commands it offers.
+    pub async fn request_commands<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<&str> {
+        let payload = RunCommandPayload {
+            request_command_list: Some(true),
+            ..Default::default()
+        };
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.runcommand.request",
+            payload,
+        )
+        .await?;
+        Ok("success")
+    }
+
+    /// Runs one of the commands offered by the peer.
+    pub async fn trigger_command<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        key: String,
+    ) -> anyhow::Result<&str> {
+        let payload = RunCommandPayload {
+            key: Some(key),
+            ..Default::default()
+        };
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.runcommand.request",
+            payload,
+        )
+        .await?;
+        Ok("success")
+    }
+}
+
+impl RunCommand {
+    /// Receives the output of every command a peer ran on this machine.
+    pub fn subscribe_output(&self) -> broadcast::Receiver<CommandOutput> {
+        self.output_sender.subscribe()
+    }
+
+    async fn advertise_commands<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: &str,
+        config: &RunCommandConfig,
+    ) {
+        match RunCommandPayload::from_config(config) {
+            Ok(payload) => {
+                if let Err(err) = self
+                    .send_payload(context, Some(device_id), "kdeconnect.runcommand", payload)
+                    .await
+                {
+                    info!("Command list not sent {err:?}");
+                }
+            }
+            Err(err) => warn!("Cannot serialize command list {err:?}"),
+        }
+    }
+
+    async fn execute(entry: &CommandEntry, device_id: String) -> anyhow::Result<CommandOutput> {
+        info!("Running command {:?}", entry.name);
+        #[cfg(windows)]
+        let mut command = {
+            let mut command = tokio::process::Command::new("cmd");
+            command.arg("/C").arg(&entry.command);
+            command
+        };
+        #[cfg(not(windows))]
+        let mut command = {
+            let mut command = tokio::process::Command::new("sh");
+            command.arg("-c").arg(&entry.command);
+            command
+        };
+        let output = tokio::time::timeout(COMMAND_TIMEOUT, command.kill_on_drop(true).output())
+            .await
+            .map_err(|_| anyhow::anyhow!("Command {:?} timed out", entry.name))??;
+        Ok(CommandOutput {
+            device_id,
+            key: entry.key.clone(),
+            name: entry.name.clone(),
+            command: entry.command.clone(),
+            exit_code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+}
+
+impl Plugin for RunCommand {
+    type PluginPayload = RunCommandPayload;
+    type PluginConfig = RunCommandConfig;
+    type PluginState = RunCommandState;
+
+    fn init(_device_mangager: &crate::devices::DeviceManager) -> Self {
+        Self {
+            output_sender: broadcast::channel(BROADCAST_CHANNEL_SIZE).0,
+        }
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.runcommand".to_string(),
+            "kdeconnect.runcommand.request".to_string(),
+        ]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.runcommand".to_string(),
+            "kdeconnect.runcommand.request".to_string(),
+        ]
+    }
+
+    /// Sends the command list to paired devices asking for it.
+    async fn reply(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        config: &Option<Self::PluginConfig>,
+    ) -> anyhow::Result<Option<Reply>> {
+        if payload.r#type != "kdeconnect.runcommand.request" || !peer.paired {
+            return Ok(None);
+        }
+        let request = serde_json::from_value::<Self::PluginPayload>(payload.body.clone())?;
+        if request.request_command_list != Some(true) || request.key.is_some() {
+            return Ok(None);
+        }
+        let list = RunCommandPayload::from_config(&config.clone().unwrap_or_default())?;
+        Ok(Some(Reply::new("kdeconnect.runcommand", list)?))
+    }
+
+    async fn parse_payload(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        config: &Option<Self::PluginConfig>,
+    ) -> Option<Self::PluginPayload> {
+        if payload.r#type == "kdeconnect.runcommand" {
+            match serde_json::from_value::<Self::PluginPayload>(payload.body.clone()) {
+                Ok(payload) => return Some(payload),
+                Err(err) => warn!("Cant parse runcommand payload {err:#?}"),
+            }
+        } else if payload.r#type == "kdeconnect.runcommand.request" {
+            let mut request =
+                match serde_json::from_value::<Self::PluginPayload>(payload.body.clone()) {
+                    Ok(request) => request,
+                    Err(err) => {
+                        warn!("Cant parse runcommand request {err:#?}");
+                        return None;
+                    }
+                };
+            if !peer.paired {
+                warn!("Ignoring command request from unpaired device");
+                return Some(request);
+            }
+            if let Some(key) = &request.key {
+                let config = config.clone().unwrap_or_default();
+                match config
+                    .commands
+                    .into_iter()
+                    .find(|command| &command.key == key)
+                {
+                    Some(entry) => {
+                        request.command = Some(entry);
+                        request.device_id = peer.device_id.clone();
+                    }
+                    None => warn!("No command with key {key}"),
+                }
+            }
+            return Some(request);
+        }
+        None
+    }
+
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        if let Some(command_list) = &payload.command_list {
+            match serde_json::from_str::<BTreeMap<String, RemoteCommand>>(command_list) {
+                Ok(commands) => {
+                    state.remote_commands = commands
+                        .into_iter()
+                        .map(|(key, command)| CommandEntry {
+                            key,
+                            name: command.name,
+                            command: command.command,
+                        })
+                        .collect();
+                }
+                Err(err) => warn!("Cannot parse remote command list {err:?}"),
+            }
+        }
+    }
+
+    /// Offers the command list right away, as KDE Connect does.
+    fn connected(
+        &self,
+        device: &DeviceWithState,
+        config: &Option<Self::PluginConfig>,
+    ) -> Option<PluginAction> {
+        if !device.device.paired || !device.capabilities.can_send("kdeconnect.runcommand") {
+            return None;
+        }
+        let peer = device.peer()?;
+        let list = match RunCommandPayload::from_config(&config.clone().unwrap_or_default()) {
+            Ok(list) => list,
+            Err(err) => {
+                warn!("Cannot serialize command list {err:?}");
+                return None;
+            }
+        };
+        Some(PluginAction::Async(Box::pin(async move {
+            if let Err(err) = peer.send("kdeconnect.runcommand", list).await {
+                warn!("Cannot send command list {err:?}");
+            }
+        })))
+    }
+
+    /// Runs the requested command and broadcasts its output once it finishes.
+    fn handle(
+        &self,
+        payload: &Self::PluginPayload,
+        _previous_state: &Self::PluginState,
+    ) -> Option<PluginAction> {
+        let entry = payload.command.clone()?;
+        let device_id = payload.device_id.clone();
+        let output_sender = self.output_sender.clone();
+        Some(PluginAction::Async(Box::pin(async move {
+            match Self::execute(&entry, device_id).await {
+                // Nobody listening is fine.
+                Ok(output) => {
+                    let _ = output_sender.send(output);
+                }
+                Err(err) => warn!("Running command failed {err:?}"),
+            }
+        })))
+    }
+
+    fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+
+    fn validate_config(&self, config: &Self::PluginConfig) -> anyhow::Result<()> {
+        let mut keys = BTreeSet::new();
+        for entry in &config.commands {
+            if entry.name.trim().is_empty() || entry.command.trim().is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Command {:?} needs a name and a command",
+                    entry.key
+                ));
+            }
+            if !keys.insert(&entry.key) {
+                return Err(anyhow::anyhow!("Duplicate command key {:?}", entry.key));
+            }
+        }
+        Ok(())
+    }
+
+    fn should_send(
+        &self,
+        config: &Option<Self::PluginConfig>,
+        _state: &mut Self::PluginState,
+        _payload: &Self::PluginPayload,
+    ) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+}
+
+//https://github.com/KDE/kdeconnect-kde/tree/master/plugins/runcommand
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RunCommandPayload {
+    /// JSON object of `key -> {name, command}`, encoded as a string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command_list: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    can_add_command: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_command_list: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    setup: Option<bool>,
+
+    /// Local command a paired device asked to run, executed in [`Plugin::handle`].
+    #[serde(skip)]
+    #[graphql(skip)]
+    command: Option<CommandEntry>,
+
+    #[serde(skip)]
+    #[graphql(skip)]
+    device_id: String,
+}
+
+impl RunCommandPayload {
+    fn from_config(config: &RunCommandConfig) -> anyhow::Result<Self> {
+        let commands = config
+            .commands
+            .iter()
+            .map(|entry| {
+                (
+                    entry.key.clone(),
+                    RemoteCommand {
+                        name: entry.name.clone(),
+                        command: entry.command.clone(),
+                    },
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+        Ok(Self {
+            command_list: Some(serde_json::to_string(&commands)?),
+            can_add_command: Some(false),
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RemoteCommand {
+    name: String,
+    command: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "CommandEntryInput")]
+pub struct CommandEntry {
+    pub key: String,
+    pub name: String,
+    pub command: String,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct CommandOutput {
+    pub device_id: String,
+    pub key: String,
+    pub name: String,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "RunCommandConfigInput")]
+#[serde(default)]
+pub struct RunCommandConfig {
+    enabled: bool,
+    commands: Vec<CommandEntry>,
+}
+
+impl Default for RunCommandConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            commands: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, SimpleObject)]
+pub struct RunCommandState {
+    /// Commands offered by the peer.
+    remote_commands: Vec<CommandEntry>,
+}
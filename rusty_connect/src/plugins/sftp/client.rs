@@ -0,0 +1,128 @@
+use std::{path::Path, sync::Arc};
+
+use async_graphql::SimpleObject;
+use russh::client;
+use russh_sftp::client::SftpSession;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::debug;
+
+/// Accepts whatever host key the server presents.
+///
+/// Peers generate a fresh SSH key for every browsing session and only share the
+/// credentials over the already authenticated KDE Connect link, so there is no
+/// key to pin against.
+struct AcceptAnyKey;
+
+impl client::Handler for AcceptAnyKey {
+    type Error = russh::Error;
+
+    async fn check_server_key(
+        &mut self,
+        _server_public_key: &russh::keys::PublicKey,
+    ) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct SftpEntry {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    pub size: Option<u64>,
+    /// Modification time in seconds since epoch.
+    pub modified: Option<u32>,
+}
+
+/// Minimal SFTP client used to browse a peer's storage.
+pub struct SftpClient {
+    session: SftpSession,
+    _handle: client::Handle<AcceptAnyKey>,
+}
+
+impl SftpClient {
+    pub async fn connect(
+        host: &str,
+        port: u16,
+        user: &str,
+        password: &str,
+    ) -> anyhow::Result<Self> {
+        debug!("Connecting sftp to {host}:{port}");
+        let config = Arc::new(client::Config::default());
+        let mut handle = client::connect(config, (host, port), AcceptAnyKey).await?;
+        if !handle
+            .authenticate_password(user, password)
+            .await?
+            .success()
+        {
+            return Err(anyhow::anyhow!("SFTP authentication failed"));
+        }
+        let channel = handle.channel_open_session().await?;
+        channel.request_subsystem(true, "sftp").await?;
+        let session = SftpSession::new(channel.into_stream()).await?;
+        Ok(Self {
+            session,
+            _handle: handle,
+        })
+    }
+
+    pub async fn list(&self, path: &str) -> anyhow::Result<Vec<SftpEntry>> {
+        let mut entries = self
+            .session
+            .read_dir(path)
+            .await?
+            .map(|entry| {
+                let metadata = entry.metadata();
+                SftpEntry {
+                    name: entry.file_name(),
+                    path: entry.path(),
+                    is_dir: entry.file_type().is_dir(),
+                    size: metadata.size,
+                    modified: metadata.mtime,
+                }
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        Ok(entries)
+    }
+
+    pub async fn download(&self, remote_path: &str, local_path: &Path) -> anyhow::Result<u64> {
+        let mut remote = self.session.open(remote_path).await?;
+        let mut local = tokio::fs::File::create(local_path).await?;
+        let copied = tokio::io::copy(&mut remote, &mut local).await?;
+        local.flush().await?;
+        Ok(copied)
+    }
+
+    pub async fn upload(&self, local_path: &Path, remote_path: &str) -> anyhow::Result<u64> {
+        let mut local = tokio::fs::File::open(local_path).await?;
+        let mut remote = self.session.create(remote_path).await?;
+        let mut buffer = vec![0u8; 32 * 1024];
+        let mut copied = 0u64;
+        loop {
+            let read = local.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            remote.write_all(&buffer[..read]).await?;
+            copied += read as u64;
+        }
+        remote.shutdown().await?;
+        Ok(copied)
+    }
+
+    /// Removes a file, or an empty directory.
+    pub async fn delete(&self, remote_path: &str) -> anyhow::Result<()> {
+        if self.session.metadata(remote_path).await?.is_dir() {
+            self.session.remove_dir(remote_path).await?;
+        } else {
+            self.session.remove_file(remote_path).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn close(self) -> anyhow::Result<()> {
+        self.session.close().await?;
+        Ok(())
+    }
+}
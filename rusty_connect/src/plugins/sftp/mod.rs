@@ -0,0 +1,338 @@
+use std::path::{Path, PathBuf};
+
+use async_graphql::{ComplexObject, Context, InputObject, Object, SimpleObject};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::devices::Peer;
+
+use super::{Plugin, PluginExt};
+
+pub use self::client::{SftpClient, SftpEntry};
+
+pub mod client;
+
+pub struct Sftp {
+    pub downloads_path: PathBuf,
+}
+
+#[Object]
+impl Sftp {
+    /// Asks the peer to start its SFTP server; credentials arrive as a `kdeconnect.sftp` payload.
+    pub async fn start_browsing<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<&str> {
+        let payload = SftpPayload {
+            start_browsing: Some(true),
+            ..Default::default()
+        };
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.sftp.request",
+            payload,
+        )
+        .await?;
+        Ok("success")
+    }
+
+    pub async fn list_remote_files<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        path: Option<String>,
+    ) -> anyhow::Result<Vec<SftpEntry>> {
+        let (client, root) = self.connect(context, &device_id).await?;
+        let entries = client.list(path.as_deref().unwrap_or(&root)).await;
+        client.close().await?;
+        entries
+    }
+
+    /// Downloads a remote file, into the downloads folder unless `local_path` is given.
+    pub async fn download_remote_file<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        remote_path: String,
+        local_path: Option<String>,
+    ) -> anyhow::Result<String> {
+        let local_path = match local_path {
+            Some(local_path) => PathBuf::from(local_path),
+            None => {
+                let file_name = Path::new(&remote_path)
+                    .file_name()
+                    .ok_or(anyhow::anyhow!("Remote path has no file name"))?;
+                self.downloads_path.join(file_name)
+            }
+        };
+        let (client, _) = self.connect(context, &device_id).await?;
+        let result = client.download(&remote_path, &local_path).await;
+        client.close().await?;
+        result?;
+        Ok(local_path.to_string_lossy().to_string())
+    }
+
+    pub async fn upload_remote_file<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        local_path: String,
+        remote_path: String,
+    ) -> anyhow::Result<u64> {
+        let (client, _) = self.connect(context, &device_id).await?;
+        let result = client.upload(Path::new(&local_path), &remote_path).await;
+        client.close().await?;
+        result
+    }
+
+    pub async fn delete_remote_file<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        remote_path: String,
+    ) -> anyhow::Result<&str> {
+        let (client, _) = self.connect(context, &device_id).await?;
+        let result = client.delete(&remote_path).await;
+        client.close().await?;
+        result?;
+        Ok("success")
+    }
+}
+
+impl Sftp {
+    /// Connects using the credentials last received from the device, returning the client and its root path.
+    async fn connect<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: &str,
+    ) -> anyhow::Result<(SftpClient, String)> {
+        let state = self.get_state(context, device_id).await?;
+        let credentials = state
+            .connection
+            .ok_or(anyhow::anyhow!(
+                "No SFTP credentials received, call startBrowsing first"
+            ))?
+            .credentials()?;
+        let client = SftpClient::connect(
+            &credentials.host,
+            credentials.port,
+            &credentials.user,
+            &credentials.password,
+        )
+        .await?;
+        Ok((client, credentials.root))
+    }
+}
+
+impl Plugin for Sftp {
+    type PluginPayload = SftpPayload;
+    type PluginConfig = SftpConfig;
+    type PluginState = SftpState;
+
+    fn init(device_mangager: &crate::devices::DeviceManager) -> Self {
+        Self {
+            downloads_path: device_mangager.downloads_path.clone(),
+        }
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec!["kdeconnect.sftp".to_string()]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec!["kdeconnect.sftp.request".to_string()]
+    }
+
+    async fn parse_payload(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        _config: &Option<Self::PluginConfig>,
+    ) -> Option<Self::PluginPayload> {
+        if payload.r#type == "kdeconnect.sftp" {
+            match serde_json::from_value::<Self::PluginPayload>(payload.body.clone()) {
+                Ok(mut sftp_payload) => {
+                    // Older peers leave the ip out and expect the connection address to be used.
+                    if sftp_payload.ip.is_none() {
+                        sftp_payload.ip = Some(peer.address.ip().to_string());
+                    }
+                    return Some(sftp_payload);
+                }
+                Err(err) => warn!("Cant parse sftp payload {err:#?}"),
+            }
+        }
+        None
+    }
+
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        if payload.error_message.is_some() {
+            state.connection = None;
+        } else if payload.port.is_some() {
+            state.connection = Some(payload.clone());
+        }
+    }
+
+    fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+
+    fn should_send(
+        &self,
+        config: &Option<Self::PluginConfig>,
+        _state: &mut Self::PluginState,
+        _payload: &Self::PluginPayload,
+    ) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+}
+
+//https://github.com/KDE/kdeconnect-kde/tree/master/plugins/sftp
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Default, Clone)]
+#[graphql(complex)]
+#[serde(rename_all = "camelCase")]
+pub struct SftpPayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start_browsing: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ip: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    port: Option<u16>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    password: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    multi_paths: Option<Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path_names: Option<Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error_message: Option<String>,
+}
+
+#[ComplexObject]
+impl SftpPayload {
+    async fn mounts(&self) -> Vec<SftpMount> {
+        self.mount_points()
+    }
+}
+
+impl SftpPayload {
+    pub fn credentials(&self) -> anyhow::Result<SftpCredentials> {
+        let (Some(host), Some(port), Some(user), Some(password)) =
+            (&self.ip, self.port, &self.user, &self.password)
+        else {
+            return Err(anyhow::anyhow!("Incomplete SFTP credentials"));
+        };
+        Ok(SftpCredentials {
+            host: host.clone(),
+            port,
+            user: user.clone(),
+            password: password.clone(),
+            root: self.path.clone().unwrap_or("/".to_string()),
+        })
+    }
+
+    /// Storage roots offered by the device, named after `pathNames` where given.
+    pub fn mount_points(&self) -> Vec<SftpMount> {
+        let Some(paths) = &self.multi_paths else {
+            return self
+                .path
+                .iter()
+                .map(|path| SftpMount::unnamed(path))
+                .collect();
+        };
+        let names = self.path_names.clone().unwrap_or_default();
+        paths
+            .iter()
+            .enumerate()
+            .map(|(index, path)| match names.get(index) {
+                Some(name) => SftpMount {
+                    name: name.clone(),
+                    path: path.clone(),
+                },
+                None => SftpMount::unnamed(path),
+            })
+            .collect()
+    }
+}
+
+/// Where and how to log into the SFTP server a device started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SftpCredentials {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    pub root: String,
+}
+
+impl SftpCredentials {
+    /// `sftp://` URL of the root folder, without the password.
+    pub fn url(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        let root = self.root.trim_start_matches('/');
+        format!("sftp://{}@{host}:{}/{root}", self.user, self.port)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, SimpleObject)]
+pub struct SftpMount {
+    pub name: String,
+    pub path: String,
+}
+
+impl SftpMount {
+    /// Named after the last path segment, as no name was sent.
+    fn unnamed(path: &str) -> Self {
+        let name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or(path.to_string());
+        Self {
+            name,
+            path: path.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "SftpConfigInput")]
+pub struct SftpConfig {
+    enabled: bool,
+}
+
+impl Default for SftpConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Default, Clone, SimpleObject)]
+pub struct SftpState {
+    /// Credentials from the last `kdeconnect.sftp` payload.
+    pub connection: Option<SftpPayload>,
+}
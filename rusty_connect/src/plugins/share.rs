@@ -1,30 +1,17 @@
-use std::{
-    collections::HashMap,
-    net::SocketAddr,
-    path::{Path, PathBuf},
-    sync::Arc,
-};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
-use async_graphql::{Object, SimpleObject, Subscription, Union};
-use async_stream::stream;
+use async_graphql::{InputObject, Object, SimpleObject, Union};
 
-use futures::Stream;
 use serde::{Deserialize, Serialize};
-use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::TcpStream,
-    sync::Mutex,
-};
-use tokio_native_tls::{
-    // rustls::{
-    //     pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer, ServerName},
-    //     ClientConfig,
-    // },
-    native_tls::{self, Identity},
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{
+    cert::CertPair,
+    devices::Peer,
+    payload_transfer::{self, TransferOptions},
+    payloads::PayloadTransferInfo,
 };
-use tracing::{debug, info, warn};
-
-use crate::{cert::CertPair, payloads::PayloadTransferInfo};
 
 use super::Plugin;
 
@@ -68,7 +55,8 @@ impl Plugin for Share {
     async fn parse_payload(
         &self,
         payload: &crate::payloads::Payload,
-        address: SocketAddr,
+        peer: &Peer,
+        _config: &Option<Self::PluginConfig>,
     ) -> Option<Self::PluginPayload> {
         info!("Payload received {payload:#?}");
 
@@ -89,21 +77,35 @@ impl Plugin for Share {
                             }));
                         let certs = self.certs.clone();
                         let port = *port;
+                        let address = peer.address;
+                        let tx = Arc::new(tx);
+                        let options = TransferOptions::for_peer(peer).with_progress({
+                            let tx = tx.clone();
+                            move |progress| {
+                                tx.send_replace(DownloadProgress::Downloading(Progress {
+                                    total_bytes: progress.total,
+                                    read_bytes: progress.transferred,
+                                }));
+                            }
+                        });
                         let _download_task = tokio::spawn(async move {
-                            if let Err(err) = Self::receive_file(
-                                address,
-                                port,
-                                total_size as usize,
-                                &file_path,
-                                certs,
-                                &tx,
+                            match payload_transfer::download_to_file(
+                                address, port, total_size, &file_path, &certs, &options,
                             )
                             .await
                             {
-                                warn!("Download failed {err:?} ");
-                                tx.send_replace(DownloadProgress::Failed(DownloadFailed {
-                                    reason: format!("{err:#?}"),
-                                }));
+                                Ok(total_bytes) => {
+                                    tx.send_replace(DownloadProgress::Completed(Completed {
+                                        total_bytes,
+                                        path: file_path.to_string_lossy().to_string(),
+                                    }));
+                                }
+                                Err(err) => {
+                                    warn!("Download failed {err:?} ");
+                                    tx.send_replace(DownloadProgress::Failed(DownloadFailed {
+                                        reason: format!("{err:#?}"),
+                                    }));
+                                }
                             }
                         });
                         {
@@ -140,73 +142,6 @@ impl Plugin for Share {
     }
 }
 
-impl Share {
-    pub async fn receive_file(
-        address: SocketAddr,
-        port: u16,
-        size: usize,
-        path: &Path,
-        certs: CertPair,
-        progress_sender: &tokio::sync::watch::Sender<DownloadProgress>,
-    ) -> anyhow::Result<()> {
-        const BUFFER_SIZE: usize = 10 * 1024;
-
-        debug!("TCP Stream initialising");
-        let stream = TcpStream::connect(SocketAddr::new(address.ip(), port)).await?;
-
-        debug!("TLS Config initializing");
-        let key = Identity::from_pkcs8(&certs.0, &certs.1)?;
-        let connector = native_tls::TlsConnector::builder()
-            .danger_accept_invalid_certs(true)
-            .identity(key)
-            .build()?;
-        let tls_connector = tokio_native_tls::TlsConnector::from(connector);
-
-        // let server_name = ServerName::IpAddress(address.ip().into());
-        let server_name = address.to_string();
-        debug!("Upgrading to TLS Stream to server name: {server_name:?}");
-        let mut tls_stream = tls_connector.connect(&server_name, stream).await?;
-
-        // debug!("Creating file");
-        let mut file = tokio::fs::File::create(path).await?;
-
-        let mut buffer = vec![0u8; BUFFER_SIZE];
-
-        let mut total_bytes_read = 0;
-        while total_bytes_read < size {
-            let bytes_to_read = std::cmp::min(size - total_bytes_read, BUFFER_SIZE);
-            let bytes_read = tls_stream.read(&mut buffer[..bytes_to_read]).await?;
-            if bytes_read == 0 {
-                // End of stream reached
-                break;
-            }
-
-            // Write the read data to the file
-            file.write_all(&buffer[..bytes_read]).await?;
-
-            // Update the total bytes read
-            total_bytes_read += bytes_read;
-
-            progress_sender.send_replace(DownloadProgress::Downloading(Progress {
-                total_bytes: size as u64,
-                read_bytes: total_bytes_read as u64,
-            }));
-        }
-        if total_bytes_read == size {
-            progress_sender.send_replace(DownloadProgress::Completed(Completed {
-                total_bytes: total_bytes_read as u64,
-                path: path.to_string_lossy().to_string(),
-            }));
-        } else {
-            progress_sender.send_replace(DownloadProgress::Failed(DownloadFailed {
-                reason: "stopped before completion".to_string(),
-            }));
-        }
-        debug!("Receive icon completed");
-        Ok(())
-    }
-}
-
 //https://github.com/KDE/kdeconnect-kde/blob/705a72c0779babae809928fef4ad018c8562470e/plugins/notifications/README#L13C1-L22C1
 #[derive(SimpleObject, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -221,11 +156,18 @@ pub struct SharePayload {
     download_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone, SimpleObject)]
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "ShareConfigInput")]
 pub struct ShareConfig {
     enabled: bool,
 }
 
+impl Default for ShareConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Default, Clone, SimpleObject)]
 pub struct ShareState {
     enabled: bool,
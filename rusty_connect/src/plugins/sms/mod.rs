@@ -0,0 +1,460 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_graphql::{Context, InputObject, Object, SimpleObject};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    cert::CertPair,
+    devices::Peer,
+    payload_transfer::{self, TransferOptions},
+    payloads::PayloadType,
+    utils::get_timestamp,
+};
+
+use self::store::SmsStore;
+
+use super::{Plugin, PluginAction, PluginExt, ReceivedPayload};
+
+pub mod store;
+
+/// MMS attachments larger than this are not downloaded.
+const MAX_ATTACHMENT_SIZE: u64 = 64 * 1024 * 1024;
+
+pub struct Sms {
+    pub store: Arc<SmsStore>,
+    pub certs: CertPair,
+    /// Emits downloaded attachments like any other received payload.
+    payload_sender: flume::Sender<PayloadType>,
+}
+
+#[Object]
+impl Sms {
+    /// Asks the peer for the latest message of every conversation.
+    pub async fn request_conversations<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<&str> {
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.sms.request_conversations",
+            SmsPayload::default(),
+        )
+        .await?;
+        Ok("success")
+    }
+
+    /// Asks the peer for the messages of one thread, older than `range_start_timestamp` if given.
+    pub async fn request_conversation<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        thread_id: i64,
+        range_start_timestamp: Option<i64>,
+        number_to_request: Option<i64>,
+    ) -> anyhow::Result<&str> {
+        let payload = SmsPayload {
+            thread_id: Some(thread_id),
+            range_start_timestamp,
+            number_to_request,
+            ..Default::default()
+        };
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.sms.request_conversation",
+            payload,
+        )
+        .await?;
+        Ok("success")
+    }
+
+    pub async fn request_attachment<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        part_id: i64,
+        unique_identifier: String,
+    ) -> anyhow::Result<&str> {
+        let payload = SmsPayload {
+            part_id: Some(part_id),
+            unique_identifier: Some(unique_identifier),
+            ..Default::default()
+        };
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.sms.request_attachment",
+            payload,
+        )
+        .await?;
+        Ok("success")
+    }
+
+    pub async fn send_sms<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        addresses: Vec<String>,
+        message_body: String,
+        sub_id: Option<i64>,
+    ) -> anyhow::Result<&str> {
+        let payload = SmsPayload {
+            version: Some(2),
+            addresses: Some(
+                addresses
+                    .into_iter()
+                    .map(|address| SmsAddress { address })
+                    .collect(),
+            ),
+            message_body: Some(message_body),
+            sub_id,
+            ..Default::default()
+        };
+        self.send_payload(context, Some(&device_id), "kdeconnect.sms.request", payload)
+            .await?;
+        Ok("success")
+    }
+
+    /// Cached conversations, newest first.
+    pub async fn conversations(&self, device_id: String) -> anyhow::Result<Vec<Conversation>> {
+        self.store.conversations(&device_id).await
+    }
+
+    /// Cached messages of a thread, oldest first.
+    pub async fn messages(
+        &self,
+        device_id: String,
+        thread_id: i64,
+    ) -> anyhow::Result<Vec<SmsMessage>> {
+        self.store.messages(&device_id, thread_id).await
+    }
+
+    /// Local path of a downloaded attachment, if it was received.
+    pub async fn attachment_path(
+        &self,
+        device_id: String,
+        unique_identifier: String,
+    ) -> anyhow::Result<Option<String>> {
+        let path = self.attachment_file(&device_id, &unique_identifier)?;
+        Ok(tokio::fs::try_exists(&path)
+            .await?
+            .then(|| path.to_string_lossy().to_string()))
+    }
+}
+
+impl Sms {
+    fn attachment_file(
+        &self,
+        device_id: &str,
+        file_name: &str,
+    ) -> anyhow::Result<std::path::PathBuf> {
+        let file_name = Path::new(file_name)
+            .file_name()
+            .ok_or(anyhow::anyhow!("Invalid attachment name"))?;
+        Ok(self
+            .store
+            .device_folder(device_id)?
+            .join("attachments")
+            .join(file_name))
+    }
+
+    /// Where the attachment announced by `payload` goes, if it carries one.
+    fn attachment_download(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        file_name: &str,
+    ) -> anyhow::Result<AttachmentDownload> {
+        let (Some(size), Some(transfer_info)) =
+            (payload.payload_size, &payload.payload_transfer_info)
+        else {
+            return Err(anyhow::anyhow!("Attachment has no payload"));
+        };
+        Ok(AttachmentDownload {
+            peer: peer.clone(),
+            port: transfer_info.port,
+            size,
+            file_path: self.attachment_file(&peer.device_id, file_name)?,
+        })
+    }
+}
+
+/// Attachment announced by a `kdeconnect.sms.attachment_file` packet, fetched in [`Plugin::handle`].
+#[derive(Debug, Clone)]
+struct AttachmentDownload {
+    peer: Peer,
+    port: u16,
+    size: u64,
+    file_path: PathBuf,
+}
+
+impl AttachmentDownload {
+    async fn run(self, certs: &CertPair) -> anyhow::Result<String> {
+        if let Some(parent) = self.file_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let options = TransferOptions::for_peer(&self.peer).with_max_size(MAX_ATTACHMENT_SIZE);
+        payload_transfer::download_to_file(
+            self.peer.address,
+            self.port,
+            self.size,
+            &self.file_path,
+            certs,
+            &options,
+        )
+        .await?;
+        Ok(self.file_path.to_string_lossy().to_string())
+    }
+}
+
+impl Plugin for Sms {
+    type PluginPayload = SmsPayload;
+    type PluginConfig = SmsConfig;
+    type PluginState = SmsState;
+
+    fn init(device_mangager: &crate::devices::DeviceManager) -> Self {
+        Self {
+            store: Arc::new(SmsStore::new(device_mangager.sms_path.clone())),
+            certs: device_mangager.certs.clone(),
+            payload_sender: device_mangager.sender.clone(),
+        }
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.sms.messages".to_string(),
+            "kdeconnect.sms.attachment_file".to_string(),
+        ]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.sms.request".to_string(),
+            "kdeconnect.sms.request_conversations".to_string(),
+            "kdeconnect.sms.request_conversation".to_string(),
+            "kdeconnect.sms.request_attachment".to_string(),
+        ]
+    }
+
+    async fn parse_payload(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        _config: &Option<Self::PluginConfig>,
+    ) -> Option<Self::PluginPayload> {
+        if payload.r#type == "kdeconnect.sms.messages" {
+            match serde_json::from_value::<Self::PluginPayload>(payload.body.clone()) {
+                Ok(mut sms_payload) => {
+                    sms_payload.device_id = peer.device_id.clone();
+                    return Some(sms_payload);
+                }
+                Err(err) => warn!("Cant parse sms payload {err:#?}"),
+            }
+        } else if payload.r#type == "kdeconnect.sms.attachment_file" {
+            match serde_json::from_value::<Self::PluginPayload>(payload.body.clone()) {
+                Ok(mut sms_payload) => {
+                    sms_payload.device_id = peer.device_id.clone();
+                    if let Some(file_name) = &sms_payload.filename {
+                        match self.attachment_download(payload, peer, file_name) {
+                            Ok(download) => sms_payload.attachment_download = Some(download),
+                            Err(err) => warn!("Cannot get attachment {err:?}"),
+                        }
+                    }
+                    return Some(sms_payload);
+                }
+                Err(err) => warn!("Cant parse sms attachment payload {err:#?}"),
+            }
+        }
+        None
+    }
+
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        if payload.messages.is_some() {
+            state.last_synced_at = Some(get_timestamp() as u64);
+        }
+    }
+
+    /// Caches received messages, or downloads an attachment and emits it once stored.
+    fn handle(
+        &self,
+        payload: &Self::PluginPayload,
+        _previous_state: &Self::PluginState,
+    ) -> Option<PluginAction> {
+        let device_id = payload.device_id.clone();
+        if let Some(messages) = payload.messages.clone() {
+            let store = self.store.clone();
+            return Some(PluginAction::Async(Box::pin(async move {
+                if let Err(err) = store.merge(&device_id, &messages).await {
+                    warn!("Cannot cache messages {err:?}");
+                }
+            })));
+        }
+        let download = payload.attachment_download.clone()?;
+        let certs = self.certs.clone();
+        let payload_sender = self.payload_sender.clone();
+        let filename = payload.filename.clone();
+        Some(PluginAction::Async(Box::pin(async move {
+            let path = match download.run(&certs).await {
+                Ok(path) => path,
+                Err(err) => {
+                    warn!("Cannot get attachment {err:?}");
+                    return;
+                }
+            };
+            info!("Attachment saved to {path}");
+            let stored = SmsPayload {
+                filename,
+                attachment_path: Some(path),
+                ..Default::default()
+            };
+            if let Err(err) = payload_sender.try_send((device_id, ReceivedPayload::Sms(stored))) {
+                warn!("Nothing to handle attachment {err:?}");
+            }
+        })))
+    }
+
+    fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+
+    fn should_send(
+        &self,
+        config: &Option<Self::PluginConfig>,
+        _state: &mut Self::PluginState,
+        _payload: &Self::PluginPayload,
+    ) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+}
+
+//https://github.com/KDE/kdeconnect-kde/tree/master/plugins/sms
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SmsPayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    messages: Option<Vec<SmsMessage>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<i32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    addresses: Option<Vec<SmsAddress>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    message_body: Option<String>,
+
+    #[serde(rename = "subID", default, skip_serializing_if = "Option::is_none")]
+    sub_id: Option<i64>,
+
+    #[serde(rename = "threadID", default, skip_serializing_if = "Option::is_none")]
+    thread_id: Option<i64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    range_start_timestamp: Option<i64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    number_to_request: Option<i64>,
+
+    #[serde(rename = "part_id", default, skip_serializing_if = "Option::is_none")]
+    part_id: Option<i64>,
+
+    #[serde(
+        rename = "unique_identifier",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    unique_identifier: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    filename: Option<String>,
+
+    /// Where a received attachment was stored, set on the payload emitted after the download.
+    #[serde(skip)]
+    attachment_path: Option<String>,
+
+    #[serde(skip)]
+    #[graphql(skip)]
+    attachment_download: Option<AttachmentDownload>,
+
+    #[serde(skip)]
+    #[graphql(skip)]
+    device_id: String,
+}
+
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Clone)]
+pub struct SmsAddress {
+    pub address: String,
+}
+
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Clone)]
+pub struct SmsMessage {
+    #[serde(rename = "_id")]
+    pub id: i64,
+    pub thread_id: i64,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default)]
+    pub addresses: Vec<SmsAddress>,
+    /// Milliseconds since epoch.
+    pub date: i64,
+    /// Android message box, 1 for received and 2 for sent.
+    #[serde(rename = "type", default)]
+    pub message_type: i32,
+    #[serde(default)]
+    pub read: i32,
+    #[serde(default)]
+    pub event: Option<i32>,
+    #[serde(default)]
+    pub sub_id: Option<i64>,
+    #[serde(default)]
+    pub attachments: Vec<SmsAttachment>,
+}
+
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Clone)]
+pub struct SmsAttachment {
+    pub part_id: i64,
+    pub mime_type: String,
+    /// Base64 encoded preview.
+    #[serde(default)]
+    pub encoded_thumbnail: Option<String>,
+    pub unique_identifier: String,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct Conversation {
+    pub thread_id: i64,
+    pub message_count: usize,
+    pub latest: SmsMessage,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "SmsConfigInput")]
+pub struct SmsConfig {
+    enabled: bool,
+}
+
+impl Default for SmsConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Default, Clone, SimpleObject)]
+pub struct SmsState {
+    /// When messages were last received, in milliseconds since epoch.
+    last_synced_at: Option<u64>,
+}
@@ -0,0 +1,106 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::utils::is_safe_file_name;
+
+use super::{Conversation, SmsMessage};
+
+/// Messages of every thread, keyed by thread id and message id.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredMessages {
+    threads: BTreeMap<i64, BTreeMap<i64, SmsMessage>>,
+}
+
+/// Per-device message cache kept as JSON in the sms folder.
+pub struct SmsStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl SmsStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Folder holding everything cached for `device_id`.
+    pub fn device_folder(&self, device_id: &str) -> anyhow::Result<PathBuf> {
+        if !is_safe_file_name(device_id) {
+            return Err(anyhow::anyhow!("Invalid device id {device_id:?}"));
+        }
+        Ok(self.path.join(device_id))
+    }
+
+    pub async fn merge(&self, device_id: &str, messages: &[SmsMessage]) -> anyhow::Result<()> {
+        let _guard = self.lock.lock().await;
+        let file = self.device_folder(device_id)?.join("messages.json");
+        let mut stored = Self::read(&file).await?;
+        for message in messages {
+            stored
+                .threads
+                .entry(message.thread_id)
+                .or_default()
+                .insert(message.id, message.clone());
+        }
+        if let Some(parent) = file.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&file, serde_json::to_vec(&stored)?).await?;
+        Ok(())
+    }
+
+    /// Conversations ordered by their latest message, newest first.
+    pub async fn conversations(&self, device_id: &str) -> anyhow::Result<Vec<Conversation>> {
+        let _guard = self.lock.lock().await;
+        let stored = Self::read(&self.device_folder(device_id)?.join("messages.json")).await?;
+        let mut conversations = stored
+            .threads
+            .into_iter()
+            .filter_map(|(thread_id, messages)| {
+                let message_count = messages.len();
+                let latest = messages.into_values().max_by_key(|message| message.date)?;
+                Some(Conversation {
+                    thread_id,
+                    message_count,
+                    latest,
+                })
+            })
+            .collect::<Vec<_>>();
+        conversations.sort_by_key(|conversation| std::cmp::Reverse(conversation.latest.date));
+        Ok(conversations)
+    }
+
+    /// Messages of a thread, oldest first.
+    pub async fn messages(
+        &self,
+        device_id: &str,
+        thread_id: i64,
+    ) -> anyhow::Result<Vec<SmsMessage>> {
+        let _guard = self.lock.lock().await;
+        let mut stored = Self::read(&self.device_folder(device_id)?.join("messages.json")).await?;
+        let mut messages = stored
+            .threads
+            .remove(&thread_id)
+            .map(|messages| messages.into_values().collect::<Vec<_>>())
+            .unwrap_or_default();
+        messages.sort_by_key(|message| message.date);
+        Ok(messages)
+    }
+
+    /// Cached messages, an unreadable cache is an error so it is never overwritten.
+    async fn read(file: &Path) -> anyhow::Result<StoredMessages> {
+        match tokio::fs::read(file).await {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(|err| anyhow::anyhow!("Corrupt message cache {file:?}: {err}")),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(StoredMessages::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
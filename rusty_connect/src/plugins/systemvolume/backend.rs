@@ -0,0 +1,179 @@
+use std::{
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+};
+
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::debug;
+
+/// Volume of a sink at 100%, in PulseAudio units.
+pub const NORMAL_VOLUME: u32 = 65536;
+/// Highest volume offered to the phone, 150%.
+pub const MAX_VOLUME: u32 = NORMAL_VOLUME * 3 / 2;
+
+/// Audio output as described in `kdeconnect.systemvolume` packets.
+#[derive(Debug, Clone, PartialEq, Eq, SimpleObject, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sink {
+    pub name: String,
+    pub description: String,
+    pub volume: u32,
+    pub max_volume: u32,
+    pub muted: bool,
+    /// Whether this is the default sink.
+    pub enabled: bool,
+}
+
+/// Lists and changes the local audio sinks.
+pub trait AudioBackend: Send + Sync {
+    fn sinks(&self) -> anyhow::Result<Vec<Sink>>;
+    fn set_volume(&self, sink: &str, volume: u32) -> anyhow::Result<()>;
+    fn set_muted(&self, sink: &str, muted: bool) -> anyhow::Result<()>;
+    /// Makes `sink` the default one.
+    fn set_default(&self, sink: &str) -> anyhow::Result<()>;
+}
+
+/// PulseAudio, or PipeWire through `pipewire-pulse`, driven by the `pactl` command.
+pub struct PactlBackend;
+
+impl PactlBackend {
+    /// Whether `pactl` answers with the JSON sink list, which needs pactl 16 or newer.
+    pub fn is_available() -> bool {
+        Self::run(&["--format=json", "list", "sinks"])
+            .is_ok_and(|output| serde_json::from_str::<Vec<Value>>(&output).is_ok())
+    }
+
+    fn run(args: &[&str]) -> anyhow::Result<String> {
+        let output = Command::new("pactl")
+            .args(args)
+            .stderr(Stdio::null())
+            .output()?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "pactl {} failed with {}",
+                args.join(" "),
+                output.status
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Parses `pactl --format=json list sinks`; channels are averaged into one volume.
+pub fn parse_pactl_sinks(json: &str, default_sink: &str) -> anyhow::Result<Vec<Sink>> {
+    let sinks = serde_json::from_str::<Vec<Value>>(json)?;
+    Ok(sinks
+        .iter()
+        .filter_map(|sink| {
+            let name = sink.get("name")?.as_str()?.to_string();
+            let channels = sink
+                .get("volume")
+                .and_then(Value::as_object)
+                .map(|volume| {
+                    volume
+                        .values()
+                        .filter_map(|channel| channel.get("value")?.as_u64())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            let volume = match channels.len() {
+                0 => 0,
+                count => (channels.iter().sum::<u64>() / count as u64) as u32,
+            };
+            Some(Sink {
+                description: sink
+                    .get("description")
+                    .and_then(Value::as_str)
+                    .unwrap_or(&name)
+                    .to_string(),
+                volume,
+                max_volume: MAX_VOLUME,
+                muted: sink.get("mute").and_then(Value::as_bool).unwrap_or(false),
+                enabled: name == default_sink,
+                name,
+            })
+        })
+        .collect())
+}
+
+impl AudioBackend for PactlBackend {
+    fn sinks(&self) -> anyhow::Result<Vec<Sink>> {
+        let default_sink = Self::run(&["get-default-sink"])?;
+        parse_pactl_sinks(
+            &Self::run(&["--format=json", "list", "sinks"])?,
+            default_sink.trim(),
+        )
+    }
+
+    fn set_volume(&self, sink: &str, volume: u32) -> anyhow::Result<()> {
+        Self::run(&["set-sink-volume", sink, &volume.min(MAX_VOLUME).to_string()])?;
+        Ok(())
+    }
+
+    fn set_muted(&self, sink: &str, muted: bool) -> anyhow::Result<()> {
+        Self::run(&["set-sink-mute", sink, if muted { "1" } else { "0" }])?;
+        Ok(())
+    }
+
+    fn set_default(&self, sink: &str) -> anyhow::Result<()> {
+        Self::run(&["set-default-sink", sink])?;
+        Ok(())
+    }
+}
+
+/// Keeps sinks in memory, for tests and machines without a supported sound server.
+#[derive(Default)]
+pub struct MockAudioBackend {
+    sinks: Mutex<Vec<Sink>>,
+}
+
+impl MockAudioBackend {
+    pub fn new(sinks: Vec<Sink>) -> Self {
+        Self {
+            sinks: Mutex::new(sinks),
+        }
+    }
+
+    fn update(&self, sink: &str, change: impl FnOnce(&mut Sink)) -> anyhow::Result<()> {
+        let mut sinks = self.sinks.lock().expect("sinks poisoned");
+        let sink = sinks
+            .iter_mut()
+            .find(|existing| existing.name == sink)
+            .ok_or(anyhow::anyhow!("No sink named {sink}"))?;
+        change(sink);
+        Ok(())
+    }
+}
+
+impl AudioBackend for MockAudioBackend {
+    fn sinks(&self) -> anyhow::Result<Vec<Sink>> {
+        Ok(self.sinks.lock().expect("sinks poisoned").clone())
+    }
+
+    fn set_volume(&self, sink: &str, volume: u32) -> anyhow::Result<()> {
+        self.update(sink, |sink| sink.volume = volume.min(sink.max_volume))
+    }
+
+    fn set_muted(&self, sink: &str, muted: bool) -> anyhow::Result<()> {
+        self.update(sink, |sink| sink.muted = muted)
+    }
+
+    fn set_default(&self, sink: &str) -> anyhow::Result<()> {
+        self.update(sink, |_| ())?;
+        for existing in self.sinks.lock().expect("sinks poisoned").iter_mut() {
+            existing.enabled = existing.name == sink;
+        }
+        Ok(())
+    }
+}
+
+/// `pactl` when a sound server answers, otherwise `None` and volume control stays off.
+pub fn detect_backend() -> Option<Arc<dyn AudioBackend>> {
+    if PactlBackend::is_available() {
+        return Some(Arc::new(PactlBackend));
+    }
+    debug!("No pactl found, system volume control disabled");
+    None
+}
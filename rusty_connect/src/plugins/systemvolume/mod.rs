@@ -0,0 +1,337 @@
+use std::{
+    sync::{Arc, RwLock as StdRwLock},
+    time::Duration,
+};
+
+use async_graphql::{InputObject, Object, SimpleObject};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::{
+    devices::{DeviceManager, DeviceWithState, Peer},
+    utils::get_timestamp,
+};
+
+pub use self::backend::{
+    detect_backend, parse_pactl_sinks, AudioBackend, MockAudioBackend, PactlBackend, Sink,
+};
+
+use super::{Plugin, PluginAction, PluginExt, Reply};
+
+pub mod backend;
+
+/// How often local sinks are checked for changes to push to devices.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone)]
+pub struct SystemVolume {
+    backend: Arc<StdRwLock<Option<Arc<dyn AudioBackend>>>>,
+}
+
+#[Object]
+impl SystemVolume {
+    /// Local sinks offered to devices, empty without an audio backend.
+    pub async fn audio_sinks(&self) -> Vec<Sink> {
+        self.sinks().await.unwrap_or_default()
+    }
+}
+
+impl SystemVolume {
+    /// Volume control through `backend`, `None` keeps it off.
+    pub fn new(backend: Option<Arc<dyn AudioBackend>>) -> Self {
+        Self {
+            backend: Arc::new(StdRwLock::new(backend)),
+        }
+    }
+
+    /// Replaces the audio backend, `None` turns volume control off.
+    pub fn set_backend(&self, backend: Option<Arc<dyn AudioBackend>>) {
+        *self.backend.write().expect("audio backend poisoned") = backend;
+    }
+
+    fn backend(&self) -> Option<Arc<dyn AudioBackend>> {
+        self.backend.read().expect("audio backend poisoned").clone()
+    }
+
+    /// `None` without a backend or when the sinks cannot be read.
+    async fn sinks(&self) -> Option<Vec<Sink>> {
+        let backend = self.backend()?;
+        match tokio::task::spawn_blocking(move || backend.sinks()).await {
+            Ok(Ok(sinks)) => Some(sinks),
+            Ok(Err(err)) => {
+                warn!("Cannot list audio sinks {err:?}");
+                None
+            }
+            Err(err) => {
+                warn!("Audio task failed {err:?}");
+                None
+            }
+        }
+    }
+
+    /// Watches the local sinks and pushes changes to paired devices.
+    pub fn start_watching(&self, device_manager: Arc<RwLock<DeviceManager>>) {
+        let plugin = self.clone();
+        tokio::spawn(async move {
+            let mut previous = plugin.sinks().await.unwrap_or_default();
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(current) = plugin.sinks().await else {
+                    continue;
+                };
+                for payload in sink_updates(&previous, &current) {
+                    if let Err(err) = plugin
+                        .send_payload_with(
+                            &device_manager,
+                            None,
+                            "kdeconnect.systemvolume",
+                            payload,
+                        )
+                        .await
+                    {
+                        warn!("Cannot push volume change {err:?}");
+                    }
+                }
+                previous = current;
+            }
+        });
+    }
+}
+
+/// Whether local sinks go out to the device.
+fn sends(config: &Option<SystemVolumeConfig>) -> bool {
+    config.as_ref().is_none_or(|config| config.send_enabled)
+}
+
+/// Packets telling a device how the sinks changed from `previous` to `current`.
+///
+/// Added, removed or renamed sinks resend the whole list, other changes go out per sink.
+pub fn sink_updates(previous: &[Sink], current: &[Sink]) -> Vec<SystemVolumePayload> {
+    let same_sinks = previous.len() == current.len()
+        && previous.iter().zip(current).all(|(previous, current)| {
+            previous.name == current.name && previous.description == current.description
+        });
+    if !same_sinks {
+        return vec![SystemVolumePayload::with_sinks(current.to_vec())];
+    }
+    previous
+        .iter()
+        .zip(current)
+        .filter(|(previous, current)| previous != current)
+        .map(|(previous, current)| SystemVolumePayload {
+            name: Some(current.name.clone()),
+            volume: (previous.volume != current.volume).then_some(current.volume),
+            muted: (previous.muted != current.muted).then_some(current.muted),
+            enabled: (previous.enabled != current.enabled).then_some(current.enabled),
+            ..Default::default()
+        })
+        .collect()
+}
+
+impl Plugin for SystemVolume {
+    type PluginPayload = SystemVolumePayload;
+    type PluginConfig = SystemVolumeConfig;
+    type PluginState = SystemVolumeState;
+
+    fn init(_device_mangager: &crate::devices::DeviceManager) -> Self {
+        Self::new(detect_backend())
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec!["kdeconnect.systemvolume.request".to_string()]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec!["kdeconnect.systemvolume".to_string()]
+    }
+
+    async fn reply(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        config: &Option<Self::PluginConfig>,
+    ) -> anyhow::Result<Option<Reply>> {
+        let requests_sinks = payload.body.get("requestSinks") == Some(&serde_json::json!(true));
+        if payload.r#type != "kdeconnect.systemvolume.request"
+            || !requests_sinks
+            || !peer.paired
+            || !sends(config)
+        {
+            return Ok(None);
+        }
+        let Some(sinks) = self.sinks().await else {
+            return Ok(None);
+        };
+        Ok(Some(Reply::new(
+            "kdeconnect.systemvolume",
+            SystemVolumePayload::with_sinks(sinks),
+        )?))
+    }
+
+    async fn parse_payload(
+        &self,
+        payload: &crate::payloads::Payload,
+        peer: &Peer,
+        _config: &Option<Self::PluginConfig>,
+    ) -> Option<Self::PluginPayload> {
+        if payload.r#type == "kdeconnect.systemvolume.request" {
+            let payload = serde_json::from_value::<Self::PluginPayload>(payload.body.clone());
+            if let Ok(mut payload) = payload {
+                if peer.paired {
+                    payload.apply = true;
+                } else {
+                    warn!("Ignoring volume change from unpaired device");
+                }
+                return Some(payload);
+            }
+        }
+        None
+    }
+
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        if payload.apply && payload.name.is_some() {
+            state.last_change_at = Some(get_timestamp() as u64);
+        }
+    }
+
+    fn connected(
+        &self,
+        device: &DeviceWithState,
+        config: &Option<Self::PluginConfig>,
+    ) -> Option<PluginAction> {
+        if !device.device.paired
+            || !sends(config)
+            || !device.capabilities.can_send("kdeconnect.systemvolume")
+        {
+            return None;
+        }
+        let peer = device.peer()?;
+        let plugin = self.clone();
+        Some(PluginAction::Async(Box::pin(async move {
+            let Some(sinks) = plugin.sinks().await else {
+                return;
+            };
+            if let Err(err) = peer
+                .send(
+                    "kdeconnect.systemvolume",
+                    SystemVolumePayload::with_sinks(sinks),
+                )
+                .await
+            {
+                warn!("Cannot send audio sinks {err:?}");
+            }
+        })))
+    }
+
+    /// Applies the requested change to a known sink; the watcher pushes the result back to devices.
+    fn handle(
+        &self,
+        payload: &Self::PluginPayload,
+        _previous_state: &Self::PluginState,
+    ) -> Option<PluginAction> {
+        let name = payload.name.clone().filter(|_| payload.apply)?;
+        let backend = self.backend()?;
+        let (volume, muted, enabled) = (payload.volume, payload.muted, payload.enabled);
+        Some(PluginAction::Blocking(Box::new(move || {
+            match backend.sinks() {
+                Ok(sinks) if sinks.iter().any(|sink| sink.name == name) => {}
+                Ok(_) => {
+                    warn!("Ignoring change to unknown sink {name:?}");
+                    return;
+                }
+                Err(err) => {
+                    warn!("Cannot list audio sinks {err:?}");
+                    return;
+                }
+            }
+            let mut results = vec![];
+            if let Some(volume) = volume {
+                results.push(backend.set_volume(&name, volume));
+            }
+            if let Some(muted) = muted {
+                results.push(backend.set_muted(&name, muted));
+            }
+            if enabled == Some(true) {
+                results.push(backend.set_default(&name));
+            }
+            for err in results.into_iter().filter_map(Result::err) {
+                warn!("Cannot change sink {name} {err:?}");
+            }
+        })))
+    }
+
+    fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+
+    fn should_send(
+        &self,
+        config: &Option<Self::PluginConfig>,
+        _state: &mut Self::PluginState,
+        _payload: &Self::PluginPayload,
+    ) -> bool {
+        sends(config)
+    }
+}
+
+//https://github.com/KDE/kdeconnect-kde/tree/master/plugins/systemvolume
+/// Sink list or single sink change when sent, request from the phone when received.
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemVolumePayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sink_list: Option<Vec<Sink>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_sinks: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    volume: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    muted: Option<bool>,
+    /// Makes the sink the default one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    enabled: Option<bool>,
+
+    /// Whether the change came from a paired device and is applied.
+    #[serde(skip)]
+    #[graphql(skip)]
+    apply: bool,
+}
+
+impl SystemVolumePayload {
+    fn with_sinks(sinks: Vec<Sink>) -> Self {
+        Self {
+            sink_list: Some(sinks),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "SystemVolumeConfigInput")]
+pub struct SystemVolumeConfig {
+    enabled: bool,
+    send_enabled: bool,
+}
+
+impl Default for SystemVolumeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            send_enabled: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, SimpleObject)]
+pub struct SystemVolumeState {
+    /// When the device last changed a sink, in milliseconds since epoch.
+    last_change_at: Option<u64>,
+}
@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use async_graphql::{Context, Enum, InputObject, Object, SimpleObject};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::{
+    devices::{DeviceManager, Peer},
+    utils::get_timestamp,
+};
+
+use super::{Plugin, PluginExt};
+
+/// Missed calls kept per device, older ones are dropped.
+const MISSED_CALLS_LIMIT: usize = 50;
+
+#[derive(Default)]
+pub struct Telephony;
+
+#[Object]
+impl Telephony {
+    /// Silences the ringtone of an incoming call on the peer.
+    pub async fn mute_ringer<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<&str> {
+        self.send_payload(
+            context,
+            Some(&device_id),
+            "kdeconnect.telephony.request_mute",
+            TelephonyPayload::default(),
+        )
+        .await?;
+        Ok("success")
+    }
+
+    pub async fn clear_missed_calls<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<&str> {
+        let mut device_manager = context
+            .data::<Arc<RwLock<DeviceManager>>>()
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?
+            .write()
+            .await;
+        let device = device_manager
+            .devices
+            .get_mut(&device_id)
+            .ok_or(anyhow::anyhow!("Device not found with given id"))?;
+        Self::get_state_from_plugin_states(&mut device.device.plugin_states)
+            .missed_calls
+            .clear();
+        Ok("success")
+    }
+}
+
+impl Plugin for Telephony {
+    type PluginPayload = TelephonyPayload;
+    type PluginConfig = TelephonyConfig;
+    type PluginState = TelephonyState;
+
+    fn init(_device_mangager: &crate::devices::DeviceManager) -> Self {
+        Self
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec!["kdeconnect.telephony".to_string()]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec!["kdeconnect.telephony.request_mute".to_string()]
+    }
+
+    async fn parse_payload(
+        &self,
+        payload: &crate::payloads::Payload,
+        _peer: &Peer,
+        _config: &Option<Self::PluginConfig>,
+    ) -> Option<Self::PluginPayload> {
+        if payload.r#type == "kdeconnect.telephony" {
+            match serde_json::from_value::<Self::PluginPayload>(payload.body.clone()) {
+                Ok(payload) => return Some(payload),
+                Err(err) => warn!("Cant parse telephony payload {err:#?}"),
+            }
+        }
+        None
+    }
+
+    fn update_state(&self, payload: &Self::PluginPayload, state: &mut Self::PluginState) {
+        let Some(event) = payload.event else {
+            return;
+        };
+        let call = CallInfo {
+            contact_name: payload.contact_name.clone(),
+            phone_number: payload.phone_number.clone(),
+            at: get_timestamp() as u64,
+        };
+        match event {
+            _ if payload.is_cancel == Some(true) => {
+                state.call_state = CallState::Idle;
+                state.active_call = None;
+            }
+            TelephonyEvent::Ringing => {
+                state.call_state = CallState::Ringing;
+                state.active_call = Some(call);
+            }
+            TelephonyEvent::Talking => {
+                state.call_state = CallState::Talking;
+                state.active_call = Some(call);
+            }
+            TelephonyEvent::MissedCall => {
+                state.call_state = CallState::Idle;
+                state.active_call = None;
+                state.missed_calls.insert(0, call);
+                state.missed_calls.truncate(MISSED_CALLS_LIMIT);
+            }
+            TelephonyEvent::Unknown => {}
+        }
+    }
+
+    fn is_enabled(&self, config: &Option<Self::PluginConfig>) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+
+    fn should_send(
+        &self,
+        config: &Option<Self::PluginConfig>,
+        _state: &mut Self::PluginState,
+        _payload: &Self::PluginPayload,
+    ) -> bool {
+        if let Some(config) = config {
+            config.enabled
+        } else {
+            true
+        }
+    }
+}
+
+//https://github.com/KDE/kdeconnect-kde/tree/master/plugins/telephony
+#[derive(SimpleObject, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TelephonyPayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    event: Option<TelephonyEvent>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    contact_name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    phone_number: Option<String>,
+
+    /// Base64 encoded contact picture.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    phone_thumbnail: Option<String>,
+
+    /// Set when a previously reported call is over.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    is_cancel: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TelephonyEvent {
+    Ringing,
+    Talking,
+    MissedCall,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Enum)]
+pub enum CallState {
+    #[default]
+    Idle,
+    Ringing,
+    Talking,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct CallInfo {
+    pub contact_name: Option<String>,
+    pub phone_number: Option<String>,
+    /// When the event was received, in milliseconds since epoch.
+    pub at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SimpleObject, InputObject)]
+#[graphql(input_name = "TelephonyConfigInput")]
+pub struct TelephonyConfig {
+    enabled: bool,
+}
+
+impl Default for TelephonyConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Default, Clone, SimpleObject)]
+pub struct TelephonyState {
+    /// Media players can be paused while this is `Talking`.
+    pub call_state: CallState,
+    pub active_call: Option<CallInfo>,
+    /// Latest missed calls, newest first.
+    pub missed_calls: Vec<CallInfo>,
+}
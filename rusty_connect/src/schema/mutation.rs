@@ -4,9 +4,9 @@ use async_graphql::Object;
 use tokio::{net::UdpSocket, sync::RwLock};
 
 use crate::{
-    devices::{DeviceManager, DeviceWithState},
+    devices::{profiles::PluginDefaults, DeviceManager, DeviceWithState},
     payloads::{IdentityPayloadBody, Payload},
-    plugins::PluginManager,
+    plugins::{PluginConfigs, PluginManager},
 };
 
 pub struct Mutation {
@@ -36,9 +36,92 @@ impl Mutation {
         Ok(identity)
     }
 
-    pub async fn pair(&self, id: String, pair: bool) -> anyhow::Result<DeviceWithState> {
+    /// Pairing without a `profile` applies the pairing profile to devices that have none.
+    pub async fn pair(
+        &self,
+        id: String,
+        pair: bool,
+        profile: Option<String>,
+    ) -> anyhow::Result<DeviceWithState> {
         let mut manager = self.device_manager.write().await;
-        let device = manager.pair(&id, pair).await?;
+        let device = manager.pair(&id, pair, profile).await?;
         Ok(device)
     }
+
+    pub async fn set_device_profile(
+        &self,
+        id: String,
+        profile: Option<String>,
+    ) -> anyhow::Result<DeviceWithState> {
+        let mut manager = self.device_manager.write().await;
+        manager.set_profile(&id, profile).await
+    }
+
+    /// Replaces the global configs of the plugins set in `configs`.
+    pub async fn update_global_defaults(
+        &self,
+        configs: PluginConfigs,
+    ) -> anyhow::Result<PluginDefaults> {
+        self.plugin_manager.validate_configs(&configs)?;
+        let mut manager = self.device_manager.write().await;
+        manager
+            .update_defaults(|defaults| {
+                defaults.global = configs.or(&defaults.global);
+                Ok(configs.keys())
+            })
+            .await
+    }
+
+    /// Creates or replaces a profile.
+    pub async fn set_profile(
+        &self,
+        name: String,
+        configs: PluginConfigs,
+    ) -> anyhow::Result<PluginDefaults> {
+        if name.trim().is_empty() {
+            return Err(anyhow::anyhow!("Profile name cannot be empty"));
+        }
+        self.plugin_manager.validate_configs(&configs)?;
+        let mut manager = self.device_manager.write().await;
+        manager
+            .update_defaults(|defaults| {
+                let previous = defaults.profiles.insert(name, configs.clone());
+                let mut changed = configs.keys();
+                changed.extend(previous.map(|previous| previous.keys()).unwrap_or_default());
+                changed.sort();
+                changed.dedup();
+                Ok(changed)
+            })
+            .await
+    }
+
+    /// Devices using a removed profile fall back to the global defaults.
+    pub async fn remove_profile(&self, name: String) -> anyhow::Result<PluginDefaults> {
+        let mut manager = self.device_manager.write().await;
+        manager
+            .update_defaults(|defaults| {
+                let removed = defaults
+                    .profiles
+                    .remove(&name)
+                    .ok_or(anyhow::anyhow!("No profile named {name:?}"))?;
+                if defaults.pairing_profile.as_ref() == Some(&name) {
+                    defaults.pairing_profile = None;
+                }
+                Ok(removed.keys())
+            })
+            .await
+    }
+
+    pub async fn set_pairing_profile(
+        &self,
+        profile: Option<String>,
+    ) -> anyhow::Result<PluginDefaults> {
+        let mut manager = self.device_manager.write().await;
+        manager
+            .update_defaults(|defaults| {
+                defaults.pairing_profile = profile;
+                Ok(vec![])
+            })
+            .await
+    }
 }
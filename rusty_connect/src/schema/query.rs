@@ -1,11 +1,23 @@
 use std::sync::Arc;
 
-use async_graphql::Object;
+use async_graphql::{Context, Object};
 use tokio::sync::RwLock;
 
-use crate::devices::{DeviceManager, DeviceWithState};
+use crate::{
+    devices::{profiles::PluginDefaults, DeviceManager, DeviceWithState},
+    plugins::{
+        battery::{BatteryEstimate, BatterySample},
+        clipboard::ClipboardEntry,
+        contacts::Contact,
+        ping::PingLatency,
+        sms::{Conversation, SmsMessage},
+        systemvolume::Sink,
+        PluginManager,
+    },
+};
 
 pub struct Query {
+    pub plugin_manager: Arc<PluginManager>,
     pub device_manager: Arc<RwLock<DeviceManager>>,
 }
 
@@ -15,6 +27,11 @@ impl Query {
         "cowboy!"
     }
 
+    /// Read-only plugin data; plugin operations are on the mutation root.
+    pub async fn plugins(&self) -> PluginQuery {
+        PluginQuery(self.plugin_manager.clone())
+    }
+
     pub async fn devices(&self) -> Vec<DeviceWithState> {
         let devices = {
             let manager = self.device_manager.read().await;
@@ -30,4 +47,115 @@ impl Query {
         };
         device.ok_or(anyhow::anyhow!("Not device with givenId"))
     }
+
+    pub async fn plugin_defaults(&self) -> PluginDefaults {
+        self.device_manager.read().await.defaults.clone()
+    }
+}
+
+/// Plugin fields without side effects.
+pub struct PluginQuery(Arc<PluginManager>);
+
+#[Object]
+impl PluginQuery {
+    /// Local sinks offered to devices, empty without an audio backend.
+    pub async fn audio_sinks<'ctx>(&self, context: &Context<'ctx>) -> Vec<Sink> {
+        self.0
+            .systemvolume
+            .audio_sinks(context)
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Charge samples received from the device, newest first.
+    pub async fn battery_history<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<BatterySample>> {
+        self.0
+            .batttery
+            .battery_history(context, device_id, limit)
+            .await
+    }
+
+    pub async fn battery_estimate<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<Option<BatteryEstimate>> {
+        self.0.batttery.battery_estimate(context, device_id).await
+    }
+
+    /// Contents sent to and received from the device, newest first.
+    pub async fn clipboard_history<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<ClipboardEntry>> {
+        self.0
+            .clipboard
+            .clipboard_history(context, device_id, limit)
+            .await
+    }
+
+    pub async fn contacts<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        query: Option<String>,
+    ) -> anyhow::Result<Vec<Contact>> {
+        self.0.contacts.contacts(context, device_id, query).await
+    }
+
+    /// Players available on this machine.
+    pub async fn local_players<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+    ) -> anyhow::Result<Vec<String>> {
+        self.0.mpris.local_players(context).await
+    }
+
+    /// Round trip stats over the last probes sent to the device.
+    pub async fn ping_latency<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<Option<PingLatency>> {
+        self.0.ping.ping_latency(context, device_id).await
+    }
+
+    /// Cached conversations, newest first.
+    pub async fn conversations<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+    ) -> anyhow::Result<Vec<Conversation>> {
+        self.0.sms.conversations(context, device_id).await
+    }
+
+    /// Cached messages of a thread, oldest first.
+    pub async fn messages<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        thread_id: i64,
+    ) -> anyhow::Result<Vec<SmsMessage>> {
+        self.0.sms.messages(context, device_id, thread_id).await
+    }
+
+    /// Local path of a downloaded attachment, if it was received.
+    pub async fn attachment_path<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        device_id: String,
+        unique_identifier: String,
+    ) -> anyhow::Result<Option<String>> {
+        self.0
+            .sms
+            .attachment_path(context, device_id, unique_identifier)
+            .await
+    }
 }
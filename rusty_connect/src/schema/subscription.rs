@@ -2,13 +2,16 @@ use std::sync::Arc;
 
 use async_graphql::{SimpleObject, Subscription};
 use async_stream::stream;
-use futures::{Stream, StreamExt};
-use tokio::sync::RwLock;
+use futures::Stream;
+use tokio::sync::{broadcast, RwLock};
 use tracing::debug;
 
 use crate::{
-    devices::DeviceManager,
-    plugins::{share::DownloadProgress, Connected, Disconnected, PluginManager, ReceivedPayload},
+    devices::{ConfigChanged, DeviceManager},
+    plugins::{
+        battery::BatteryLow, connectivity_report::ConnectivityChanged, presenter::PointerState,
+        runcommand::CommandOutput, share::DownloadProgress, PluginManager, ReceivedPayload,
+    },
 };
 
 pub struct Subscription {
@@ -60,6 +63,77 @@ impl Subscription {
 
         Ok(stream)
     }
+
+    /// Plugin config changes, optionally limited to one device.
+    async fn plugin_config_changed(
+        &self,
+        device_id: Option<String>,
+    ) -> impl Stream<Item = ConfigChanged> {
+        let receiver = self.device_manager.read().await.subscribe_config_changed();
+        broadcast_stream(receiver, move |changed| {
+            device_id.as_ref().is_none_or(|id| id == &changed.device_id)
+        })
+    }
+
+    /// Presenter pointer changes, optionally limited to one device.
+    async fn presenter_pointer(
+        &self,
+        device_id: Option<String>,
+    ) -> impl Stream<Item = PointerState> {
+        let receiver = self.plugin_manager.presenter.subscribe_pointer();
+        broadcast_stream(receiver, move |pointer| {
+            device_id.as_ref().is_none_or(|id| id == &pointer.device_id)
+        })
+    }
+
+    /// Output of commands run on request of a device, optionally limited to one device.
+    async fn command_output(&self, device_id: Option<String>) -> impl Stream<Item = CommandOutput> {
+        let receiver = self.plugin_manager.runcommand.subscribe_output();
+        broadcast_stream(receiver, move |output| {
+            device_id.as_ref().is_none_or(|id| id == &output.device_id)
+        })
+    }
+
+    /// Low battery events, optionally limited to one device.
+    async fn battery_low(&self, device_id: Option<String>) -> impl Stream<Item = BatteryLow> {
+        let receiver = self.plugin_manager.batttery.subscribe_low();
+        broadcast_stream(receiver, move |low| {
+            device_id.as_ref().is_none_or(|id| id == &low.device_id)
+        })
+    }
+
+    /// Connectivity report changes, optionally limited to one device.
+    async fn connectivity_changed(
+        &self,
+        device_id: Option<String>,
+    ) -> impl Stream<Item = ConnectivityChanged> {
+        let receiver = self.plugin_manager.connectivityreport.subscribe_changes();
+        broadcast_stream(receiver, move |changed| {
+            device_id.as_ref().is_none_or(|id| id == &changed.device_id)
+        })
+    }
+}
+
+/// Yields the events of `receiver` accepted by `filter`, skipping those lost while lagging.
+fn broadcast_stream<T: Clone + Send + 'static>(
+    mut receiver: broadcast::Receiver<T>,
+    filter: impl Fn(&T) -> bool + Send + 'static,
+) -> impl Stream<Item = T> {
+    stream! {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    if filter(&event) {
+                        yield event;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    debug!("Skipped {skipped} events");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
 }
 
 #[derive(SimpleObject)]
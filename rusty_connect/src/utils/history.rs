@@ -0,0 +1,48 @@
+use std::{marker::PhantomData, path::PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::is_safe_file_name;
+
+/// Per-device `<device>.json` files, each holding a list of `T`.
+pub struct HistoryStore<T> {
+    path: PathBuf,
+    entries: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> HistoryStore<T> {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            entries: PhantomData,
+        }
+    }
+
+    fn device_file(&self, device_id: &str) -> anyhow::Result<PathBuf> {
+        if !is_safe_file_name(device_id) {
+            return Err(anyhow::anyhow!("Invalid device id {device_id:?}"));
+        }
+        Ok(self.path.join(format!("{device_id}.json")))
+    }
+
+    /// Blocking, only meant for startup.
+    pub fn load(&self, device_id: &str) -> anyhow::Result<Vec<T>> {
+        match std::fs::read(self.device_file(device_id)?) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub async fn save(&self, device_id: &str, history: &[T]) -> anyhow::Result<()> {
+        tokio::fs::write(self.device_file(device_id)?, serde_json::to_vec(history)?).await?;
+        Ok(())
+    }
+
+    pub async fn remove(&self, device_id: &str) -> anyhow::Result<()> {
+        match tokio::fs::remove_file(self.device_file(device_id)?).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
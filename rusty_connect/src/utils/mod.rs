@@ -1,7 +1,20 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
+pub mod history;
+
+/// Events buffered for slow subscribers of a broadcast channel before older ones are dropped.
+pub const BROADCAST_CHANNEL_SIZE: usize = 16;
+
 pub fn get_timestamp() -> u128 {
     let start = SystemTime::now();
     let since_epoch = start.duration_since(UNIX_EPOCH).expect("???");
     since_epoch.as_millis()
 }
+
+/// Whether `name` can be used as a single path component without escaping its folder.
+pub fn is_safe_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
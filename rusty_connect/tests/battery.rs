@@ -0,0 +1,319 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rusty_connect::{
+    payloads::Payload,
+    plugins::{
+        battery::{
+            estimate, history::record_sample, BatteryConfig, BatteryLow, BatterySample,
+            BatteryState, Batttery, LowBatteryReason, MemoryPowerSource, PowerSource, PowerStatus,
+            SysfsPowerSource,
+        },
+        Plugin,
+    },
+};
+use serde_json::json;
+use tokio::sync::broadcast;
+
+use self::common::TempDir;
+
+mod common;
+
+const MINUTE: u64 = 60_000;
+
+fn sample(minute: u64, current_charge: f32, is_charging: bool) -> BatterySample {
+    BatterySample {
+        timestamp: minute * MINUTE,
+        current_charge,
+        is_charging,
+    }
+}
+
+fn history(samples: &[BatterySample]) -> Vec<BatterySample> {
+    let mut history = Vec::new();
+    for sample in samples {
+        record_sample(&mut history, sample.clone(), 10);
+    }
+    history
+}
+
+#[test]
+fn discharging_estimates_time_to_empty() {
+    let history = history(&[sample(0, 60.0, false), sample(30, 50.0, false)]);
+    let estimate = estimate(&history).expect("estimate");
+    assert!(!estimate.is_charging);
+    assert_eq!(estimate.seconds_to_full, None);
+    assert_eq!(estimate.seconds_to_empty, Some(150 * 60));
+}
+
+#[test]
+fn estimate_only_uses_samples_since_plug_change() {
+    let history = history(&[
+        sample(0, 80.0, false),
+        sample(10, 40.0, true),
+        sample(40, 70.0, true),
+    ]);
+    let estimate = estimate(&history).expect("estimate");
+    assert!(estimate.is_charging);
+    assert_eq!(estimate.seconds_to_full, Some(30 * 60));
+}
+
+#[test]
+fn no_estimate_without_progress() {
+    assert_eq!(estimate(&history(&[sample(0, 50.0, true)])), None);
+    let history = history(&[sample(0, 50.0, true), sample(10, 50.0, true)]);
+    assert_eq!(estimate(&history), None);
+}
+
+#[test]
+fn history_is_bounded() {
+    let mut history = Vec::new();
+    for minute in 0..5 {
+        record_sample(&mut history, sample(minute, 50.0, false), 3);
+    }
+    let minutes: Vec<_> = history.iter().map(|s| s.timestamp / MINUTE).collect();
+    assert_eq!(minutes, [4, 3, 2]);
+}
+
+/// Fake `/sys/class/power_supply` with one folder per `(name, [(attribute, value)])`.
+fn fake_sysfs(supplies: &[(&str, &[(&str, &str)])]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("power_supply_{}", uuid::Uuid::new_v4()));
+    for (name, attributes) in supplies {
+        let supply = root.join(name);
+        std::fs::create_dir_all(&supply).unwrap();
+        for (attribute, value) in *attributes {
+            std::fs::write(supply.join(attribute), format!("{value}\n")).unwrap();
+        }
+    }
+    root
+}
+
+fn read(root: &Path) -> Option<PowerStatus> {
+    let status = SysfsPowerSource::new(root).status().unwrap();
+    std::fs::remove_dir_all(root).unwrap();
+    status
+}
+
+#[test]
+fn sysfs_averages_system_batteries_and_ignores_peripherals() {
+    let root = fake_sysfs(&[
+        (
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("capacity", "40"),
+                ("status", "Discharging"),
+            ],
+        ),
+        (
+            "BAT1",
+            &[
+                ("type", "Battery"),
+                ("capacity", "60"),
+                ("status", "Discharging"),
+            ],
+        ),
+        (
+            "hidpp_battery_0",
+            &[("type", "Battery"), ("scope", "Device"), ("capacity", "5")],
+        ),
+        ("AC", &[("type", "Mains"), ("online", "0")]),
+    ]);
+    let status = read(&root).expect("battery found");
+    assert_eq!(status.current_charge, 50.0);
+    assert!(!status.is_charging);
+}
+
+#[test]
+fn sysfs_charging_follows_online_supply() {
+    let root = fake_sysfs(&[
+        (
+            "BAT0",
+            &[("type", "Battery"), ("capacity", "100"), ("status", "Full")],
+        ),
+        ("AC", &[("type", "Mains"), ("online", "1")]),
+    ]);
+    assert!(read(&root).expect("battery found").is_charging);
+}
+
+#[test]
+fn sysfs_without_battery_has_no_status() {
+    let root = fake_sysfs(&[("AC", &[("type", "Mains"), ("online", "1")])]);
+    assert_eq!(read(&root), None);
+}
+
+/// Battery plugin storing its history in `dir`.
+async fn plugin(dir: &TempDir) -> Batttery {
+    Batttery::init(&common::device_manager(dir).await)
+}
+
+fn config(low_threshold: u32) -> Option<BatteryConfig> {
+    serde_json::from_value(json!({"low_threshold": low_threshold})).ok()
+}
+
+/// Feeds `kdeconnect.battery` packets through the plugin like the payload loop does.
+struct Device {
+    plugin: Batttery,
+    config: Option<BatteryConfig>,
+    state: BatteryState,
+}
+
+impl Device {
+    async fn receive(&mut self, current_charge: f32, is_charging: bool, low: bool) -> bool {
+        let (peer, _) = common::peer(true);
+        let packet = Payload::generate_new(
+            "kdeconnect.battery",
+            json!({
+                "currentCharge": current_charge,
+                "isCharging": is_charging,
+                "thresholdEvent": low as u8,
+            }),
+        );
+        let parsed = self
+            .plugin
+            .parse_payload(&packet, &peer, &self.config)
+            .await
+            .expect("parsed");
+        let previous = self.state.clone();
+        self.plugin.update_state(&parsed, &mut self.state);
+        self.plugin.handle(&parsed, &previous).is_some()
+    }
+}
+
+fn low_events(receiver: &mut broadcast::Receiver<BatteryLow>) -> Vec<(f32, LowBatteryReason)> {
+    std::iter::from_fn(|| receiver.try_recv().ok())
+        .map(|low| (low.current_charge, low.reason))
+        .collect()
+}
+
+#[tokio::test]
+async fn device_flag_fires_once_per_low_spell() {
+    let dir = TempDir::new("battery");
+    let plugin = plugin(&dir).await;
+    let mut lows = plugin.subscribe_low();
+    let mut device = Device {
+        plugin,
+        config: config(0),
+        state: BatteryState::default(),
+    };
+    device.receive(30.0, false, true).await;
+    device.receive(29.0, false, true).await;
+    device.receive(40.0, true, false).await;
+    device.receive(28.0, false, true).await;
+    assert_eq!(
+        low_events(&mut lows),
+        vec![
+            (30.0, LowBatteryReason::Device),
+            (28.0, LowBatteryReason::Device)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn threshold_fires_when_crossed_while_discharging() {
+    let dir = TempDir::new("battery");
+    let plugin = plugin(&dir).await;
+    let mut lows = plugin.subscribe_low();
+    let mut device = Device {
+        plugin,
+        config: config(20),
+        state: BatteryState::default(),
+    };
+    for (charge, is_charging) in [
+        (30.0, false),
+        (20.0, false),
+        (15.0, false),
+        (16.0, true),
+        (14.0, false),
+    ] {
+        device.receive(charge, is_charging, false).await;
+    }
+    assert_eq!(
+        low_events(&mut lows),
+        vec![
+            (20.0, LowBatteryReason::Threshold),
+            (14.0, LowBatteryReason::Threshold)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn history_is_written_at_most_once_per_interval() {
+    let dir = TempDir::new("battery");
+    let mut device = Device {
+        plugin: plugin(&dir).await,
+        config: config(15),
+        state: BatteryState::default(),
+    };
+    assert!(device.receive(50.0, false, false).await);
+    assert!(!device.receive(49.0, false, false).await);
+    assert!(!device.receive(48.0, false, false).await);
+}
+
+/// Plugin reporting a battery at `current_charge` and discharging.
+async fn reporting(dir: &TempDir, current_charge: f32) -> Batttery {
+    let plugin = plugin(dir).await;
+    let source = MemoryPowerSource::default();
+    source.set(Some(PowerStatus {
+        current_charge,
+        is_charging: false,
+    }));
+    plugin.set_power_source(Some(Arc::new(source)));
+    plugin
+}
+
+fn battery_request() -> Payload {
+    Payload::generate_new("kdeconnect.battery.request", json!({"request": true}))
+}
+
+#[tokio::test]
+async fn battery_requests_are_answered_with_the_local_status() {
+    let dir = TempDir::new("battery");
+    let plugin = reporting(&dir, 12.0).await;
+    let (peer, _) = common::peer(true);
+    let reply = plugin
+        .reply(&battery_request(), &peer, &None)
+        .await
+        .expect("reply")
+        .expect("answered");
+    assert_eq!(reply.payload_type, "kdeconnect.battery");
+    assert_eq!(
+        reply.body,
+        json!({"currentCharge": 12.0, "isCharging": false, "thresholdEvent": 1})
+    );
+}
+
+#[tokio::test]
+async fn battery_requests_are_not_answered_unpaired_or_with_sending_off() {
+    let dir = TempDir::new("battery");
+    let plugin = reporting(&dir, 50.0).await;
+    let (unpaired, _) = common::peer(false);
+    assert!(plugin
+        .reply(&battery_request(), &unpaired, &None)
+        .await
+        .expect("reply")
+        .is_none());
+
+    let (paired, _) = common::peer(true);
+    let sending_off = serde_json::from_value(json!({"send_enabled": false})).ok();
+    assert!(plugin
+        .reply(&battery_request(), &paired, &sending_off)
+        .await
+        .expect("reply")
+        .is_none());
+}
+
+#[tokio::test]
+async fn battery_requests_are_not_answered_without_a_battery() {
+    let dir = TempDir::new("battery");
+    let plugin = plugin(&dir).await;
+    plugin.set_power_source(Some(Arc::new(MemoryPowerSource::default())));
+    let (peer, _) = common::peer(true);
+    assert!(plugin
+        .reply(&battery_request(), &peer, &None)
+        .await
+        .expect("reply")
+        .is_none());
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use rusty_connect::plugins::clipboard::{
+    history::{compile_patterns, is_sensitive, record},
+    ClipboardBackend, ClipboardEntry, ClipboardSource, ClipboardSync, MemoryBackend,
+};
+
+fn sync_with(content: Option<&str>) -> (Arc<MemoryBackend>, ClipboardSync) {
+    let backend = Arc::new(MemoryBackend::default());
+    if let Some(content) = content {
+        backend.set(content).unwrap();
+    }
+    let sync = ClipboardSync::new(backend.clone());
+    sync.prime().unwrap();
+    (backend, sync)
+}
+
+#[test]
+fn content_present_at_start_is_not_sent() {
+    let (_, sync) = sync_with(Some("before start"));
+    assert_eq!(sync.poll_local().unwrap(), None);
+}
+
+#[test]
+fn local_changes_are_reported_once() {
+    let (backend, sync) = sync_with(None);
+    backend.set("copied").unwrap();
+    let (content, timestamp) = sync.poll_local().unwrap().expect("change reported");
+    assert_eq!(content, "copied");
+    assert!(timestamp > 0);
+    assert_eq!(sync.poll_local().unwrap(), None);
+}
+
+#[test]
+fn remote_content_is_not_echoed() {
+    let (backend, sync) = sync_with(Some("local"));
+    assert!(sync.apply_remote("from phone", None).unwrap());
+    assert_eq!(backend.get().unwrap().as_deref(), Some("from phone"));
+    assert_eq!(sync.poll_local().unwrap(), None);
+    // Receiving the same content again leaves the clipboard alone.
+    assert!(!sync.apply_remote("from phone", None).unwrap());
+}
+
+#[test]
+fn connect_content_applies_only_when_newer() {
+    let (backend, sync) = sync_with(None);
+    backend.set("local").unwrap();
+    let (_, local_timestamp) = sync.poll_local().unwrap().unwrap();
+
+    assert!(!sync.apply_remote("unknown age", Some(0)).unwrap());
+    assert!(!sync
+        .apply_remote("older", Some(local_timestamp - 1))
+        .unwrap());
+    assert_eq!(backend.get().unwrap().as_deref(), Some("local"));
+
+    assert!(sync
+        .apply_remote("newer", Some(local_timestamp + 1))
+        .unwrap());
+    assert_eq!(backend.get().unwrap().as_deref(), Some("newer"));
+    assert_eq!(
+        sync.current().unwrap(),
+        Some(("newer".to_string(), local_timestamp + 1))
+    );
+}
+
+#[test]
+fn history_is_bounded_and_deduplicated() {
+    let mut history = Vec::new();
+    for content in ["a", "b", "c", "a"] {
+        record(
+            &mut history,
+            ClipboardEntry::new(content, ClipboardSource::Local),
+            2,
+        );
+    }
+    let contents: Vec<_> = history.iter().map(|entry| entry.content.as_str()).collect();
+    assert_eq!(contents, ["a", "c"]);
+}
+
+#[test]
+fn sensitive_content_matches_patterns() {
+    let patterns = compile_patterns(&[r"^\s*\d{4,8}\s*$".to_string(), "(".to_string()]);
+    assert!(is_sensitive(" 123456 ", &patterns));
+    assert!(!is_sensitive("call me at 5", &patterns));
+}
@@ -0,0 +1,51 @@
+//! Fixtures shared by the integration tests; each test file uses a subset of them.
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use rusty_connect::{
+    devices::{DeviceManager, Peer},
+    payloads::Payload,
+};
+
+/// Fresh folder under the system temp dir, removed when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(prefix: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("{prefix}-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).expect("create temp dir");
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Device manager storing its data in `dir`, without certificates.
+pub async fn device_manager(dir: &TempDir) -> DeviceManager {
+    let (sender, receiver) = flume::bounded(0);
+    DeviceManager::load_or_create(dir.path(), sender, receiver, (vec![], vec![]))
+        .await
+        .expect("device manager")
+}
+
+/// Connection to the device `phone`, with the packets sent to it.
+pub fn peer(paired: bool) -> (Peer, flume::Receiver<Payload>) {
+    let (sender, receiver) = flume::unbounded();
+    let peer = Peer {
+        device_id: "phone".to_string(),
+        paired,
+        address: "192.168.1.30:1716".parse().expect("address"),
+        certificate: None,
+        sender,
+    };
+    (peer, receiver)
+}
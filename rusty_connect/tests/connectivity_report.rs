@@ -0,0 +1,87 @@
+use rusty_connect::{
+    payloads::Payload,
+    plugins::{
+        connectivity_report::{
+            ConnectivityReport, ConnectivityReportPayload, ConnectivityReportState, SimSignal,
+        },
+        Plugin,
+    },
+};
+use serde_json::json;
+
+use self::common::TempDir;
+
+mod common;
+
+async fn plugin() -> ConnectivityReport {
+    let dir = TempDir::new("connectivity");
+    ConnectivityReport::init(&common::device_manager(&dir).await)
+}
+
+async fn parse(plugin: &ConnectivityReport, body: serde_json::Value) -> ConnectivityReportPayload {
+    let payload = Payload::generate_new("kdeconnect.connectivity_report", body);
+    plugin
+        .parse_payload(&payload, &common::peer(true).0, &None)
+        .await
+        .expect("connectivity payload")
+}
+
+fn signal(subscription_id: &str, network_type: &str, signal_strength: i32) -> SimSignal {
+    SimSignal {
+        subscription_id: subscription_id.to_string(),
+        network_type: network_type.to_string(),
+        signal_strength,
+    }
+}
+
+#[tokio::test]
+async fn every_sim_is_reported() {
+    let plugin = plugin().await;
+    let payload = parse(
+        &plugin,
+        json!({"signalStrengths": {
+            "6": {"networkType": "5G", "signalStrength": 2},
+            "1": {"networkType": "4G", "signalStrength": 4},
+        }}),
+    )
+    .await;
+    let mut state = ConnectivityReportState::default();
+    plugin.update_state(&payload, &mut state);
+    assert_eq!(
+        state.signals,
+        vec![signal("1", "4G", 4), signal("6", "5G", 2)]
+    );
+    assert!(state.updated_at.is_some());
+}
+
+#[tokio::test]
+async fn changes_are_emitted_only_when_signals_differ() {
+    let plugin = plugin().await;
+    let mut changes = plugin.subscribe_changes();
+    let mut state = ConnectivityReportState::default();
+    let body = json!({"signalStrengths": {"1": {"networkType": "4G", "signalStrength": 3}}});
+
+    for _ in 0..2 {
+        let payload = parse(&plugin, body.clone()).await;
+        let previous_state = state.clone();
+        plugin.update_state(&payload, &mut state);
+        assert!(plugin.handle(&payload, &previous_state).is_none());
+    }
+    let changed = changes.try_recv().expect("first report");
+    assert_eq!(changed.device_id, "phone");
+    assert_eq!(changed.signals, vec![signal("1", "4G", 3)]);
+    assert!(changes.try_recv().is_err(), "unchanged report emitted");
+
+    let payload = parse(
+        &plugin,
+        json!({"signalStrengths": {"1": {"networkType": "4G", "signalStrength": 1}}}),
+    )
+    .await;
+    let previous_state = state.clone();
+    plugin.update_state(&payload, &mut state);
+    plugin.handle(&payload, &previous_state);
+    assert_eq!(
+        changes.try_recv().expect("changed report").signals,
+        vec![signal("1", "4G", 1)]
+    );
+}
@@ -0,0 +1,67 @@
+use rusty_connect::plugins::contacts::store::ContactStore;
+
+use self::common::TempDir;
+
+mod common;
+
+const VCARD: &str =
+    "BEGIN:VCARD\r\nVERSION:2.1\r\nFN:Ada Lovelace\r\nTEL:+44 20 1234\r\nEND:VCARD\r\n";
+
+fn store() -> (ContactStore, TempDir) {
+    let dir = TempDir::new("contacts");
+    (ContactStore::new(dir.path().to_path_buf()), dir)
+}
+
+#[tokio::test]
+async fn dotted_uids_round_trip() {
+    let (store, _dir) = store();
+    let uid = "1.2%3/../lookup";
+    store.save("phone", uid, VCARD, 42).await.expect("save");
+
+    let timestamps = store.timestamps("phone").await.expect("timestamps");
+    assert_eq!(timestamps.get(uid), Some(&42));
+    let contacts = store.search("phone", "ada").await.expect("search");
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0].uid, uid);
+    assert_eq!(contacts[0].phone_numbers, vec!["+44 20 1234"]);
+
+    store.remove("phone", uid).await.expect("remove");
+    assert!(store
+        .timestamps("phone")
+        .await
+        .expect("timestamps")
+        .is_empty());
+}
+
+#[tokio::test]
+async fn saving_again_replaces_the_contact() {
+    let (store, _dir) = store();
+    store.save("phone", "a.b", VCARD, 1).await.expect("save");
+    store.save("phone", "a.b", VCARD, 2).await.expect("save");
+    let timestamps = store.timestamps("phone").await.expect("timestamps");
+    assert_eq!(timestamps.len(), 1);
+    assert_eq!(timestamps.get("a.b"), Some(&2));
+}
+
+#[tokio::test]
+async fn uids_with_line_breaks_are_rejected() {
+    let (store, _dir) = store();
+    assert!(store
+        .save("phone", "a\r\nFN:Mallory", VCARD, 1)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn our_properties_win_over_the_ones_the_peer_sent() {
+    let (store, _dir) = store();
+    let vcard = "BEGIN:VCARD\r\nVERSION:2.1\r\nFN:Ada Lovelace\r\nX-KDECONNECT-UID:peer\r\n\
+                 X-KDECONNECT-TIMESTAMP:7\r\nEND:VCARD\r\n";
+    store.save("phone", "ours", vcard, 42).await.expect("save");
+
+    let timestamps = store.timestamps("phone").await.expect("timestamps");
+    assert_eq!(timestamps.len(), 1);
+    assert_eq!(timestamps.get("ours"), Some(&42));
+    let contacts = store.search("phone", "").await.expect("search");
+    assert_eq!(contacts[0].uid, "ours");
+}
@@ -0,0 +1,182 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use futures::future::BoxFuture;
+use rusty_connect::{
+    devices::{DeviceWithState, Peer},
+    payloads::{IdentityPayloadBody, Payload},
+    plugins::{
+        extension::{ExtensionHandle, ExtensionPlugin},
+        PluginAction, PluginManager, ReceivedPayload, Reply,
+    },
+};
+use serde_json::{json, Value};
+use tokio::sync::RwLock;
+
+use self::common::TempDir;
+
+mod common;
+
+/// Counts `kdeconnect.counter` packets and answers `kdeconnect.counter.request`.
+#[derive(Clone, Default)]
+struct Counter {
+    connections: Arc<AtomicUsize>,
+}
+
+impl ExtensionPlugin for Counter {
+    fn id(&self) -> &str {
+        "counter"
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec![
+            "kdeconnect.counter".to_string(),
+            "kdeconnect.counter.request".to_string(),
+        ]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec!["kdeconnect.counter".to_string()]
+    }
+
+    fn connected(
+        &self,
+        _device: &DeviceWithState,
+        _config: &Option<Value>,
+    ) -> Option<PluginAction> {
+        let connections = self.connections.clone();
+        Some(PluginAction::Async(Box::pin(async move {
+            connections.fetch_add(1, Ordering::SeqCst);
+        })))
+    }
+
+    fn reply<'a>(
+        &'a self,
+        payload: &'a Payload,
+        _peer: &'a Peer,
+        _config: &'a Option<Value>,
+    ) -> BoxFuture<'a, anyhow::Result<Option<Reply>>> {
+        Box::pin(async move {
+            if payload.r#type != "kdeconnect.counter.request" {
+                return Ok(None);
+            }
+            Ok(Some(Reply::new("kdeconnect.counter", json!({"count": 0}))?))
+        })
+    }
+
+    fn parse_payload<'a>(
+        &'a self,
+        payload: &'a Payload,
+        _peer: &'a Peer,
+        _config: &'a Option<Value>,
+    ) -> BoxFuture<'a, Option<Value>> {
+        Box::pin(async move { Some(payload.body.clone()) })
+    }
+
+    fn update_state(&self, payload: &Value, state: &mut Value) {
+        let count = state
+            .get("count")
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        let step = payload.get("step").and_then(Value::as_u64).unwrap_or(1);
+        *state = json!({"count": count + step});
+    }
+}
+
+fn identity() -> IdentityPayloadBody {
+    IdentityPayloadBody {
+        device_name: "Phone".to_string(),
+        device_id: "phone".to_string(),
+        device_type: "phone".to_string(),
+        incoming_capabilities: vec!["kdeconnect.counter".to_string()],
+        outgoing_capabilities: vec![
+            "kdeconnect.counter".to_string(),
+            "kdeconnect.counter.request".to_string(),
+        ],
+        protocol_version: 7,
+        tcp_port: Some(1716),
+    }
+}
+
+#[tokio::test]
+async fn packets_are_routed_to_the_extension_and_its_config_persists() {
+    let dir = TempDir::new("extension");
+    let counter = Counter::default();
+    let device_manager = Arc::new(RwLock::new(common::device_manager(&dir).await));
+    let plugin_manager = PluginManager::new(
+        "laptop".to_string(),
+        "Laptop".to_string(),
+        "laptop".to_string(),
+        &*device_manager.read().await,
+    );
+    plugin_manager
+        .extensions
+        .register(Arc::new(counter.clone()))
+        .expect("register");
+    let capabilities = plugin_manager.capabilities_for(&identity());
+    let (_, receiver, _) = device_manager
+        .write()
+        .await
+        .connected_to(
+            "127.0.0.1:1716".parse().expect("address"),
+            identity(),
+            capabilities,
+        )
+        .await
+        .expect("connect");
+
+    let mut devices = device_manager.write().await;
+    let device = devices.devices.get_mut("phone").expect("device");
+    for action in plugin_manager.connected(device) {
+        action.spawn(&plugin_manager.blocking_queues, "phone");
+    }
+
+    let payload = Payload::generate_new("kdeconnect.counter", json!({"step": 2}));
+    let received = plugin_manager
+        .parse_payload(payload, Some(device))
+        .await
+        .expect("parse");
+    let ReceivedPayload::Extension(extension_payload) = &received else {
+        panic!("payload not routed to the extension");
+    };
+    assert_eq!(extension_payload.plugin, "counter");
+    plugin_manager.update_state(&received, device);
+    assert_eq!(
+        device.device.extension_states["counter"],
+        json!({"count": 2})
+    );
+
+    let request = Payload::generate_new("kdeconnect.counter.request", json!({}));
+    let (answered, reply) = tokio::join!(
+        plugin_manager.parse_payload(request, Some(device)),
+        receiver.recv_async()
+    );
+    assert!(matches!(
+        answered.expect("parse"),
+        ReceivedPayload::Request(_)
+    ));
+    assert_eq!(reply.expect("reply").r#type, "kdeconnect.counter");
+    drop(devices);
+
+    for _ in 0..10 {
+        tokio::task::yield_now().await;
+    }
+    assert_eq!(counter.connections.load(Ordering::SeqCst), 1);
+
+    let handle = ExtensionHandle::new(Arc::new(counter.clone()), device_manager.clone());
+    let config = json!({"enabled": true, "limit": 5});
+    handle
+        .set_config("phone", config.clone())
+        .await
+        .expect("set config");
+    assert_eq!(
+        handle.config("phone").await.expect("config"),
+        Some(config.clone())
+    );
+
+    let reloaded = Arc::new(RwLock::new(common::device_manager(&dir).await));
+    let handle = ExtensionHandle::new(Arc::new(counter), reloaded);
+    assert_eq!(handle.config("phone").await.expect("config"), Some(config));
+}
@@ -0,0 +1,59 @@
+use std::sync::{Arc, Mutex};
+
+use rusty_connect::{
+    payloads::Payload,
+    plugins::{
+        findmyphone::{FindMyPhone, FindMyPhoneState, RingHandler},
+        Plugin, PluginAction,
+    },
+};
+use serde_json::json;
+
+mod common;
+
+#[derive(Default)]
+struct Rings(Mutex<Vec<String>>);
+
+impl RingHandler for Rings {
+    fn ring(&self, device_id: &str) {
+        self.0.lock().expect("rings").push(device_id.to_string());
+    }
+}
+
+/// Runs a find request from the peer, returning the state it leaves behind.
+async fn find(plugin: &FindMyPhone, paired: bool) -> FindMyPhoneState {
+    let (peer, _) = common::peer(paired);
+    let packet = Payload::generate_new("kdeconnect.findmyphone.request", json!({}));
+    let parsed = plugin
+        .parse_payload(&packet, &peer, &None)
+        .await
+        .expect("parsed");
+    let mut state = FindMyPhoneState::default();
+    plugin.update_state(&parsed, &mut state);
+    if let Some(PluginAction::Blocking(ring)) = plugin.handle(&parsed, &state) {
+        ring();
+    }
+    state
+}
+
+#[tokio::test]
+async fn paired_devices_ring_and_are_recorded() {
+    let plugin = FindMyPhone::default();
+    let rings = Arc::new(Rings::default());
+    plugin.set_ring_handler(Some(rings.clone()));
+
+    let state = find(&plugin, true).await;
+    assert!(state.last_request_at.is_some());
+    assert_eq!(*rings.0.lock().expect("rings"), vec!["phone"]);
+}
+
+#[tokio::test]
+async fn unpaired_devices_are_ignored() {
+    let plugin = FindMyPhone::default();
+    let rings = Arc::new(Rings::default());
+    plugin.set_ring_handler(Some(rings.clone()));
+
+    let state = find(&plugin, false).await;
+    assert_eq!(state.last_request_at, None);
+    assert!(rings.0.lock().expect("rings").is_empty());
+}
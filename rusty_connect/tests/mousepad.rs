@@ -0,0 +1,255 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use enigo::{Key, MouseButton};
+use rusty_connect::plugins::{
+    mousepad::{
+        input_actions, special_key, InputAction, InputBackend, MousepadPayload, MousepadState,
+        RecordingBackend,
+    },
+    BlockingQueues,
+};
+use serde_json::json;
+
+/// Applies actions like a real input device would, so unbalanced presses show up.
+#[derive(Default)]
+struct MockBackend {
+    events: Vec<InputAction>,
+    held_keys: HashSet<Key>,
+    held_buttons: HashSet<MouseButton>,
+}
+
+impl MockBackend {
+    fn apply(&mut self, actions: Vec<InputAction>) {
+        for action in actions {
+            match &action {
+                InputAction::KeyDown(key) => {
+                    assert!(self.held_keys.insert(*key), "{key:?} pressed twice");
+                }
+                InputAction::KeyUp(key) => {
+                    assert!(self.held_keys.remove(key), "{key:?} released but not held");
+                }
+                InputAction::MouseDown(button) => {
+                    assert!(
+                        self.held_buttons.insert(*button),
+                        "{button:?} pressed twice"
+                    );
+                }
+                InputAction::MouseUp(button) => {
+                    assert!(
+                        self.held_buttons.remove(button),
+                        "{button:?} released but not held"
+                    );
+                }
+                _ => {}
+            }
+            self.events.push(action);
+        }
+    }
+}
+
+fn payload(body: serde_json::Value) -> MousepadPayload {
+    serde_json::from_value(body).expect("valid mousepad payload")
+}
+
+#[test]
+fn special_keys_cover_protocol_table() {
+    let cases = [
+        (1, Some(Key::Backspace)),
+        (2, Some(Key::Tab)),
+        (3, Some(Key::Return)),
+        (4, Some(Key::LeftArrow)),
+        (5, Some(Key::UpArrow)),
+        (6, Some(Key::RightArrow)),
+        (7, Some(Key::DownArrow)),
+        (8, Some(Key::PageUp)),
+        (9, Some(Key::PageDown)),
+        (10, Some(Key::Home)),
+        (11, Some(Key::End)),
+        (12, Some(Key::Return)),
+        (13, Some(Key::Delete)),
+        (14, Some(Key::Escape)),
+        #[cfg(target_os = "linux")]
+        (15, Some(Key::SysReq)),
+        #[cfg(target_os = "linux")]
+        (16, Some(Key::ScrollLock)),
+        (17, None),
+        (20, None),
+        (21, Some(Key::F1)),
+        (26, Some(Key::F6)),
+        (32, Some(Key::F12)),
+        (0, None),
+        (33, None),
+    ];
+    for (code, expected) in cases {
+        assert_eq!(special_key(code), expected, "special key {code}");
+    }
+}
+
+#[test]
+fn requests_translate_to_input_actions() {
+    let cases = [
+        (
+            json!({"singleclick": true}),
+            vec![InputAction::MouseClick(MouseButton::Left)],
+        ),
+        (
+            json!({"doubleclick": true}),
+            vec![
+                InputAction::MouseClick(MouseButton::Left),
+                InputAction::MouseClick(MouseButton::Left),
+            ],
+        ),
+        (
+            json!({"rightclick": true}),
+            vec![InputAction::MouseClick(MouseButton::Right)],
+        ),
+        (
+            json!({"middleclick": true}),
+            vec![InputAction::MouseClick(MouseButton::Middle)],
+        ),
+        (
+            json!({"dx": 3.4, "dy": -2.6}),
+            vec![InputAction::MoveRelative { dx: 3, dy: -3 }],
+        ),
+        (
+            json!({"dx": 0.0, "dy": 5.0, "scroll": true}),
+            vec![InputAction::ScrollY(-1)],
+        ),
+        (
+            json!({"dx": 1.0, "dy": -4.0, "scroll": true}),
+            vec![InputAction::ScrollY(1)],
+        ),
+        (
+            json!({"dx": -6.0, "dy": 2.0, "scroll": true}),
+            vec![InputAction::ScrollX(-1)],
+        ),
+        (json!({"dx": 0.0, "dy": 0.0, "scroll": true}), vec![]),
+        (
+            json!({"key": "hello"}),
+            vec![InputAction::Text("hello".to_string())],
+        ),
+        (
+            json!({"specialKey": 12}),
+            vec![InputAction::KeyClick(Key::Return)],
+        ),
+        (
+            json!({"key": "c", "ctrl": true}),
+            vec![
+                InputAction::KeyDown(Key::Control),
+                InputAction::Text("c".to_string()),
+                InputAction::KeyUp(Key::Control),
+            ],
+        ),
+        (
+            json!({"specialKey": 4, "ctrl": true, "alt": true, "shift": true, "super": true}),
+            vec![
+                InputAction::KeyDown(Key::Control),
+                InputAction::KeyDown(Key::Alt),
+                InputAction::KeyDown(Key::Shift),
+                InputAction::KeyDown(Key::Meta),
+                InputAction::KeyClick(Key::LeftArrow),
+                InputAction::KeyUp(Key::Meta),
+                InputAction::KeyUp(Key::Shift),
+                InputAction::KeyUp(Key::Alt),
+                InputAction::KeyUp(Key::Control),
+            ],
+        ),
+        (json!({"specialKey": 18, "ctrl": true}), vec![]),
+        (json!({"key": "", "shift": true}), vec![]),
+    ];
+    for (body, expected) in cases {
+        let mut backend = MockBackend::default();
+        backend.apply(input_actions(
+            &payload(body.clone()),
+            &MousepadState::default(),
+        ));
+        assert_eq!(backend.events, expected, "payload {body}");
+        assert!(backend.held_keys.is_empty(), "keys left held for {body}");
+    }
+}
+
+#[test]
+fn hold_is_released_once() {
+    let mut backend = MockBackend::default();
+    let mut state = MousepadState::default();
+    let steps = [
+        (json!({"singlehold": true}), true),
+        // A second hold while held must not press again.
+        (json!({"singlehold": true}), true),
+        (json!({"dx": 10.0, "dy": 0.0}), true),
+        (json!({"singlerelease": true}), false),
+        // Releasing again is a no-op.
+        (json!({"singlerelease": true}), false),
+    ];
+    for (body, held) in steps {
+        let actions = input_actions(&payload(body.clone()), &state);
+        state.track_held_buttons(&actions);
+        backend.apply(actions);
+        assert_eq!(
+            backend.held_buttons.contains(&MouseButton::Left),
+            held,
+            "after {body}"
+        );
+    }
+    assert_eq!(
+        backend.events,
+        vec![
+            InputAction::MouseDown(MouseButton::Left),
+            InputAction::MoveRelative { dx: 10, dy: 0 },
+            InputAction::MouseUp(MouseButton::Left),
+        ]
+    );
+}
+
+#[test]
+fn recording_backend_keeps_applied_actions() {
+    let backend = RecordingBackend::default();
+    let state = MousepadState::default();
+    for body in [
+        json!({"singleclick": true}),
+        json!({"key": "a", "shift": true}),
+    ] {
+        backend
+            .apply(&input_actions(&payload(body), &state))
+            .expect("recording never fails");
+    }
+    assert_eq!(
+        backend.take(),
+        vec![
+            InputAction::MouseClick(MouseButton::Left),
+            InputAction::KeyDown(Key::Shift),
+            InputAction::Text("a".to_string()),
+            InputAction::KeyUp(Key::Shift),
+        ]
+    );
+    assert!(backend.take().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blocking_work_of_a_device_runs_in_order() {
+    let queues = BlockingQueues::default();
+    let applied = Arc::new(Mutex::new(vec![]));
+    for index in 0..50 {
+        let applied = applied.clone();
+        queues.push(
+            "phone",
+            Box::new(move || {
+                if index % 7 == 0 {
+                    std::thread::sleep(Duration::from_millis(2));
+                }
+                applied.lock().expect("applied").push(index);
+            }),
+        );
+    }
+    let (done, finished) = flume::bounded(1);
+    queues.push("phone", Box::new(move || done.send(()).expect("done")));
+    finished.recv_async().await.expect("queue drained");
+    assert_eq!(
+        *applied.lock().expect("applied"),
+        (0..50).collect::<Vec<_>>()
+    );
+}
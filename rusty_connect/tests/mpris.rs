@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use rusty_connect::{
+    payloads::Payload,
+    plugins::{
+        mpris::{InMemoryMediaBackend, Mpris, MprisState, PlayerStatus},
+        Plugin, PluginAction,
+    },
+};
+use serde_json::{json, Value};
+
+mod common;
+
+fn backend() -> Arc<InMemoryMediaBackend> {
+    Arc::new(InMemoryMediaBackend::default().with_player(
+        "vlc",
+        PlayerStatus {
+            title: "Song".to_string(),
+            length: 180_000,
+            position: 10_000,
+            volume: 80,
+            can_play: true,
+            can_pause: true,
+            ..Default::default()
+        },
+    ))
+}
+
+/// Parses `body` as a request from `peer` and runs what the plugin does with it.
+async fn request(mpris: &Mpris, body: Value, paired: bool) -> Vec<Payload> {
+    let (peer, sent) = common::peer(paired);
+    let packet = Payload::generate_new("kdeconnect.mpris.request", body);
+    let parsed = mpris
+        .parse_payload(&packet, &peer, &None)
+        .await
+        .expect("parsed");
+    if let Some(PluginAction::Async(action)) = mpris.handle(&parsed, &MprisState::default()) {
+        action.await;
+    }
+    sent.drain().collect()
+}
+
+#[tokio::test]
+async fn requests_drive_the_local_player_and_report_it() {
+    let backend = backend();
+    let mpris = Mpris::with_backend(backend.clone());
+
+    let replies = request(
+        &mpris,
+        json!({
+            "player": "vlc",
+            "action": "PlayPause",
+            "setVolume": 30,
+            "SetPosition": 60_000,
+            "requestNowPlaying": true,
+        }),
+        true,
+    )
+    .await;
+
+    let status = backend.get("vlc").expect("player");
+    assert!(status.is_playing);
+    assert_eq!(status.volume, 30);
+    assert_eq!(status.position, 60_000);
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0].r#type, "kdeconnect.mpris");
+    assert_eq!(replies[0].body["player"], "vlc");
+    assert_eq!(replies[0].body["isPlaying"], true);
+    assert_eq!(replies[0].body["volume"], 30);
+    assert_eq!(replies[0].body["pos"], 60_000);
+}
+
+#[tokio::test]
+async fn player_list_requests_are_answered() {
+    let mpris = Mpris::with_backend(backend());
+    let replies = request(&mpris, json!({"requestPlayerList": true}), true).await;
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0].body["playerList"], json!(["vlc"]));
+}
+
+#[tokio::test]
+async fn unpaired_devices_cannot_control_players() {
+    let backend = backend();
+    let mpris = Mpris::with_backend(backend.clone());
+    let replies = request(
+        &mpris,
+        json!({"player": "vlc", "action": "Play", "requestNowPlaying": true}),
+        false,
+    )
+    .await;
+    assert!(replies.is_empty());
+    assert!(!backend.get("vlc").expect("player").is_playing);
+}
+
+#[tokio::test]
+async fn reported_players_are_kept_in_the_state() {
+    let mpris = Mpris::with_backend(backend());
+    let (peer, _) = common::peer(true);
+    let mut state = MprisState::default();
+    for body in [
+        json!({"playerList": ["spotify", "vlc"]}),
+        json!({"player": "vlc", "title": "Song", "isPlaying": true}),
+        json!({"playerList": ["vlc"]}),
+    ] {
+        let packet = Payload::generate_new("kdeconnect.mpris", body);
+        let parsed = mpris
+            .parse_payload(&packet, &peer, &None)
+            .await
+            .expect("parsed");
+        mpris.update_state(&parsed, &mut state);
+    }
+    let players = serde_json::to_value(&state).expect("state")["players"].clone();
+    assert_eq!(players.as_array().map(Vec::len), Some(1));
+    assert_eq!(players[0]["name"], "vlc");
+    assert_eq!(players[0]["status"]["is_playing"], true);
+}
@@ -0,0 +1,141 @@
+use std::{net::SocketAddr, time::Duration};
+
+use rusty_connect::{
+    cert::{certgen::generate_cert, CertPair},
+    payload_transfer::{
+        download_to_file, download_to_memory, upload_from_reader, CancellationToken,
+        TransferOptions,
+    },
+};
+use tokio::sync::OnceCell;
+
+use self::common::TempDir;
+
+mod common;
+
+/// Generating an RSA key is slow, so every test shares one.
+async fn certs() -> &'static CertPair {
+    static CERTS: OnceCell<CertPair> = OnceCell::const_new();
+    CERTS
+        .get_or_init(|| async {
+            let dir = TempDir::new("payload-transfer-certs");
+            generate_cert(
+                "laptop",
+                dir.path().join("cert.pem"),
+                dir.path().join("key.pem"),
+            )
+            .await
+            .expect("certs")
+        })
+        .await
+}
+
+fn localhost() -> SocketAddr {
+    "127.0.0.1:1716".parse().expect("address")
+}
+
+fn options() -> TransferOptions {
+    TransferOptions {
+        peer_address: Some(localhost().ip()),
+        ..Default::default()
+    }
+    .with_timeout(Duration::from_secs(5))
+}
+
+#[tokio::test]
+async fn uploads_round_trip_into_memory() {
+    let certs = certs().await;
+    let data = (0..200_000u32).map(|i| i as u8).collect::<Vec<_>>();
+    let (port, upload) = upload_from_reader(
+        std::io::Cursor::new(data.clone()),
+        data.len() as u64,
+        certs,
+        options(),
+    )
+    .await
+    .expect("upload");
+
+    let received = download_to_memory(localhost(), port, data.len() as u64, certs, &options())
+        .await
+        .expect("download");
+    assert_eq!(received, data);
+    assert_eq!(
+        upload.await.expect("task").expect("sent"),
+        data.len() as u64
+    );
+}
+
+#[tokio::test]
+async fn downloads_are_renamed_from_part_once_complete() {
+    let certs = certs().await;
+    let dir = TempDir::new("payload-transfer");
+    let path = dir.path().join("file.txt");
+    let (port, upload) = upload_from_reader(&b"hello"[..], 5, certs, options())
+        .await
+        .expect("upload");
+
+    download_to_file(localhost(), port, 5, &path, certs, &options())
+        .await
+        .expect("download");
+    upload.await.expect("task").expect("sent");
+    assert_eq!(tokio::fs::read(&path).await.expect("file"), b"hello");
+    assert!(!dir.path().join("file.txt.part").exists());
+}
+
+#[tokio::test]
+async fn payloads_over_the_limit_are_refused() {
+    let certs = certs().await;
+    let options = options().with_max_size(4);
+    assert!(upload_from_reader(&b"hello"[..], 5, certs, options.clone())
+        .await
+        .is_err());
+    // Refused before connecting, so nothing needs to listen on the port.
+    assert!(download_to_memory(localhost(), 1, 5, certs, &options)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn cancelled_downloads_leave_no_file_behind() {
+    let certs = certs().await;
+    let dir = TempDir::new("payload-transfer");
+    let path = dir.path().join("file.txt");
+    // The peer announces more than it ever sends, so the download stalls.
+    let (_writer, reader) = tokio::io::duplex(64);
+    let (port, upload) = upload_from_reader(reader, 5, certs, options())
+        .await
+        .expect("upload");
+
+    let cancellation = CancellationToken::new();
+    let download_options = options().with_cancellation(cancellation.clone());
+    let download = download_to_file(localhost(), port, 5, &path, certs, &download_options);
+    let cancel = async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        cancellation.cancel();
+    };
+    let (downloaded, _) = tokio::join!(download, cancel);
+    assert!(downloaded.is_err());
+    assert!(!path.exists());
+    assert!(!dir.path().join("file.txt.part").exists());
+    upload.abort();
+}
+
+#[tokio::test]
+async fn uploads_refuse_other_addresses_before_the_handshake() {
+    let certs = certs().await;
+    let cancellation = CancellationToken::new();
+    let upload_options = TransferOptions {
+        peer_address: Some("192.0.2.1".parse().expect("address")),
+        ..options()
+    }
+    .with_cancellation(cancellation.clone());
+    let (port, upload) = upload_from_reader(&b"hello"[..], 5, certs, upload_options)
+        .await
+        .expect("upload");
+
+    assert!(download_to_memory(localhost(), port, 5, certs, &options())
+        .await
+        .is_err());
+    cancellation.cancel();
+    assert!(upload.await.expect("task").is_err());
+}
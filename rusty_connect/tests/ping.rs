@@ -0,0 +1,96 @@
+use rusty_connect::{
+    payloads::Payload,
+    plugins::{
+        ping::{Ping, PingLatency, PingPayload, PingState, Probe},
+        Plugin,
+    },
+    utils::get_timestamp,
+};
+use serde_json::json;
+
+use self::common::TempDir;
+
+mod common;
+
+fn probe(sent_at: u64, round_trip_ms: Option<u64>) -> Probe {
+    Probe {
+        sent_at,
+        round_trip_ms,
+    }
+}
+
+#[test]
+fn stats_cover_answered_and_lost_probes() {
+    let now = 60_000;
+    let probes = [
+        probe(55_000, Some(30)),
+        probe(50_000, None),
+        probe(45_000, Some(10)),
+        probe(40_000, Some(20)),
+    ];
+    let latency = PingLatency::from_probes(&probes, now).expect("stats");
+    assert_eq!(latency.sent, 4);
+    assert_eq!(latency.lost, 1);
+    assert_eq!(latency.last_ms, Some(30));
+    assert_eq!(latency.min_ms, Some(10));
+    assert_eq!(latency.max_ms, Some(30));
+    assert_eq!(latency.average_ms, Some(20.0));
+    assert_eq!(latency.jitter_ms, Some(15.0));
+}
+
+#[test]
+fn probes_in_flight_are_not_lost() {
+    let probes = [probe(59_000, None), probe(40_000, Some(12))];
+    let latency = PingLatency::from_probes(&probes, 60_000).expect("stats");
+    assert_eq!(latency.sent, 1);
+    assert_eq!(latency.lost, 0);
+    assert_eq!(PingLatency::from_probes(&probes[..1], 60_000), None);
+}
+
+#[tokio::test]
+async fn probe_requests_are_answered_with_their_sent_at_only() {
+    let dir = TempDir::new("ping");
+    let ping = Ping::init(&common::device_manager(&dir).await);
+    let (peer, _) = common::peer(true);
+    let request = Payload::generate_new(
+        "kdeconnect.ping.request",
+        json!({"sentAt": 1_000, "message": "spoofed", "extra": [1, 2, 3]}),
+    );
+    let reply = ping
+        .reply(&request, &peer, &None)
+        .await
+        .expect("reply")
+        .expect("answered");
+    assert_eq!(reply.payload_type, "kdeconnect.ping");
+    assert_eq!(reply.body["sentAt"], json!(1_000));
+    assert!(reply.body["message"].is_null());
+    assert!(reply.body.get("extra").is_none());
+}
+
+#[tokio::test]
+async fn echoes_update_the_stats_without_reaching_subscribers() {
+    let dir = TempDir::new("ping");
+    let ping = Ping::init(&common::device_manager(&dir).await);
+    let (peer, _) = common::peer(true);
+    let mut state = PingState::default();
+    let sent_at = get_timestamp() as u64;
+    let probe: PingPayload = serde_json::from_value(json!({"sentAt": sent_at})).expect("probe");
+    ping.sent(&probe, &mut state);
+
+    let echo = Payload::generate_new("kdeconnect.ping", json!({"sentAt": sent_at}));
+    let echo = ping
+        .parse_payload(&echo, &peer, &None)
+        .await
+        .expect("parsed");
+    ping.update_state(&echo, &mut state);
+    assert!(!ping.emits(&echo));
+    let probes = serde_json::to_value(&state).expect("state")["probes"].clone();
+    assert!(probes[0]["round_trip_ms"].is_u64());
+
+    let user_ping = Payload::generate_new("kdeconnect.ping", json!({"message": "hi"}));
+    let user_ping = ping
+        .parse_payload(&user_ping, &peer, &None)
+        .await
+        .expect("parsed");
+    assert!(ping.emits(&user_ping));
+}
@@ -0,0 +1,78 @@
+use rusty_connect::{
+    payloads::Payload,
+    plugins::{
+        presenter::{pointer_after, Presenter, PresenterPayload, PresenterState},
+        Plugin,
+    },
+};
+use serde_json::json;
+
+use self::common::TempDir;
+
+mod common;
+
+fn payload(body: serde_json::Value) -> PresenterPayload {
+    serde_json::from_value(body).expect("valid presenter payload")
+}
+
+#[test]
+fn pointer_moves_from_the_previous_state_and_stays_on_screen() {
+    let mut state = PresenterState::default();
+    let steps = [
+        (json!({"dx": 0.25, "dy": -0.1}), (0.75, 0.4)),
+        (json!({"dx": 0.5}), (1.0, 0.4)),
+        (json!({"dy": -1.0}), (1.0, 0.0)),
+    ];
+    for (body, (x, y)) in steps {
+        let pointer = pointer_after(&payload(body.clone()), &state);
+        assert!(pointer.active, "after {body}");
+        assert!((pointer.x - x).abs() < 1e-6, "x after {body}");
+        assert!((pointer.y - y).abs() < 1e-6, "y after {body}");
+        state.pointer = Some(pointer);
+    }
+}
+
+#[test]
+fn stopping_hides_and_recenters_the_pointer() {
+    let moved = pointer_after(&payload(json!({"dx": 0.3})), &PresenterState::default());
+    let state = PresenterState {
+        pointer: Some(moved),
+    };
+    let pointer = pointer_after(&payload(json!({"stop": true})), &state);
+    assert!(!pointer.active);
+    assert_eq!((pointer.x, pointer.y), (0.5, 0.5));
+
+    let pointer = pointer_after(&payload(json!({"dy": 0.1})), &PresenterState::default());
+    assert!((pointer.x - 0.5).abs() < 1e-6 && (pointer.y - 0.6).abs() < 1e-6);
+}
+
+#[tokio::test]
+async fn handled_payloads_broadcast_the_pointer_of_their_device() {
+    let dir = TempDir::new("presenter");
+    let presenter = Presenter::init(&common::device_manager(&dir).await);
+    let mut pointers = presenter.subscribe_pointer();
+    let (peer, _) = common::peer(true);
+
+    let mut state = PresenterState::default();
+    for (body, active) in [
+        (json!({"dx": 0.1, "dy": 0.1}), true),
+        (json!({"stop": true}), false),
+    ] {
+        let packet = Payload::generate_new("kdeconnect.presenter", body);
+        let parsed = presenter
+            .parse_payload(&packet, &peer, &None)
+            .await
+            .expect("parsed");
+        let previous = state.clone();
+        presenter.update_state(&parsed, &mut state);
+        presenter.handle(&parsed, &previous);
+
+        let pointer = pointers.try_recv().expect("pointer broadcast");
+        assert_eq!(pointer.device_id, "phone");
+        assert_eq!(pointer.active, active);
+        assert_eq!(
+            state.pointer.as_ref().map(|pointer| pointer.active),
+            Some(active)
+        );
+    }
+}
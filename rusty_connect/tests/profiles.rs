@@ -0,0 +1,110 @@
+use rusty_connect::{
+    devices::profiles::PluginDefaults,
+    payloads::IdentityPayloadBody,
+    plugins::{DeviceCapabilities, PluginConfigs},
+};
+
+use self::common::TempDir;
+
+mod common;
+
+fn enabled(configs: &PluginConfigs, plugin: &str) -> Option<bool> {
+    serde_json::to_value(configs).expect("configs")[plugin]["enabled"].as_bool()
+}
+
+async fn initial_defaults() -> PluginDefaults {
+    let dir = TempDir::new("profiles");
+    PluginDefaults::load(&dir.path().join("defaults.json"))
+        .await
+        .expect("defaults")
+}
+
+#[tokio::test]
+async fn unassigned_devices_start_without_input_commands_or_files() {
+    let defaults = initial_defaults().await;
+    let configs = defaults.resolve(None, &PluginConfigs::default());
+    for plugin in ["mousepad", "runcommand", "sftp", "share"] {
+        assert_eq!(enabled(&configs, plugin), Some(false), "{plugin}");
+    }
+    assert_eq!(enabled(&configs, "ping"), None);
+}
+
+#[tokio::test]
+async fn trusted_profile_enables_them_again() {
+    let defaults = initial_defaults().await;
+    let configs = defaults.resolve(Some("trusted"), &PluginConfigs::default());
+    for plugin in ["mousepad", "runcommand", "sftp", "share"] {
+        assert_eq!(enabled(&configs, plugin), Some(true), "{plugin}");
+    }
+}
+
+fn identity(device_id: &str) -> IdentityPayloadBody {
+    IdentityPayloadBody {
+        device_name: device_id.to_string(),
+        device_id: device_id.to_string(),
+        device_type: "phone".to_string(),
+        incoming_capabilities: vec![],
+        outgoing_capabilities: vec![],
+        protocol_version: 7,
+        tcp_port: Some(1716),
+    }
+}
+
+#[tokio::test]
+async fn devices_paired_before_profiles_existed_become_trusted() {
+    let dir = TempDir::new("profiles");
+    let mut manager = common::device_manager(&dir).await;
+    for (device_id, paired) in [("paired", true), ("unpaired", false)] {
+        manager
+            .connected_to(
+                "127.0.0.1:1716".parse().expect("address"),
+                identity(device_id),
+                DeviceCapabilities::default(),
+            )
+            .await
+            .expect("connect");
+        manager
+            .devices
+            .get_mut(device_id)
+            .expect("device")
+            .device
+            .paired = paired;
+    }
+    manager.save().await.expect("save");
+    drop(manager);
+    tokio::fs::remove_file(dir.path().join("plugin_defaults"))
+        .await
+        .expect("remove defaults");
+
+    let manager = common::device_manager(&dir).await;
+    let paired = &manager.devices["paired"].device;
+    assert_eq!(paired.profile.as_deref(), Some("trusted"));
+    assert_eq!(enabled(&paired.effective_configs, "share"), Some(true));
+    let unpaired = &manager.devices["unpaired"].device;
+    assert_eq!(unpaired.profile, None);
+    assert_eq!(enabled(&unpaired.effective_configs, "share"), Some(false));
+
+    let reloaded = common::device_manager(&dir).await;
+    assert_eq!(
+        reloaded.devices["paired"].device.profile.as_deref(),
+        Some("trusted")
+    );
+}
+
+#[tokio::test]
+async fn corrupt_defaults_are_moved_aside_and_stay_restrictive() {
+    let dir = TempDir::new("profiles");
+    let path = dir.path().join("defaults.json");
+    tokio::fs::write(&path, b"{not json").await.expect("write");
+
+    let defaults = PluginDefaults::load(&path).await.expect("defaults");
+    let configs = defaults.resolve(None, &PluginConfigs::default());
+    assert_eq!(enabled(&configs, "runcommand"), Some(false));
+    assert_eq!(
+        tokio::fs::read(dir.path().join("defaults.corrupt"))
+            .await
+            .expect("corrupt file"),
+        b"{not json"
+    );
+    assert!(PluginDefaults::load(&path).await.is_ok());
+}
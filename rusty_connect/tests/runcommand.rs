@@ -0,0 +1,60 @@
+use rusty_connect::{
+    payloads::Payload,
+    plugins::{
+        runcommand::{RunCommand, RunCommandConfig},
+        Plugin, PluginAction,
+    },
+};
+use serde_json::json;
+
+use self::common::TempDir;
+
+mod common;
+
+fn config() -> Option<RunCommandConfig> {
+    serde_json::from_value(json!({
+        "enabled": true,
+        "commands": [{"key": "greet", "name": "Greet", "command": "echo hello"}],
+    }))
+    .ok()
+}
+
+fn request() -> Payload {
+    Payload::generate_new("kdeconnect.runcommand.request", json!({"key": "greet"}))
+}
+
+#[tokio::test]
+async fn requested_commands_broadcast_their_output() {
+    let dir = TempDir::new("runcommand");
+    let runcommand = RunCommand::init(&common::device_manager(&dir).await);
+    let mut outputs = runcommand.subscribe_output();
+    let (peer, _) = common::peer(true);
+
+    let parsed = runcommand
+        .parse_payload(&request(), &peer, &config())
+        .await
+        .expect("parsed");
+    let Some(PluginAction::Async(run)) = runcommand.handle(&parsed, &Default::default()) else {
+        panic!("command not run");
+    };
+    run.await;
+
+    let output = outputs.try_recv().expect("output");
+    assert_eq!(output.device_id, "phone");
+    assert_eq!(output.key, "greet");
+    assert_eq!(output.exit_code, Some(0));
+    assert_eq!(output.stdout.trim(), "hello");
+}
+
+#[tokio::test]
+async fn unpaired_devices_cannot_run_commands() {
+    let dir = TempDir::new("runcommand");
+    let runcommand = RunCommand::init(&common::device_manager(&dir).await);
+    let (peer, _) = common::peer(false);
+
+    let parsed = runcommand
+        .parse_payload(&request(), &peer, &config())
+        .await
+        .expect("parsed");
+    assert!(runcommand.handle(&parsed, &Default::default()).is_none());
+}
@@ -0,0 +1,176 @@
+use std::net::SocketAddr;
+
+use rusty_connect::{
+    devices::Peer,
+    payloads::Payload,
+    plugins::{
+        sftp::{Sftp, SftpClient, SftpMount, SftpPayload, SftpState},
+        Plugin,
+    },
+};
+use serde_json::json;
+
+fn peer(address: &str) -> Peer {
+    Peer {
+        device_id: "phone".to_string(),
+        paired: true,
+        address: address.parse::<SocketAddr>().expect("address"),
+        certificate: None,
+        sender: flume::unbounded().0,
+    }
+}
+
+fn sftp() -> Sftp {
+    Sftp {
+        downloads_path: std::env::temp_dir(),
+    }
+}
+
+async fn parse(body: serde_json::Value, peer: &Peer) -> Option<SftpPayload> {
+    let payload = Payload::generate_new("kdeconnect.sftp", body);
+    sftp().parse_payload(&payload, peer, &None).await
+}
+
+#[tokio::test]
+async fn credentials_come_from_the_packet() {
+    let payload = parse(
+        json!({
+            "ip": "192.168.1.20",
+            "port": 1739,
+            "user": "kdeconnect",
+            "password": "secret",
+            "path": "/storage/emulated/0",
+        }),
+        &peer("192.168.1.30:1716"),
+    )
+    .await
+    .expect("sftp payload");
+    let credentials = payload.credentials().expect("credentials");
+    assert_eq!(credentials.host, "192.168.1.20");
+    assert_eq!(credentials.port, 1739);
+    assert_eq!(credentials.password, "secret");
+    assert_eq!(
+        credentials.url(),
+        "sftp://kdeconnect@192.168.1.20:1739/storage/emulated/0"
+    );
+}
+
+#[tokio::test]
+async fn missing_ip_falls_back_to_the_connection_address() {
+    let payload = parse(
+        json!({"port": 1739, "user": "kdeconnect", "password": "secret"}),
+        &peer("[fe80::1]:1716"),
+    )
+    .await
+    .expect("sftp payload");
+    let credentials = payload.credentials().expect("credentials");
+    assert_eq!(credentials.root, "/");
+    assert_eq!(credentials.url(), "sftp://kdeconnect@[fe80::1]:1739/");
+}
+
+#[tokio::test]
+async fn incomplete_credentials_are_rejected() {
+    let payload = parse(json!({"port": 1739}), &peer("192.168.1.30:1716"))
+        .await
+        .expect("sftp payload");
+    assert!(payload.credentials().is_err());
+}
+
+#[tokio::test]
+async fn errors_forget_the_previous_connection() {
+    let peer = peer("192.168.1.30:1716");
+    let mut state = SftpState::default();
+    let started = parse(
+        json!({"port": 1739, "user": "kdeconnect", "password": "secret"}),
+        &peer,
+    )
+    .await
+    .expect("sftp payload");
+    sftp().update_state(&started, &mut state);
+    assert!(state.connection.is_some());
+
+    let failed = parse(json!({"errorMessage": "No storage"}), &peer)
+        .await
+        .expect("sftp payload");
+    sftp().update_state(&failed, &mut state);
+    assert!(state.connection.is_none());
+}
+
+#[test]
+fn mounts_pair_paths_with_their_names() {
+    let payload = serde_json::from_value::<SftpPayload>(json!({
+        "multiPaths": ["/storage/emulated/0", "/storage/1234-5678", "/storage/usb"],
+        "pathNames": ["All files", "SD card"],
+    }))
+    .expect("sftp payload");
+    assert_eq!(
+        payload.mount_points(),
+        vec![
+            SftpMount {
+                name: "All files".to_string(),
+                path: "/storage/emulated/0".to_string(),
+            },
+            SftpMount {
+                name: "SD card".to_string(),
+                path: "/storage/1234-5678".to_string(),
+            },
+            SftpMount {
+                name: "usb".to_string(),
+                path: "/storage/usb".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn single_path_is_the_only_mount() {
+    let payload = serde_json::from_value::<SftpPayload>(json!({"path": "/storage/emulated/0"}))
+        .expect("sftp payload");
+    assert_eq!(
+        payload.mount_points(),
+        vec![SftpMount {
+            name: "0".to_string(),
+            path: "/storage/emulated/0".to_string(),
+        }]
+    );
+}
+
+/// Needs a local sshd with password login, see the env variables below.
+#[tokio::test]
+#[ignore = "needs a local sshd, set SFTP_TEST_USER and SFTP_TEST_PASSWORD"]
+async fn round_trips_a_file_through_a_local_sshd() {
+    let user = std::env::var("SFTP_TEST_USER").expect("SFTP_TEST_USER");
+    let password = std::env::var("SFTP_TEST_PASSWORD").expect("SFTP_TEST_PASSWORD");
+    let port = std::env::var("SFTP_TEST_PORT")
+        .map(|port| port.parse().expect("SFTP_TEST_PORT"))
+        .unwrap_or(22);
+
+    let local = std::env::temp_dir().join(format!("{}.txt", uuid::Uuid::new_v4()));
+    std::fs::write(&local, "hello over sftp").expect("write local file");
+    let remote_dir = std::env::temp_dir().to_string_lossy().to_string();
+    let file_name = format!("{}.txt", uuid::Uuid::new_v4());
+    let remote = format!("{remote_dir}/{file_name}");
+
+    let client = SftpClient::connect("127.0.0.1", port, &user, &password)
+        .await
+        .expect("connect");
+    assert_eq!(client.upload(&local, &remote).await.expect("upload"), 15);
+    let listed = client.list(&remote_dir).await.expect("list");
+    assert!(listed
+        .iter()
+        .any(|entry| entry.name == file_name && !entry.is_dir));
+
+    let downloaded = local.with_extension("download");
+    client
+        .download(&remote, &downloaded)
+        .await
+        .expect("download");
+    assert_eq!(
+        std::fs::read_to_string(&downloaded).expect("read download"),
+        "hello over sftp"
+    );
+    client.delete(&remote).await.expect("delete");
+    client.close().await.expect("close");
+    std::fs::remove_file(local).ok();
+    std::fs::remove_file(downloaded).ok();
+}
@@ -0,0 +1,98 @@
+use rusty_connect::plugins::sms::{store::SmsStore, SmsMessage};
+use serde_json::json;
+
+use self::common::TempDir;
+
+mod common;
+
+fn store() -> (SmsStore, TempDir) {
+    let dir = TempDir::new("sms");
+    (SmsStore::new(dir.path().to_path_buf()), dir)
+}
+
+fn message(id: i64, thread_id: i64, date: i64, body: &str) -> SmsMessage {
+    serde_json::from_value(json!({
+        "_id": id,
+        "thread_id": thread_id,
+        "body": body,
+        "addresses": [{"address": "+44 20 1234"}],
+        "date": date,
+        "type": 1,
+        "read": 1,
+    }))
+    .expect("message")
+}
+
+#[test]
+fn peer_messages_parse_with_missing_optional_fields() {
+    let message: SmsMessage = serde_json::from_value(json!({
+        "_id": 7,
+        "thread_id": 3,
+        "date": 1_700_000_000_000_i64,
+        "sub_id": 1,
+    }))
+    .expect("message");
+    assert_eq!(message.id, 7);
+    assert_eq!(message.thread_id, 3);
+    assert!(message.body.is_empty());
+    assert!(message.addresses.is_empty());
+    assert_eq!(message.sub_id, Some(1));
+}
+
+#[tokio::test]
+async fn merge_keeps_threads_and_replaces_known_messages() {
+    let (store, _dir) = store();
+    store
+        .merge(
+            "phone",
+            &[message(1, 10, 100, "hi"), message(2, 20, 300, "yo")],
+        )
+        .await
+        .expect("merge");
+    store
+        .merge(
+            "phone",
+            &[
+                message(1, 10, 100, "hi, edited"),
+                message(3, 10, 200, "there"),
+            ],
+        )
+        .await
+        .expect("merge");
+
+    let conversations = store.conversations("phone").await.expect("conversations");
+    let threads = conversations
+        .iter()
+        .map(|conversation| (conversation.thread_id, conversation.message_count))
+        .collect::<Vec<_>>();
+    assert_eq!(threads, vec![(20, 1), (10, 2)]);
+    assert_eq!(conversations[1].latest.id, 3);
+
+    let messages = store.messages("phone", 10).await.expect("messages");
+    let bodies = messages
+        .iter()
+        .map(|message| message.body.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(bodies, vec!["hi, edited", "there"]);
+    assert!(store
+        .messages("phone", 99)
+        .await
+        .expect("messages")
+        .is_empty());
+}
+
+#[tokio::test]
+async fn corrupt_cache_is_reported_and_left_untouched() {
+    let (store, _dir) = store();
+    let folder = store.device_folder("phone").expect("folder");
+    tokio::fs::create_dir_all(&folder).await.expect("folder");
+    let file = folder.join("messages.json");
+    tokio::fs::write(&file, b"{not json").await.expect("write");
+
+    assert!(store.conversations("phone").await.is_err());
+    assert!(store
+        .merge("phone", &[message(1, 10, 100, "hi")])
+        .await
+        .is_err());
+    assert_eq!(tokio::fs::read(&file).await.expect("read"), b"{not json");
+}
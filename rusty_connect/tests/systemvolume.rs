@@ -0,0 +1,147 @@
+use std::sync::{Arc, Mutex};
+
+use rusty_connect::{
+    payloads::Payload,
+    plugins::{
+        systemvolume::{
+            backend::MAX_VOLUME, parse_pactl_sinks, sink_updates, AudioBackend, MockAudioBackend,
+            Sink, SystemVolume, SystemVolumeState,
+        },
+        Plugin, PluginAction,
+    },
+};
+use serde_json::json;
+
+mod common;
+
+/// Records the sinks changes are applied to.
+struct RecordingBackend {
+    sinks: MockAudioBackend,
+    changed: Mutex<Vec<String>>,
+}
+
+impl AudioBackend for RecordingBackend {
+    fn sinks(&self) -> anyhow::Result<Vec<Sink>> {
+        self.sinks.sinks()
+    }
+
+    fn set_volume(&self, sink: &str, volume: u32) -> anyhow::Result<()> {
+        self.changed.lock().unwrap().push(sink.to_string());
+        self.sinks.set_volume(sink, volume)
+    }
+
+    fn set_muted(&self, sink: &str, muted: bool) -> anyhow::Result<()> {
+        self.changed.lock().unwrap().push(sink.to_string());
+        self.sinks.set_muted(sink, muted)
+    }
+
+    fn set_default(&self, sink: &str) -> anyhow::Result<()> {
+        self.changed.lock().unwrap().push(sink.to_string());
+        self.sinks.set_default(sink)
+    }
+}
+
+fn sink(name: &str, volume: u32, muted: bool, enabled: bool) -> Sink {
+    Sink {
+        name: name.to_string(),
+        description: name.to_uppercase(),
+        volume,
+        max_volume: MAX_VOLUME,
+        muted,
+        enabled,
+    }
+}
+
+#[test]
+fn pactl_sinks_are_parsed() {
+    let output = json!([
+        {
+            "name": "speakers",
+            "description": "Built-in Speakers",
+            "mute": false,
+            "volume": {
+                "front-left": { "value": 32768, "value_percent": "50%" },
+                "front-right": { "value": 65536, "value_percent": "100%" }
+            }
+        },
+        { "name": "hdmi", "mute": true, "volume": {} }
+    ]);
+    let sinks = parse_pactl_sinks(&output.to_string(), "hdmi").unwrap();
+    assert_eq!(sinks.len(), 2);
+    assert_eq!(sinks[0].description, "Built-in Speakers");
+    assert_eq!(sinks[0].volume, 49152);
+    assert!(!sinks[0].enabled);
+    assert_eq!(sinks[1].description, "hdmi");
+    assert!(sinks[1].muted && sinks[1].enabled);
+}
+
+#[test]
+fn changed_sinks_send_only_what_changed() {
+    let previous = [sink("a", 100, false, true), sink("b", 100, false, false)];
+    let current = [sink("a", 100, true, false), sink("b", 200, false, true)];
+    let updates = sink_updates(&previous, &current)
+        .iter()
+        .map(|payload| serde_json::to_value(payload).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        updates,
+        [
+            json!({ "name": "a", "muted": true, "enabled": false }),
+            json!({ "name": "b", "volume": 200, "enabled": true }),
+        ]
+    );
+    assert!(sink_updates(&current, &current).is_empty());
+}
+
+#[test]
+fn added_sink_resends_the_list() {
+    let previous = [sink("a", 100, false, true)];
+    let current = [sink("a", 100, false, true), sink("b", 100, false, false)];
+    let updates = sink_updates(&previous, &current);
+    assert_eq!(updates.len(), 1);
+    let packet = serde_json::to_value(&updates[0]).unwrap();
+    assert_eq!(packet["sinkList"].as_array().map(Vec::len), Some(2));
+}
+
+#[test]
+fn mock_backend_applies_changes() {
+    let backend = MockAudioBackend::new(vec![
+        sink("a", 100, false, true),
+        sink("b", 100, false, false),
+    ]);
+    backend.set_volume("b", u32::MAX).unwrap();
+    backend.set_default("b").unwrap();
+    assert!(backend.set_muted("missing", true).is_err());
+    let sinks = backend.sinks().unwrap();
+    assert_eq!(sinks[1].volume, MAX_VOLUME);
+    assert!(!sinks[0].enabled && sinks[1].enabled);
+}
+
+#[tokio::test]
+async fn changes_to_unknown_sinks_are_ignored() {
+    let backend = Arc::new(RecordingBackend {
+        sinks: MockAudioBackend::new(vec![sink("a", 100, false, true)]),
+        changed: Mutex::new(vec![]),
+    });
+    let plugin = SystemVolume::new(Some(backend.clone()));
+    let (peer, _) = common::peer(true);
+
+    for name in ["--help", "a"] {
+        let request = Payload::generate_new(
+            "kdeconnect.systemvolume.request",
+            json!({ "name": name, "volume": 200, "muted": true }),
+        );
+        let payload = plugin
+            .parse_payload(&request, &peer, &None)
+            .await
+            .expect("volume request");
+        let Some(PluginAction::Blocking(work)) =
+            plugin.handle(&payload, &SystemVolumeState::default())
+        else {
+            panic!("no blocking work for {name}");
+        };
+        work();
+    }
+    assert_eq!(*backend.changed.lock().unwrap(), ["a", "a"]);
+    assert_eq!(backend.sinks().unwrap(), [sink("a", 200, true, true)]);
+}
@@ -0,0 +1,81 @@
+use rusty_connect::{
+    payloads::Payload,
+    plugins::{
+        telephony::{CallState, Telephony, TelephonyState},
+        Plugin,
+    },
+};
+use serde_json::json;
+
+mod common;
+
+async fn receive(state: &mut TelephonyState, body: serde_json::Value) {
+    let (peer, _) = common::peer(true);
+    let packet = Payload::generate_new("kdeconnect.telephony", body);
+    let parsed = Telephony
+        .parse_payload(&packet, &peer, &None)
+        .await
+        .expect("parsed");
+    Telephony.update_state(&parsed, state);
+}
+
+#[tokio::test]
+async fn calls_move_through_ringing_talking_and_idle() {
+    let mut state = TelephonyState::default();
+    receive(
+        &mut state,
+        json!({"event": "ringing", "contactName": "Ada", "phoneNumber": "+44 20 1234"}),
+    )
+    .await;
+    assert_eq!(state.call_state, CallState::Ringing);
+    let call = state.active_call.as_ref().expect("active call");
+    assert_eq!(call.contact_name.as_deref(), Some("Ada"));
+
+    receive(
+        &mut state,
+        json!({"event": "talking", "contactName": "Ada"}),
+    )
+    .await;
+    assert_eq!(state.call_state, CallState::Talking);
+
+    receive(
+        &mut state,
+        json!({"event": "talking", "contactName": "Ada", "isCancel": true}),
+    )
+    .await;
+    assert_eq!(state.call_state, CallState::Idle);
+    assert!(state.active_call.is_none());
+    assert!(state.missed_calls.is_empty());
+}
+
+#[tokio::test]
+async fn missed_calls_are_kept_newest_first_and_capped() {
+    let mut state = TelephonyState::default();
+    receive(&mut state, json!({"event": "ringing", "phoneNumber": "1"})).await;
+    for number in 0..60 {
+        receive(
+            &mut state,
+            json!({"event": "missedCall", "phoneNumber": number.to_string()}),
+        )
+        .await;
+    }
+    assert_eq!(state.call_state, CallState::Idle);
+    assert!(state.active_call.is_none());
+    assert_eq!(state.missed_calls.len(), 50);
+    assert_eq!(state.missed_calls[0].phone_number.as_deref(), Some("59"));
+}
+
+#[tokio::test]
+async fn unknown_events_change_nothing() {
+    let mut state = TelephonyState::default();
+    receive(&mut state, json!({"event": "ringing", "phoneNumber": "1"})).await;
+    receive(&mut state, json!({"event": "sms", "phoneNumber": "2"})).await;
+    assert_eq!(state.call_state, CallState::Ringing);
+    assert_eq!(
+        state
+            .active_call
+            .as_ref()
+            .and_then(|call| call.phone_number.as_deref()),
+        Some("1")
+    );
+}